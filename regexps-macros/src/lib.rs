@@ -0,0 +1,53 @@
+/*!
+The [`regex!`] macro parses a pattern when your program is compiled, so a syntax error
+in it is a compile error instead of an error returned at run time
+
+Add both `regexps` and `regexps-macros` to your dependencies, the expanded macro refers to `regexps`
+```
+use regexps_macros::regex;
+
+fn is_date(text: &str) -> bool {
+    // Compiled the first time it's used, then shared by all calls
+    let date = regex!("(?<year>....)-(?<month>..)-(?<day>..)");
+    date.find(text).is_some_and(|found| found.as_str() == text)
+}
+
+assert!(is_date("2024-02-29"));
+assert!(!is_date("29 Feb"));
+```
+
+An invalid pattern is reported with the same message [`Regex::new`] returns, carets included
+```compile_fail
+use regexps_macros::regex;
+
+// error: Syntax error at end of pattern: Expected ) after expression
+let unclosed = regex!("(ab");
+```
+
+[`Regex::new`]: https://docs.rs/regexps/latest/regexps/regex/struct.Regex.html#method.new
+*/
+
+use proc_macro::TokenStream;
+use quote::quote;
+use regexps::regex::Regex;
+use syn::{parse_macro_input, LitStr};
+
+// `regex!("pattern")` is a `&'static Regex` of `pattern`, which must be a string literal
+// The pattern is parsed when compiling, the regex is created once when first used
+#[proc_macro]
+pub fn regex(input: TokenStream) -> TokenStream {
+    let pattern = parse_macro_input!(input as LitStr);
+    if let Err(error) = Regex::new(&pattern.value()) {
+        return syn::Error::new(pattern.span(), error.message)
+            .to_compile_error()
+            .into();
+    }
+
+    quote! {{
+        static REGEX: ::std::sync::OnceLock<::regexps::regex::Regex> = ::std::sync::OnceLock::new();
+        REGEX.get_or_init(|| {
+            ::regexps::regex::Regex::new(#pattern).expect("pattern was checked when compiling")
+        })
+    }}
+    .into()
+}
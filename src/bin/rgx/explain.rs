@@ -0,0 +1,53 @@
+// Show how a pattern is parsed: its syntax tree and what it matches in English
+
+use std::io::{self, Write};
+
+use crate::{parse_arguments, EXIT_SELECTED, EXIT_TROUBLE};
+use regexps::parser::Parser;
+
+const USAGE: &str = "\
+Usage: rgx explain PATTERN
+Print the syntax tree of PATTERN then explain what it matches
+
+Options:
+  -h, --help             Print this help
+
+Exit status is 0 if PATTERN is valid, 2 otherwise";
+
+// Run an explanation with command line arguments `args`, return exit status
+pub fn run(args: &[String]) -> u8 {
+    let arguments = match parse_arguments(args, &[("help", 'h')], &[]) {
+        Ok(arguments) => arguments,
+        Err(error) => {
+            eprintln!("rgx explain: {error}\nTry `rgx explain --help` for more information");
+            return EXIT_TROUBLE;
+        }
+    };
+    if let Some((flag, _)) = arguments.options.iter().find(|(flag, _)| *flag != 'h') {
+        eprintln!("rgx explain: unknown option `-{flag}`");
+        return EXIT_TROUBLE;
+    }
+    if !arguments.options.is_empty() {
+        // Ignore failing to print help, like when output is closed early
+        let _ = writeln!(io::stdout(), "{USAGE}");
+        return EXIT_SELECTED;
+    }
+    let pattern = match arguments.operands.as_slice() {
+        [pattern] => pattern,
+        _ => {
+            eprintln!("{USAGE}");
+            return EXIT_TROUBLE;
+        }
+    };
+
+    let regexp = match Parser::parse(pattern) {
+        Ok(regexp) => regexp,
+        Err(error) => {
+            eprintln!("{error}");
+            return EXIT_TROUBLE;
+        }
+    };
+    // Ignore failing to print, like when output is closed early
+    let _ = write!(io::stdout(), "{}\n{}\n", regexp.tree(), regexp.explain());
+    EXIT_SELECTED
+}
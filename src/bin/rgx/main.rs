@@ -0,0 +1,103 @@
+// rgx, search files with regular expressions of this crate
+// Usage: rgx [OPTIONS] PATTERN [PATH]...
+//        rgx s [OPTIONS] PATTERN REPLACEMENT [FILE]...
+//        rgx explain PATTERN
+// Options and exit status follow `grep`, run `rgx --help` or `rgx s --help` for details
+
+// Search files for lines matching a pattern
+mod search;
+
+// Replace matches of a pattern in files
+mod substitute;
+
+// Show how a pattern is parsed
+mod explain;
+
+use std::process::ExitCode;
+
+// Exit status when some line was selected (or some match was replaced)
+const EXIT_SELECTED: u8 = 0;
+// Exit status when no line was selected (or no match was replaced)
+const EXIT_NOT_SELECTED: u8 = 1;
+// Exit status when an error occurred, even if some line was selected
+const EXIT_TROUBLE: u8 = 2;
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let status = match args.first().map(String::as_str) {
+        Some("s") | Some("sub") => substitute::run(&args[1..]),
+        Some("explain") => explain::run(&args[1..]),
+        _ => search::run(&args),
+    };
+    ExitCode::from(status)
+}
+
+// Command line arguments split into options and operands
+struct Arguments {
+    // Short letter of each option given, in order, with its value if it takes one
+    options: Vec<(char, Option<String>)>,
+    // Arguments which are not options, like patterns and paths
+    operands: Vec<String>,
+}
+
+// Split `args` into options and operands
+// `long` gives the short letter of each long option, like ("count", 'c')
+// Options in `with_value` take a value: `-c 3`, `-c3`, `--count 3` or `--count=3`
+// Short options may be grouped, like `-in`, and everything after `--` is an operand
+fn parse_arguments(
+    args: &[String],
+    long: &[(&str, char)],
+    with_value: &[char],
+) -> Result<Arguments, String> {
+    let mut arguments = Arguments {
+        options: vec![],
+        operands: vec![],
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            arguments.operands.extend(args.by_ref().cloned());
+        } else if let Some(name) = arg.strip_prefix("--") {
+            let (name, value) = match name.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (name, None),
+            };
+            let flag = match long.iter().find(|(long_name, _)| *long_name == name) {
+                Some((_, flag)) => *flag,
+                None => return Err(format!("unknown option `--{name}`")),
+            };
+            let value = match (with_value.contains(&flag), value) {
+                (true, Some(value)) => Some(value),
+                (true, None) => match args.next() {
+                    Some(value) => Some(value.clone()),
+                    None => return Err(format!("option `--{name}` requires a value")),
+                },
+                (false, Some(_)) => return Err(format!("option `--{name}` takes no value")),
+                (false, None) => None,
+            };
+            arguments.options.push((flag, value));
+        } else if arg.len() > 1 && arg.starts_with('-') {
+            for (index, flag) in arg.char_indices().skip(1) {
+                if !with_value.contains(&flag) {
+                    arguments.options.push((flag, None));
+                    continue;
+                }
+                // Value is the rest of this argument or the next argument
+                let rest = &arg[index + flag.len_utf8()..];
+                let value = if !rest.is_empty() {
+                    rest.to_string()
+                } else {
+                    match args.next() {
+                        Some(value) => value.clone(),
+                        None => return Err(format!("option `-{flag}` requires a value")),
+                    }
+                };
+                arguments.options.push((flag, Some(value)));
+                break;
+            }
+        } else {
+            arguments.operands.push(arg.clone());
+        }
+    }
+    Ok(arguments)
+}
@@ -0,0 +1,299 @@
+// Search files for lines matching a pattern, like `grep`
+
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use crate::{parse_arguments, EXIT_NOT_SELECTED, EXIT_SELECTED, EXIT_TROUBLE};
+use regexps::regex::{lines::LineSearchBuilder, Regex, RegexBuilder};
+
+const USAGE: &str = "\
+Usage: rgx [OPTIONS] PATTERN [PATH]...
+Search each PATH (standard input if none or -) for lines matching PATTERN
+
+Options:
+  -i, --ignore-case      Match regardless of case
+  -v, --invert-match     Select lines which do not match
+  -c, --count            Print how many lines were selected in each file
+  -o, --only-matching    Print only matched parts of selected lines, one per line
+  -n, --line-number      Print the number of each line before it
+  -r, --recursive        Search directories and everything inside them
+  -h, --help             Print this help
+
+To replace matches instead, see `rgx s --help`
+To show how a pattern is parsed and what it matches, see `rgx explain --help`
+To search for the pattern `s` (or `sub` or `explain`), write `rgx -- s`
+
+Exit status is 0 if a line was selected, 1 if none was, 2 if an error occurred";
+
+// Short letter of each long option
+const LONG_OPTIONS: &[(&str, char)] = &[
+    ("ignore-case", 'i'),
+    ("invert-match", 'v'),
+    ("count", 'c'),
+    ("only-matching", 'o'),
+    ("line-number", 'n'),
+    ("recursive", 'r'),
+    ("help", 'h'),
+];
+
+// Name shown for standard input
+const STDIN_NAME: &str = "(standard input)";
+
+// Options given on the command line
+#[derive(Debug, Default)]
+struct Options {
+    ignore_case: bool,
+    invert: bool,
+    count: bool,
+    only_matching: bool,
+    line_number: bool,
+    recursive: bool,
+    help: bool,
+    pattern: Option<String>,
+    paths: Vec<String>,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Options, String> {
+        let mut options = Options::default();
+        let arguments = parse_arguments(args, LONG_OPTIONS, &[])?;
+        for (flag, _) in arguments.options {
+            options.set(flag)?;
+        }
+
+        let mut operands = arguments.operands.into_iter();
+        options.pattern = operands.next();
+        options.paths = operands.collect();
+        Ok(options)
+    }
+
+    fn set(&mut self, flag: char) -> Result<(), String> {
+        match flag {
+            'i' => self.ignore_case = true,
+            'v' => self.invert = true,
+            'c' => self.count = true,
+            'o' => self.only_matching = true,
+            'n' => self.line_number = true,
+            'r' => self.recursive = true,
+            'h' => self.help = true,
+            _ => return Err(format!("unknown option `-{flag}`")),
+        }
+        Ok(())
+    }
+}
+
+// Run a search with command line arguments `args`, return exit status
+pub fn run(args: &[String]) -> u8 {
+    let mut options = match Options::parse(args) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("rgx: {error}\nTry `rgx --help` for more information");
+            return EXIT_TROUBLE;
+        }
+    };
+    if options.help {
+        // Ignore failing to print help, like when output is closed early
+        let _ = writeln!(io::stdout(), "{USAGE}");
+        return EXIT_SELECTED;
+    }
+    let pattern = match options.pattern.take() {
+        Some(pattern) => pattern,
+        None => {
+            eprintln!("{USAGE}");
+            return EXIT_TROUBLE;
+        }
+    };
+
+    let regex = match RegexBuilder::new()
+        .case_insensitive(options.ignore_case)
+        .build(&pattern)
+    {
+        Ok(regex) => regex,
+        Err(error) => {
+            eprintln!("{error}");
+            return EXIT_TROUBLE;
+        }
+    };
+
+    let mut paths = std::mem::take(&mut options.paths);
+    if paths.is_empty() {
+        // Like grep, search working directory recursively and standard input otherwise
+        paths.push(String::from(if options.recursive { "." } else { "-" }));
+    }
+    let stdout = io::stdout();
+    let mut searcher = Searcher {
+        regex,
+        show_names: paths.len() > 1,
+        options,
+        output: BufWriter::new(stdout.lock()),
+        selected: false,
+        failed: false,
+    };
+
+    let outcome = paths
+        .iter()
+        .try_for_each(|path| searcher.search_path(Path::new(path), true))
+        .and_then(|_| searcher.output.flush());
+    match outcome {
+        Ok(()) => {}
+        // Output was closed early, like piping into `head`
+        Err(error) if error.kind() == io::ErrorKind::BrokenPipe => {}
+        Err(error) => {
+            eprintln!("rgx: write error: {error}");
+            return EXIT_TROUBLE;
+        }
+    }
+
+    if searcher.failed {
+        EXIT_TROUBLE
+    } else if searcher.selected {
+        EXIT_SELECTED
+    } else {
+        EXIT_NOT_SELECTED
+    }
+}
+
+struct Searcher<W: Write> {
+    regex: Regex,
+    options: Options,
+    output: W,
+    // Print the name of file before each line
+    show_names: bool,
+    // True once some line was selected
+    selected: bool,
+    // True once some path could not be searched
+    failed: bool,
+}
+
+impl<W: Write> Searcher<W> {
+    // Report a path which could not be searched
+    fn fail(&mut self, name: &str, error: impl std::fmt::Display) {
+        eprintln!("rgx: {name}: {error}");
+        self.failed = true;
+    }
+
+    // Search a file, standard input (path -) or a directory when searching recursively
+    // Errors reading input are reported and searching goes on
+    // only errors writing output are returned
+    fn search_path(&mut self, path: &Path, given: bool) -> io::Result<()> {
+        let name = path.display().to_string();
+        if given && name == "-" {
+            return self.search_reader(STDIN_NAME, io::stdin().lock());
+        }
+
+        // Follow symbolic links given on the command line but not those found inside directories
+        let metadata = if given {
+            fs::metadata(path)
+        } else {
+            fs::symlink_metadata(path)
+        };
+        let metadata = match metadata {
+            Ok(metadata) => metadata,
+            Err(error) => {
+                self.fail(&name, error);
+                return Ok(());
+            }
+        };
+
+        if metadata.is_dir() {
+            if !self.options.recursive {
+                self.fail(&name, "Is a directory");
+                return Ok(());
+            }
+            // Files found inside directories are always named
+            // files given after the directory are named only if there are many
+            let show_names = std::mem::replace(&mut self.show_names, true);
+            let searched = self.search_directory(path);
+            self.show_names = show_names;
+            return searched;
+        }
+        if !metadata.is_file() && !given {
+            // Symbolic links, sockets and such found inside directories
+            return Ok(());
+        }
+
+        match File::open(path) {
+            Ok(file) => self.search_reader(&name, BufReader::new(file)),
+            Err(error) => {
+                self.fail(&name, error);
+                Ok(())
+            }
+        }
+    }
+
+    // Search everything inside a directory, in order of names
+    fn search_directory(&mut self, path: &Path) -> io::Result<()> {
+        let entries = fs::read_dir(path).and_then(|entries| {
+            entries
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<io::Result<Vec<_>>>()
+        });
+        let mut entries = match entries {
+            Ok(entries) => entries,
+            Err(error) => {
+                self.fail(&path.display().to_string(), error);
+                return Ok(());
+            }
+        };
+        entries.sort();
+        entries
+            .iter()
+            .try_for_each(|entry| self.search_path(entry, false))
+    }
+
+    // Search lines of `reader`, named `name` in output
+    fn search_reader<R: BufRead>(&mut self, name: &str, reader: R) -> io::Result<()> {
+        let mut count = 0;
+        let lines = LineSearchBuilder::new()
+            .invert(self.options.invert)
+            .search(&self.regex, reader);
+        for line in lines {
+            let line = match line {
+                Ok(line) => line,
+                Err(error) => {
+                    self.fail(name, error);
+                    break;
+                }
+            };
+            count += 1;
+            self.selected = true;
+            if self.options.count {
+                continue;
+            }
+
+            if self.options.only_matching {
+                // Inverted search selects lines without matches, nothing to print
+                if self.options.invert {
+                    continue;
+                }
+                for found in self.regex.find_iter(line.as_str()) {
+                    if !found.is_empty() {
+                        self.write_prefix(name, Some(line.number()))?;
+                        writeln!(self.output, "{}", found.as_str())?;
+                    }
+                }
+            } else {
+                self.write_prefix(name, Some(line.number()))?;
+                writeln!(self.output, "{}", line.as_str())?;
+            }
+        }
+
+        if self.options.count {
+            self.write_prefix(name, None)?;
+            writeln!(self.output, "{count}")?;
+        }
+        Ok(())
+    }
+
+    // Write file name and line number (if enabled) before a line
+    fn write_prefix(&mut self, name: &str, line_number: Option<usize>) -> io::Result<()> {
+        if self.show_names {
+            write!(self.output, "{name}:")?;
+        }
+        if let (true, Some(number)) = (self.options.line_number, line_number) {
+            write!(self.output, "{number}:")?;
+        }
+        Ok(())
+    }
+}
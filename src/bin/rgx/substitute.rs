@@ -0,0 +1,365 @@
+// Replace matches of a pattern in files, like `sed s/PATTERN/REPLACEMENT/g`
+// Unlike sed, each file is searched as a whole rather than line by line
+
+use std::fs;
+use std::io::{self, BufWriter, Read, Write};
+use std::ops::Range;
+use std::path::Path;
+
+use crate::{parse_arguments, EXIT_NOT_SELECTED, EXIT_SELECTED, EXIT_TROUBLE};
+use regexps::matcher::template::Template;
+use regexps::regex::{Regex, RegexBuilder};
+
+const USAGE: &str = "\
+Usage: rgx s [OPTIONS] PATTERN REPLACEMENT [FILE]...
+Replace matches of PATTERN in each FILE (standard input if none or -)
+with REPLACEMENT and print the result
+
+Unlike sed, each FILE is searched as a whole and not line by line: a match may span
+many lines, . matches a line break too and \\n in PATTERN matches one
+
+REPLACEMENT may refer to what groups of PATTERN matched: $1 or ${1} for group 1,
+${name} for the group named `name`, $0 for the whole match and $$ for a literal $
+
+Options:
+  -i, --ignore-case      Match regardless of case
+  -c, --count N          Replace at most N matches in each file
+  -w, --in-place         Write results back to each FILE instead of printing them
+  -d, --dry-run          Print a diff of changes instead of applying them
+  -h, --help             Print this help
+
+Exit status is 0 if a match was replaced, 1 if none was, 2 if an error occurred";
+
+// Short letter of each long option
+const LONG_OPTIONS: &[(&str, char)] = &[
+    ("ignore-case", 'i'),
+    ("count", 'c'),
+    ("in-place", 'w'),
+    ("dry-run", 'd'),
+    ("help", 'h'),
+];
+
+// Name shown for standard input
+const STDIN_NAME: &str = "(standard input)";
+
+// Options given on the command line
+#[derive(Debug, Default)]
+struct Options {
+    ignore_case: bool,
+    count: Option<usize>,
+    in_place: bool,
+    dry_run: bool,
+    help: bool,
+    operands: Vec<String>,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Options, String> {
+        let mut options = Options::default();
+        let arguments = parse_arguments(args, LONG_OPTIONS, &['c'])?;
+        for (flag, value) in arguments.options {
+            match flag {
+                'i' => options.ignore_case = true,
+                'c' => {
+                    let value = value.unwrap_or_default();
+                    options.count = match value.parse::<usize>() {
+                        Ok(count) => Some(count),
+                        Err(_) => return Err(format!("invalid count `{value}`")),
+                    };
+                }
+                'w' => options.in_place = true,
+                'd' => options.dry_run = true,
+                'h' => options.help = true,
+                _ => return Err(format!("unknown option `-{flag}`")),
+            }
+        }
+        options.operands = arguments.operands;
+        Ok(options)
+    }
+}
+
+// Run a substitution with command line arguments `args`, return exit status
+pub fn run(args: &[String]) -> u8 {
+    let mut options = match Options::parse(args) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("rgx s: {error}\nTry `rgx s --help` for more information");
+            return EXIT_TROUBLE;
+        }
+    };
+    if options.help {
+        // Ignore failing to print help, like when output is closed early
+        let _ = writeln!(io::stdout(), "{USAGE}");
+        return EXIT_SELECTED;
+    }
+    if options.operands.len() < 2 {
+        eprintln!("{USAGE}");
+        return EXIT_TROUBLE;
+    }
+
+    let mut operands = std::mem::take(&mut options.operands).into_iter();
+    let pattern = operands.next().unwrap();
+    let replacement = operands.next().unwrap();
+    let mut paths = operands.collect::<Vec<_>>();
+    if paths.is_empty() {
+        paths.push(String::from("-"));
+    }
+    if options.in_place && !options.dry_run && paths.iter().any(|path| path == "-") {
+        eprintln!("rgx s: can not edit standard input in place");
+        return EXIT_TROUBLE;
+    }
+
+    let regex = match RegexBuilder::new()
+        .case_insensitive(options.ignore_case)
+        .build(&pattern)
+    {
+        Ok(regex) => regex,
+        Err(error) => {
+            eprintln!("{error}");
+            return EXIT_TROUBLE;
+        }
+    };
+    // Check group references once, before touching any file
+    let template = match Template::new(&replacement, regex.group_info()) {
+        Ok(template) => template,
+        Err(error) => {
+            eprintln!("{error}");
+            return EXIT_TROUBLE;
+        }
+    };
+
+    let stdout = io::stdout();
+    let mut substituter = Substituter {
+        regex,
+        template,
+        options,
+        output: BufWriter::new(stdout.lock()),
+        replaced: false,
+        failed: false,
+    };
+
+    let outcome = paths
+        .iter()
+        .try_for_each(|path| substituter.substitute_path(path))
+        .and_then(|_| substituter.output.flush());
+    match outcome {
+        Ok(()) => {}
+        // Output was closed early, like piping into `head`
+        Err(error) if error.kind() == io::ErrorKind::BrokenPipe => {}
+        Err(error) => {
+            eprintln!("rgx s: write error: {error}");
+            return EXIT_TROUBLE;
+        }
+    }
+
+    if substituter.failed {
+        EXIT_TROUBLE
+    } else if substituter.replaced {
+        EXIT_SELECTED
+    } else {
+        EXIT_NOT_SELECTED
+    }
+}
+
+// A replaced match
+struct Edit {
+    // Character range of the match in the original text
+    range: Range<usize>,
+    // What the match was replaced with
+    replacement: String,
+}
+
+struct Substituter<W: Write> {
+    regex: Regex,
+    template: Template,
+    options: Options,
+    output: W,
+    // True once some match was replaced
+    replaced: bool,
+    // True once some file could not be read or written
+    failed: bool,
+}
+
+impl<W: Write> Substituter<W> {
+    // Report a file which could not be read or written
+    fn fail(&mut self, name: &str, error: impl std::fmt::Display) {
+        eprintln!("rgx s: {name}: {error}");
+        self.failed = true;
+    }
+
+    // Replace matches in a file or standard input (path -)
+    // Errors reading or writing files are reported and substituting goes on
+    // only errors writing output are returned
+    fn substitute_path(&mut self, path: &str) -> io::Result<()> {
+        let (name, text) = if path == "-" {
+            let mut text = String::new();
+            (
+                STDIN_NAME,
+                io::stdin().read_to_string(&mut text).map(|_| text),
+            )
+        } else {
+            (path, fs::read_to_string(path))
+        };
+        let text = match text {
+            Ok(text) => text,
+            Err(error) => {
+                self.fail(name, error);
+                return Ok(());
+            }
+        };
+
+        let (result, edits) = self.substitute(&text);
+        if !edits.is_empty() {
+            self.replaced = true;
+        }
+
+        if self.options.dry_run {
+            let characters = text.chars().collect::<Vec<_>>();
+            write_diff(&mut self.output, name, &characters, &edits)
+        } else if self.options.in_place {
+            if result != text {
+                if let Err(error) = write_in_place(Path::new(path), &result) {
+                    self.fail(name, error);
+                }
+            }
+            Ok(())
+        } else {
+            self.output.write_all(result.as_bytes())
+        }
+    }
+
+    // Copy of `text` with matches replaced, along with each replacement made
+    fn substitute(&self, text: &str) -> (String, Vec<Edit>) {
+        let mut edits = vec![];
+        let mut matcher = self.regex.matcher(text);
+        let result = matcher.replacen(
+            |captures| {
+                let mut replacement = String::new();
+                self.template.expand(captures, &mut replacement);
+                edits.push(Edit {
+                    range: captures.range(),
+                    replacement: replacement.clone(),
+                });
+                replacement.into()
+            },
+            self.options.count.unwrap_or(usize::MAX),
+        );
+        (result, edits)
+    }
+}
+
+// Replace file at `path` with `content`
+// A temporary file is written next to it then renamed over it
+// so the file is never left half written
+fn write_in_place(path: &Path, content: &str) -> io::Result<()> {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temporary = path.with_file_name(format!(".{file_name}.rgx-tmp"));
+    let permissions = fs::metadata(path)?.permissions();
+    let written = fs::write(&temporary, content)
+        .and_then(|_| fs::set_permissions(&temporary, permissions))
+        .and_then(|_| fs::rename(&temporary, path));
+    if written.is_err() {
+        // Do not leave the temporary file behind
+        let _ = fs::remove_file(&temporary);
+    }
+    written
+}
+
+// Write a unified diff (without context lines) of replacing `edits` in `text`
+// Lines touched by the same edits make one hunk
+fn write_diff<W: Write>(
+    output: &mut W,
+    name: &str,
+    text: &[char],
+    edits: &[Edit],
+) -> io::Result<()> {
+    // Character index where each line starts
+    let mut line_starts = vec![];
+    for (index, ch) in text.iter().enumerate() {
+        if index == 0 || text[index - 1] == '\n' {
+            line_starts.push(index);
+        }
+        if *ch == '\n' && index + 1 == text.len() {
+            // An empty line after the last \n, where text can be appended
+            line_starts.push(text.len());
+        }
+    }
+    if text.is_empty() {
+        line_starts.push(0);
+    }
+    // Line containing character `position`
+    let line_of = |position: usize| line_starts.partition_point(|start| *start <= position) - 1;
+    // Character range of lines `first` to `last`
+    let lines_range = |first: usize, last: usize| {
+        let end = line_starts.get(last + 1).copied().unwrap_or(text.len());
+        line_starts[first]..end
+    };
+
+    // Group edits into hunks by the lines they touch
+    let mut hunks: Vec<(Range<usize>, &[Edit])> = vec![];
+    let mut hunk_start = 0;
+    for (index, edit) in edits.iter().enumerate() {
+        let first = line_of(edit.range.start);
+        let last = match edit.range.end.checked_sub(1) {
+            // Replacing a \n joins its line with the next line
+            Some(end) if end >= edit.range.start && text[end] != '\n' => line_of(end),
+            _ => line_of(edit.range.end),
+        };
+        match hunks.last_mut() {
+            Some((lines, hunk_edits)) if first < lines.end => {
+                lines.end = std::cmp::max(lines.end, last + 1);
+                *hunk_edits = &edits[hunk_start..=index];
+            }
+            _ => {
+                hunks.push((first..last + 1, &edits[index..=index]));
+                hunk_start = index;
+            }
+        }
+    }
+
+    let mut header_written = false;
+    // How many more lines new text has than old text, before current hunk
+    let mut lines_delta = 0isize;
+    for (lines, hunk_edits) in hunks {
+        let old_range = lines_range(lines.start, lines.end - 1);
+        let old = text[old_range.clone()].iter().collect::<String>();
+        let mut new = String::new();
+        let mut copied = old_range.start;
+        for edit in hunk_edits {
+            new.extend(&text[copied..edit.range.start]);
+            new.push_str(&edit.replacement);
+            copied = edit.range.end;
+        }
+        new.extend(&text[copied..old_range.end]);
+        if old == new {
+            continue;
+        }
+
+        if !header_written {
+            writeln!(output, "--- {name}\n+++ {name}")?;
+            header_written = true;
+        }
+        let old_lines = old.split_inclusive('\n').collect::<Vec<_>>();
+        let new_lines = new.split_inclusive('\n').collect::<Vec<_>>();
+        // A hunk without lines starts at the line before it
+        let old_start = lines.start + usize::from(!old_lines.is_empty());
+        let new_start =
+            (lines.start as isize + lines_delta) as usize + usize::from(!new_lines.is_empty());
+        writeln!(
+            output,
+            "@@ -{old_start},{} +{new_start},{} @@",
+            old_lines.len(),
+            new_lines.len()
+        )?;
+        for (sign, lines) in [('-', &old_lines), ('+', &new_lines)] {
+            for line in lines {
+                write!(output, "{sign}{line}")?;
+                if !line.ends_with('\n') {
+                    writeln!(output, "\n\\ No newline at end of file")?;
+                }
+            }
+        }
+        lines_delta += new_lines.len() as isize - old_lines.len() as isize;
+    }
+    Ok(())
+}
@@ -48,7 +48,7 @@ Let's take an example, given string `(a|b|c)+`:
 `(` / `a` / `|` / `b` / `|` / `c` / `)` / `+`
 
 - step 2: <code>[Parser]</code> always tries to parse an alternation (like above expression),
-when it fails it returns parsed expression (if any) as a concatenation (such as `abc`)
+  when it fails it returns parsed expression (if any) as a concatenation (such as `abc`)
 
 [Parser]: parser::Parser
 
@@ -58,9 +58,71 @@ In doing so, it attempts to parse **elementary** expressions like
 Once observing the leading `(` it tries to parse a group and again inside
 that group it attempts to parse an alternation following the same manner above
 
-If it succeeded it returns a syntax tree object named <code>[ParsedRegexp]</code>
+If it succeeded it returns a syntax tree object named <code>[SyntaxTree]</code>,
+all of its expressions are stored in one vector and refer to each other by their [NodeId].
+The older linked form <code>[ParsedRegexp]</code> is still available through [`SyntaxTree::to_regexp`]
 
+```
+use regexps::parser::{syntax_tree::ExpressionType, Parser};
+
+let regexp = Parser::parse("ab|c").unwrap();
+let root = &regexp[regexp.root()];
+assert!(matches!(root.expression_type, ExpressionType::Alternation));
+assert_eq!(regexp.pattern(root.children[0]), "ab");
+assert_eq!(regexp.to_regexp().read().unwrap().pattern(), "ab|c");
+```
+
+[SyntaxTree]: parser::syntax_tree::SyntaxTree
+[NodeId]: parser::syntax_tree::NodeId
 [ParsedRegexp]: parser::syntax_tree::ParsedRegexp
+[`SyntaxTree::to_regexp`]: parser::syntax_tree::SyntaxTree::to_regexp
+
+A slash before a metacharacter matches it literally, `\n` `\t` `\r` `\f` `\v` and `\0` stand
+for control characters, `\xHH` for the character with code HH (exactly two hexadecimal digits)
+and `\u{H...}` for the character with code point H... (one to six hexadecimal digits)
+```
+use regexps::regex::Regex;
+
+let regex = Regex::new("\\x41\\u{e9}\\u{1F600}\\.\\t").unwrap();
+assert_eq!(regex.find("xAé😀.\t").unwrap().as_str(), "Aé😀.\t");
+assert_eq!(Regex::new("\\u{10FFFF}").unwrap().find("\u{10FFFF}").unwrap().len(), 4);
+```
+
+An escape sequence which can not be decoded is an [`InvalidEscape`] error
+at the index (in characters) of its slash
+```
+use regexps::parser::{error::SyntaxErrorKind, Parser, ParserBuilder};
+use regexps::scanner::tokens::EscapeError::{self, *};
+
+let error = |parser: &ParserBuilder, pattern: &str| -> (EscapeError, usize) {
+    let error = parser.parse(pattern).unwrap_err();
+    match error.kind {
+        SyntaxErrorKind::InvalidEscape(escape) => (escape, error.position),
+        kind => panic!("{kind:?}"),
+    }
+};
+let lenient = *ParserBuilder::new().strict_escapes(false);
+assert_eq!(error(&lenient, "a\\x4"), (MalformedHexEscape, 1));
+assert_eq!(error(&lenient, "é\\xG1"), (MalformedHexEscape, 1));
+assert_eq!(error(&lenient, "\\u41"), (MalformedUnicodeEscape, 0));
+assert_eq!(error(&lenient, "a|\\u{}"), (MalformedUnicodeEscape, 2));
+assert_eq!(error(&lenient, "\\u{1000000}"), (MalformedUnicodeEscape, 0));
+// Above the last code point
+assert_eq!(error(&lenient, "ab\\u{110000}"), (InvalidCodePoint, 2));
+// Surrogates, which only exist in UTF-16
+assert_eq!(error(&lenient, "(\\u{D800})"), (InvalidCodePoint, 1));
+assert_eq!(error(&lenient, "\\u{dfff}"), (InvalidCodePoint, 0));
+
+// Strict escapes (the default of `ParserBuilder`) also reject a slash escaping nothing
+let strict = ParserBuilder::new();
+assert_eq!(error(&strict, "a\\c"), (UnknownEscape, 1));
+assert_eq!(error(&strict, "ab\\"), (TrailingSlash, 2));
+// which `Parser::parse` reads as a literal slash
+assert!(Parser::parse("a\\c").is_ok() && Parser::parse("ab\\").is_ok());
+```
+
+[`InvalidEscape`]: parser::error::SyntaxErrorKind::InvalidEscape
+[`SyntaxError`]: parser::error::SyntaxError
 
 ------
 
@@ -78,10 +140,354 @@ You can create a <code>[Matcher]</code> using its factory method [`Matcher::new`
 
 [`Matcher::new`]: matcher::Matcher::new
 
+[`Matcher::new`] returns a [`SyntaxError`] when the pattern is invalid. It used to return
+a `String`, which is the error's `message` field and its [`Display`](std::fmt::Display) text,
+and the error still converts into a `String` with `?`
+```
+use regexps::matcher::Matcher;
+use regexps::parser::error::SyntaxErrorKind;
+
+fn count(pattern: &str, target: &str) -> Result<usize, String> {
+    Ok(Matcher::new(pattern, target)?.count())
+}
+
+assert_eq!(count("a+", "aa-a"), Ok(2));
+
+let error = Matcher::new("(a", "").err().unwrap();
+assert_eq!(error.kind, SyntaxErrorKind::ExpectedRightParen);
+assert_eq!(count("(a", ""), Err(error.to_string()));
+```
+
 <code>[Matcher]</code> gives you matching ranges [`std::ops::Range`], in other words it gives you ***start and end index*** of each matching substring in your `target string`
 
 [Matcher]: matcher::Matcher
 
+To match the same pattern against many targets, parse it once into a <code>[Regex]</code>
+using [`Regex::new`] then create a <code>[Matcher]</code> for each target using [`Regex::matcher`]
+
+To get matched text without copying it, use [`Regex::find`], [`Regex::find_at`] or [`Regex::find_iter`]
+which give matches borrowing your `target string`, their positions are byte offsets into it
+```
+use regexps::regex::Regex;
+
+let vowels = Regex::new("(a|é|i|o|u)+").unwrap();
+let words = vowels.find_iter("café au lait").map(|m| m.as_str()).collect::<Vec<_>>();
+assert_eq!(words, vec!["a", "é", "au", "ai"]);
+assert_eq!(vowels.find_at("café au lait", 7).unwrap().range(), 7..8);
+```
+
+To match regardless of case, build the regex with a <code>[RegexBuilder]</code>
+```
+use regexps::regex::RegexBuilder;
+
+let errors = RegexBuilder::new().case_insensitive(true).build("error").unwrap();
+assert_eq!(errors.matcher("Error, ERROR, error").sub("E"), "E, E, E");
+```
+
+To search for a string verbatim, use [`Regex::literal`] which skips the parser altogether
+and finds matches using a fast substring search
+```
+use regexps::regex::Regex;
+
+let needle = Regex::literal("1+1");
+assert_eq!(needle.as_str(), "1\\+1");
+assert_eq!(needle.matcher("1+1=2, 1+1+1=3").sub("two"), "two=2, two+1=3");
+```
+
+To find which of many patterns match a target, put them in a <code>[RegexSet]</code>
+which walks the target once, trying each pattern only where it may match
+```
+use regexps::regex::set::RegexSet;
+
+let alerts = RegexSet::new(["error", "warn(ing)?", "disk (full|failure)"]).unwrap();
+assert_eq!(alerts.matches("warning: disk full"), vec![1, 2]);
+```
+
+Groups `(...)` are numbered from 1 in the order their `(` appear, a group can also be named
+like `(?<year>....)`. Replacement strings given to [`Matcher::sub_template`] can refer to
+what groups matched using `$1`, `${1}` or `${name}` (and `$$` for a literal `$`)
+```
+use regexps::matcher::Matcher;
+
+let mut dates = Matcher::new("(?<day>..)/(?<month>..)/(....)", "Due 31/12/2024").unwrap();
+assert_eq!(dates.sub_template("$3-${month}-${day}").unwrap(), "Due 2024-12-31");
+// Pattern has no group 4
+assert!(dates.sub_template("$4").is_err());
+```
+
+To compute replacements in code, give a closure to [`Matcher::replace_all_with`]
+(or to [`Matcher::replacen`] to replace only the first few matches)
+which returns a [`std::borrow::Cow`], owning the replacement or borrowing it, even from the captures
+```
+use regexps::matcher::Matcher;
+
+let mut words = Matcher::new("(a|b)+", "xaby ba z").unwrap();
+assert_eq!(words.replace_all_with(|m| m.as_string().to_uppercase().into()), "xABy BA z");
+
+let mut pairs = Matcher::new("(?<key>a)=(b)", "a=b, a=b").unwrap();
+let names = pairs.replace_all_with(|m| m.group_info().name(1).unwrap().into());
+assert_eq!(names, "key, key");
+```
+
+To search files too large to hold in memory, give any [`std::io::BufRead`] to [`Regex::stream_matches`]
+which reads it in chunks and reports byte offsets from its start,
+use a <code>[StreamBuilder]</code> to choose how much is read at once and how long matches spanning chunks may be
+```
+use regexps::regex::{stream::StreamBuilder, Regex};
+
+let log = "ok\nERROR disk\nok\nERROR net\n";
+let errors = Regex::new("ERROR (disk|net)").unwrap();
+let found = StreamBuilder::new()
+    .chunk_size(4)
+    .max_look_behind(16)
+    .search(&errors, log.as_bytes())
+    .map(|m| m.unwrap().range())
+    .collect::<Vec<_>>();
+assert_eq!(found, vec![3..13, 17..26]);
+```
+
+To find which lines match, like `grep` does, use [`Regex::search_lines`]
+or a <code>[LineSearchBuilder]</code> to invert the search, stop early or report lines around matching ones
+```
+use regexps::regex::{lines::LineSearchBuilder, Regex};
+
+let log = "boot\nERROR disk\nretry\nok\n";
+let errors = Regex::new("ERROR").unwrap();
+let found = LineSearchBuilder::new()
+    .after_context(1)
+    .search(&errors, log.as_bytes())
+    .map(|line| {
+        let line = line.unwrap();
+        (line.number(), line.offset(), line.as_str().to_string(), line.is_match())
+    })
+    .collect::<Vec<_>>();
+assert_eq!(
+    found,
+    vec![
+        (2, 5, "ERROR disk".to_string(), true),
+        (3, 16, "retry".to_string(), false),
+    ]
+);
+```
+
+To see how a pattern was parsed, print [`SyntaxTree::tree`], and to read what it matches
+in plain English, print [`SyntaxTree::explain`] (also available as `rgx explain PATTERN`)
+```
+use regexps::parser::Parser;
+
+let regexp = Parser::parse("x(a|b)+").unwrap();
+assert_eq!(regexp.explain().to_string(), "'x', then one or more of: ('a' or 'b') [group 1]");
+assert_eq!(
+    regexp.tree(),
+    "Concatenation `x(a|b)+` at 0..7\n\
+     |-- Character 'x' `x` at 0..1\n\
+     `-- Group 1, one or more (+) `(a|b)+` at 1..7\n    \
+         `-- Alternation `a|b` at 2..5\n        \
+             |-- Character 'a' `a` at 2..3\n        \
+             `-- Character 'b' `b` at 4..5\n"
+);
+```
+
+To turn a syntax tree back into a pattern use [`SyntaxTree::to_pattern`], it prints a canonical
+pattern which parses to the same tree
+```
+use regexps::parser::Parser;
+
+let regexp = Parser::parse("(?<first>\\x41|\\n)\\.+").unwrap();
+let pattern = regexp.to_pattern();
+assert_eq!(pattern, "(?<first>A|\\n)\\.+");
+assert!(Parser::parse(&pattern).unwrap().same_structure(&regexp));
+```
+
+To generate a pattern, build it with [combinators] instead of putting strings together,
+characters are always matched literally so nothing needs escaping
+```
+use regexps::parser::combinators::{character, literal, named_group};
+use regexps::parser::Parser;
+use regexps::regex::Regex;
+
+// An http or https address, maybe with www. after ://
+let pattern = named_group("scheme", literal("http").then(character('s').optional()))
+    .then(literal("://"))
+    .then(literal("www.").optional());
+assert_eq!(pattern.to_pattern(), "(?<scheme>https?)://(www\\.)?");
+
+let regexp = pattern.build().unwrap();
+assert_eq!(regexp, Parser::parse("(?<scheme>https?)://(www\\.)?").unwrap());
+let regex = Regex::from_regexp(&regexp);
+let found = regex.find_iter("see https://www.rs or http://a");
+assert_eq!(found.map(|found| found.as_str()).collect::<Vec<_>>(), ["https://www.", "http://"]);
+```
+
+To analyze a syntax tree implement [Visitor] and [`walk`] the tree, and to rewrite it
+implement [Folder] and [`fold`] it into a new tree, which fails if the rewritten
+expressions don't make a valid tree
+```
+use regexps::parser::syntax_tree::*;
+use regexps::parser::visit::{fold, walk, Folder, Visitor, Walk};
+use regexps::parser::Parser;
+
+// Count groups, without looking inside quantified ones
+struct Groups(usize);
+
+impl Visitor for Groups {
+    fn pre(&mut self, tree: &SyntaxTree, id: NodeId) -> Walk {
+        match tree[id].expression_type {
+            ExpressionType::Group { quantifier: Quantifier::None, .. } => {
+                self.0 += 1;
+                Walk::Continue
+            }
+            ExpressionType::Group { .. } => Walk::SkipChildren,
+            _ => Walk::Continue,
+        }
+    }
+}
+
+let regexp = Parser::parse("(a)((b)|(c)*)").unwrap();
+let mut groups = Groups(0);
+walk(&regexp, &mut groups);
+assert_eq!(groups.0, 3);
+
+// Replace each `a` with a `b`
+struct AToB;
+
+impl Folder for AToB {
+    fn fold(
+        &mut self,
+        tree: &SyntaxTree,
+        id: NodeId,
+        children: Vec<NodeId>,
+        builder: &mut TreeBuilder,
+    ) -> NodeId {
+        let mut node = tree[id].clone();
+        if let ExpressionType::CharacterExpression { value, .. } = &mut node.expression_type {
+            if *value == Some('a') {
+                *value = Some('b');
+            }
+        }
+        node.children = children;
+        builder.add(node)
+    }
+}
+
+let rewritten = fold(&Parser::parse("a(ca)+").unwrap(), &mut AToB).unwrap();
+assert_eq!(rewritten.explain().to_string(), "'b', then one or more of: \"cb\" [group 1]");
+
+// Expressions which don't make a valid tree fail to build, like a group without child
+let mut builder = TreeBuilder::new();
+let group = builder.add(Node::new(ExpressionType::Group { quantifier: Quantifier::None, index: 0 }));
+let error = builder.build(group).unwrap_err();
+assert_eq!((error.kind, error.id), (BuildErrorKind::InvalidChildren, group));
+```
+
+To match with a simpler tree, [`optimize`] it: branches written before are removed, common
+starts factored and single character branches merged into character sets matched in one step.
+Groups are kept so captures don't change, unless the [Optimizer] removes them where
+matching doesn't need them
+```
+use regexps::parser::optimize::Optimizer;
+use regexps::parser::syntax_tree::ExpressionType;
+use regexps::parser::Parser;
+
+let regexp = Parser::parse("((a+)*|abc|abd|a)").unwrap();
+assert_eq!(regexp.optimize().to_pattern(), "((a+)*|a(b(c|d)|))");
+
+let dropped = Optimizer::new().keep_groups(false).optimize(&regexp);
+assert_eq!(dropped.to_pattern(), "a*|a(b(c|d)|)");
+
+// `x|y|z` becomes one expression, printed as the alternation it matches like
+let merged = Optimizer::new().keep_groups(false).optimize(&Parser::parse("(x|y|z)+").unwrap());
+let root = &merged[merged.root()];
+assert!(matches!(root.expression_type, ExpressionType::CharacterSet { .. }));
+assert_eq!(merged.to_pattern(), "(x|y|z)+");
+```
+
+Before running patterns written by someone else, check them for shapes which may take the
+backtracking matcher exponential or polynomial time with [`SyntaxTree::backtracking_warnings`]
+```
+use regexps::parser::redos::BacktrackingWarningKind;
+use regexps::parser::Parser;
+
+let warnings = Parser::parse("x(a+)+y|b*b*").unwrap().backtracking_warnings();
+assert_eq!(warnings[0].kind, BacktrackingWarningKind::NestedQuantifiers);
+assert_eq!(warnings[0].span, 1..6);
+assert!(!warnings[1].kind.is_exponential());
+assert_eq!(
+    warnings[1].to_string(),
+    "Warning in position 8: Quantifiers matching the same text one after the other may backtrack polynomially
+x(a+)+y|b*b*
+        ^^^^
+Make one of them unable to match what the other matches, like `a*a*` written `a*`"
+);
+assert!(Parser::parse("(a+b)+").unwrap().backtracking_warnings().is_empty());
+```
+
+Patterns are parsed and compiled within limits on how deeply groups nest, how many expressions
+they have and how much memory they take, which [RegexBuilder] and [ParserBuilder] can change
+```
+use regexps::parser::error::SyntaxErrorKind;
+use regexps::regex::{Regex, RegexBuilder};
+
+let deep = format!("{}a{}", "(".repeat(10_000), ")".repeat(10_000));
+assert_eq!(Regex::new(&deep).unwrap_err().kind, SyntaxErrorKind::NestTooDeep);
+
+let error = RegexBuilder::new().nest_limit(2).build("(a(b(c)))").unwrap_err();
+assert_eq!(error.position, 4);
+
+let error = RegexBuilder::new().size_limit(1024).build(&"a".repeat(100)).unwrap_err();
+assert_eq!(error.kind, SyntaxErrorKind::ProgramTooLarge);
+```
+
+A regex can be saved as bytes with [`Regex::to_bytes`] and loaded back without parsing its pattern.
+The format is versioned and checked on load, see [`serialize`]. With feature `serde`,
+syntax trees can also be serialized with [serde](https://serde.rs), and a [Regex] is serialized
+as its pattern string, so an invalid pattern in a configuration file fails to load with its syntax error
+```
+use regexps::parser::serialize::DecodeErrorKind;
+use regexps::regex::{Regex, RegexBuilder};
+
+let regex = RegexBuilder::new().case_insensitive(true).build("(?<word>err|warn)+").unwrap();
+let bytes = regex.to_bytes();
+
+let loaded = Regex::from_bytes(&bytes).unwrap();
+assert_eq!(loaded.as_str(), "(?<word>err|warn)+");
+assert_eq!(loaded.find("a WARNing").unwrap().as_str(), "WARN");
+
+let error = Regex::from_bytes(&bytes[..bytes.len() - 1]).unwrap_err();
+assert_eq!(error.kind, DecodeErrorKind::UnexpectedEnd);
+```
+
+[`Matcher::sub_template`]: matcher::Matcher::sub_template
+[`Matcher::replace_all_with`]: matcher::Matcher::replace_all_with
+[`Matcher::replacen`]: matcher::Matcher::replacen
+[Regex]: regex::Regex
+[RegexSet]: regex::set::RegexSet
+[RegexBuilder]: regex::RegexBuilder
+[ParserBuilder]: parser::ParserBuilder
+[`Regex::to_bytes`]: regex::Regex::to_bytes
+[`serialize`]: parser::serialize
+[`optimize`]: parser::syntax_tree::SyntaxTree::optimize
+[Optimizer]: parser::optimize::Optimizer
+[`SyntaxTree::backtracking_warnings`]: parser::syntax_tree::SyntaxTree::backtracking_warnings
+[`Regex::new`]: regex::Regex::new
+[`Regex::matcher`]: regex::Regex::matcher
+[`Regex::literal`]: regex::Regex::literal
+[`Regex::find`]: regex::Regex::find
+[`Regex::find_at`]: regex::Regex::find_at
+[`Regex::find_iter`]: regex::Regex::find_iter
+[`Regex::stream_matches`]: regex::Regex::stream_matches
+[StreamBuilder]: regex::stream::StreamBuilder
+[`Regex::search_lines`]: regex::Regex::search_lines
+[LineSearchBuilder]: regex::lines::LineSearchBuilder
+[`SyntaxTree::to_pattern`]: parser::syntax_tree::SyntaxTree::to_pattern
+[combinators]: parser::combinators
+[Visitor]: parser::visit::Visitor
+[`walk`]: parser::visit::walk
+[Folder]: parser::visit::Folder
+[`fold`]: parser::visit::fold
+[`SyntaxTree::tree`]: parser::syntax_tree::SyntaxTree::tree
+[`SyntaxTree::explain`]: parser::syntax_tree::SyntaxTree::explain
+
 ------
 */
 
@@ -98,6 +504,10 @@ pub mod parser;
 // Use a syntax tree to match against strings
 pub mod matcher;
 
+// Regex module
+// Parse a pattern once and create matchers from it
+pub mod regex;
+
 // Format error as follow:
 // First line prints error type, its position and the specific error name
 // Second line prints source string (string given to parser to process)
@@ -142,8 +552,10 @@ pub fn format_error(
     formatted_error.push_str(source);
     formatted_error.push('\n');
 
+    // Column of the next character on the line of carets
+    let mut column = 0;
     for (pos, count) in positions_and_markers_count {
-        for _ in 1..=*pos {
+        for _ in column..*pos {
             // Add spaces to align ^ with error position in source string
             formatted_error.push(' ');
         }
@@ -151,6 +563,7 @@ pub fn format_error(
             // Add as many carets as needed to pinpoint item causing error
             formatted_error.push('^');
         }
+        column = column.max(*pos) + *count as usize;
     }
 
     if !hints.is_empty() {
@@ -0,0 +1,340 @@
+// Aho-Corasick automaton for alternations of plain strings like `error|warn|fatal`
+// Finds which alternative matches without trying them one after another
+
+use std::collections::{HashMap, VecDeque};
+
+use super::{captures::GroupInfo, prefilter::extract_literals};
+use crate::parser::syntax_tree::*;
+
+// Index of the trie root in `AhoCorasick::states`
+const ROOT: usize = 0;
+
+#[derive(Debug, Clone)]
+struct State {
+    // Trie edges, sorted by character for binary search
+    transitions: Vec<(char, usize)>,
+    // Longest proper suffix of this state which is also a state
+    fail: usize,
+    // Smallest index of the strings ending at this state, if any
+    output: Option<usize>,
+    // Nearest state along `fail` links (this one excluded) having an output
+    dictionary_suffix: Option<usize>,
+    // Length of the string spelled from root to this state
+    depth: usize,
+}
+
+impl State {
+    fn new(depth: usize) -> State {
+        State {
+            transitions: vec![],
+            fail: ROOT,
+            output: None,
+            dictionary_suffix: None,
+            depth,
+        }
+    }
+
+    fn next(&self, ch: char) -> Option<usize> {
+        self.transitions
+            .binary_search_by(|(edge, _)| edge.cmp(&ch))
+            .ok()
+            .map(|index| self.transitions[index].1)
+    }
+}
+
+// Matches follow the leftmost-first semantics of alternations:
+// the match starting first wins, and among those starting at the same position
+// the string given first wins, even if a later one is longer
+#[derive(Debug, Clone)]
+pub struct AhoCorasick {
+    states: Vec<State>,
+    // Length of the longest string
+    max_len: usize,
+    // How many strings this automaton was built from
+    strings_count: usize,
+}
+
+impl AhoCorasick {
+    pub fn new(strings: &[Vec<char>]) -> AhoCorasick {
+        let mut states = vec![State::new(0)];
+
+        // Build the trie
+        let mut max_len = 0;
+        for (index, string) in strings.iter().enumerate() {
+            max_len = std::cmp::max(max_len, string.len());
+            let mut current = ROOT;
+            for &ch in string {
+                current = match states[current].next(ch) {
+                    Some(next) => next,
+                    None => {
+                        let next = states.len();
+                        states.push(State::new(states[current].depth + 1));
+                        let transitions = &mut states[current].transitions;
+                        let position = transitions.partition_point(|(edge, _)| *edge < ch);
+                        transitions.insert(position, (ch, next));
+                        next
+                    }
+                };
+            }
+            // Keep the first of duplicate strings
+            if states[current].output.is_none() {
+                states[current].output = Some(index);
+            }
+        }
+
+        // Compute failure links breadth first
+        // so links of shallower states are ready when needed
+        let mut queue = VecDeque::new();
+        for &(_, child) in &states[ROOT].transitions {
+            queue.push_back(child);
+        }
+        while let Some(state) = queue.pop_front() {
+            let transitions = states[state].transitions.clone();
+            for (ch, child) in transitions {
+                queue.push_back(child);
+                // Follow failure links of the parent until one has an edge labeled `ch`
+                let mut fallback = states[state].fail;
+                let fail = loop {
+                    if let Some(next) = states[fallback].next(ch) {
+                        break next;
+                    }
+                    if fallback == ROOT {
+                        break ROOT;
+                    }
+                    fallback = states[fallback].fail;
+                };
+                states[child].fail = fail;
+                states[child].dictionary_suffix = if states[fail].output.is_some() {
+                    Some(fail)
+                } else {
+                    states[fail].dictionary_suffix
+                };
+            }
+        }
+
+        AhoCorasick {
+            states,
+            max_len,
+            strings_count: strings.len(),
+        }
+    }
+
+    // How many strings this automaton was built from
+    pub fn len(&self) -> usize {
+        self.strings_count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings_count == 0
+    }
+
+    // Approximate number of bytes this automaton allocated
+    pub(crate) fn heap_size(&self) -> usize {
+        self.states
+            .iter()
+            .map(|state| {
+                std::mem::size_of::<State>()
+                    + state.transitions.len() * std::mem::size_of::<(char, usize)>()
+            })
+            .sum()
+    }
+
+    // Match one of the strings starting exactly at `start`
+    // Return (string index, match end) of the first matching string
+    pub fn anchored_match(&self, haystack: &[char], start: usize) -> Option<(usize, usize)> {
+        let mut best = self.states[ROOT].output.map(|index| (index, start));
+        let mut current = ROOT;
+        for (position, &ch) in haystack.iter().enumerate().skip(start) {
+            current = match self.states[current].next(ch) {
+                Some(next) => next,
+                None => break,
+            };
+            if let Some(index) = self.states[current].output {
+                if best.is_none_or(|(best_index, _)| index < best_index) {
+                    best = Some((index, position + 1));
+                }
+            }
+        }
+        best
+    }
+
+    // Leftmost-first match of the strings at or after `start`
+    // Return (string index, match start, match end)
+    pub fn find(&self, haystack: &[char], start: usize) -> Option<(usize, usize, usize)> {
+        if start > haystack.len() {
+            return None;
+        }
+        if self.states[ROOT].output.is_some() {
+            // The empty string occurs everywhere
+            return self
+                .anchored_match(haystack, start)
+                .map(|(index, end)| (index, start, end));
+        }
+
+        // Find the leftmost position where some string begins
+        // then let `anchored_match` choose which string wins there
+        let mut leftmost: Option<usize> = None;
+        let mut current = ROOT;
+        for (position, &ch) in haystack.iter().enumerate().skip(start) {
+            if let Some(leftmost) = leftmost {
+                if position - leftmost >= self.max_len {
+                    // No string starting later can begin before `leftmost`
+                    break;
+                }
+            }
+            current = self.step(current, ch);
+
+            // All strings ending at `position`
+            let mut output_state = if self.states[current].output.is_some() {
+                Some(current)
+            } else {
+                self.states[current].dictionary_suffix
+            };
+            while let Some(state) = output_state {
+                let match_start = position + 1 - self.states[state].depth;
+                if leftmost.is_none_or(|leftmost| match_start < leftmost) {
+                    leftmost = Some(match_start);
+                }
+                output_state = self.states[state].dictionary_suffix;
+            }
+        }
+
+        let leftmost = leftmost?;
+        self.anchored_match(haystack, leftmost)
+            .map(|(index, end)| (index, leftmost, end))
+    }
+
+    // All occurrences of the strings at or after `start`, overlapping ones included
+    // in the order they end, found in one pass over `haystack`
+    // Empty strings are left out, and only the first of duplicate strings is reported
+    pub fn occurrences<'a>(&'a self, haystack: &'a [char], start: usize) -> Occurrences<'a> {
+        Occurrences {
+            automaton: self,
+            haystack,
+            position: start,
+            current: ROOT,
+            output_state: None,
+        }
+    }
+
+    // State reached from `state` reading `ch`, following failure links
+    fn step(&self, mut state: usize, ch: char) -> usize {
+        loop {
+            if let Some(next) = self.states[state].next(ch) {
+                return next;
+            }
+            if state == ROOT {
+                return ROOT;
+            }
+            state = self.states[state].fail;
+        }
+    }
+}
+
+// Occurrences of the strings of an automaton, see `AhoCorasick::occurrences`
+// Each one is (string index, occurrence start, occurrence end)
+#[derive(Debug, Clone)]
+pub struct Occurrences<'a> {
+    automaton: &'a AhoCorasick,
+    haystack: &'a [char],
+    // Next haystack position to read
+    position: usize,
+    // State reached after reading haystack up to `position`
+    current: usize,
+    // Next state whose string ends at `position`, not reported yet
+    output_state: Option<usize>,
+}
+
+impl Iterator for Occurrences<'_> {
+    type Item = (usize, usize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let states = &self.automaton.states;
+        loop {
+            if let Some(state) = self.output_state {
+                let state = &states[state];
+                self.output_state = state.dictionary_suffix;
+                match state.output {
+                    Some(index) if state.depth > 0 => {
+                        return Some((index, self.position - state.depth, self.position))
+                    }
+                    _ => continue,
+                }
+            }
+
+            let ch = *self.haystack.get(self.position)?;
+            self.position += 1;
+            self.current = self.automaton.step(self.current, ch);
+            self.output_state = if states[self.current].output.is_some() {
+                Some(self.current)
+            } else {
+                states[self.current].dictionary_suffix
+            };
+        }
+    }
+}
+
+// Automata of all alternations of plain strings within a pattern
+// keyed by index sequence of the alternation in pattern syntax tree
+// (see field `pattern_index_sequence` of `Matcher`)
+#[derive(Debug, Clone, Default)]
+pub struct LiteralAlternations {
+    automata: HashMap<Vec<usize>, AhoCorasick>,
+}
+
+impl LiteralAlternations {
+    // Alternations containing groups are left out
+    // because their branches must be matched one by one to know what each group matched
+    pub fn new(regexp: &SyntaxTree, groups: &GroupInfo) -> LiteralAlternations {
+        let mut alternations = LiteralAlternations::default();
+        // Root expression is the zeroth (first) child in its level
+        alternations.collect(regexp, regexp.root(), groups, &mut vec![0]);
+        alternations
+    }
+
+    fn collect(
+        &mut self,
+        regexp: &SyntaxTree,
+        id: NodeId,
+        groups: &GroupInfo,
+        index_sequence: &mut Vec<usize>,
+    ) {
+        let parsed = &regexp[id];
+        if matches!(parsed.expression_type, ExpressionType::Alternation)
+            && groups.subtree_groups(index_sequence).is_none()
+        {
+            if let Some(alternatives) = extract_literals(regexp, id).alternatives {
+                self.automata
+                    .insert(index_sequence.clone(), AhoCorasick::new(&alternatives));
+                // Children are never matched one by one
+                return;
+            }
+        }
+
+        for (index, child) in parsed.children.iter().enumerate() {
+            index_sequence.push(index);
+            self.collect(regexp, *child, groups, index_sequence);
+            index_sequence.pop();
+        }
+    }
+
+    pub fn get(&self, index_sequence: &[usize]) -> Option<&AhoCorasick> {
+        self.automata.get(index_sequence)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.automata.is_empty()
+    }
+
+    // Approximate number of bytes all automata allocated
+    pub(crate) fn heap_size(&self) -> usize {
+        self.automata
+            .iter()
+            .map(|(index_sequence, automaton)| {
+                std::mem::size_of::<(Vec<usize>, AhoCorasick)>()
+                    + index_sequence.len() * std::mem::size_of::<usize>()
+                    + automaton.heap_size()
+            })
+            .sum()
+    }
+}
@@ -0,0 +1,195 @@
+// Capture groups
+// What each group (...) of a pattern matched within a match
+
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Arc;
+
+use super::Match;
+use crate::parser::syntax_tree::*;
+
+// Numbers and names of all groups of a pattern
+#[derive(Debug, Clone)]
+pub struct GroupInfo {
+    // Name of each group (None if unnamed) indexed by group number
+    // item 0 stands for the whole match, which has no name
+    names: Vec<Option<Arc<str>>>,
+
+    // Numbers of groups found inside each expression having any groups
+    // keyed by index sequence of the expression in pattern syntax tree
+    // (see field `pattern_index_sequence` of `Matcher`)
+    // Groups are numbered in the order their ( appear, so groups
+    // of any expression always have consecutive numbers
+    subtrees: HashMap<Vec<usize>, Range<usize>>,
+}
+
+impl Default for GroupInfo {
+    // Group info of a pattern without groups
+    fn default() -> Self {
+        GroupInfo {
+            names: vec![None],
+            subtrees: HashMap::new(),
+        }
+    }
+}
+
+impl GroupInfo {
+    pub fn new(regexp: &SyntaxTree) -> GroupInfo {
+        let mut info = GroupInfo::default();
+        // Root expression is the zeroth (first) child in its level
+        info.collect(regexp, regexp.root(), &mut vec![0]);
+        info
+    }
+
+    // Record groups of expression `id`, return their numbers
+    fn collect(
+        &mut self,
+        regexp: &SyntaxTree,
+        id: NodeId,
+        index_sequence: &mut Vec<usize>,
+    ) -> Range<usize> {
+        let parsed = &regexp[id];
+        let mut groups = match parsed.expression_type {
+            ExpressionType::Group { index, .. } => {
+                if self.names.len() <= index {
+                    self.names.resize(index + 1, None);
+                }
+                self.names[index] = parsed.group_name.clone();
+                index..index + 1
+            }
+            _ => 0..0,
+        };
+
+        for (index, child) in parsed.children.iter().enumerate() {
+            index_sequence.push(index);
+            let child_groups = self.collect(regexp, *child, index_sequence);
+            index_sequence.pop();
+            if groups.is_empty() {
+                groups = child_groups;
+            } else if !child_groups.is_empty() {
+                groups.end = child_groups.end;
+            }
+        }
+
+        if !groups.is_empty() {
+            self.subtrees.insert(index_sequence.clone(), groups.clone());
+        }
+        groups
+    }
+
+    // How many groups, counting group 0 (the whole match)
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    // Always false, there is at least group 0 (the whole match)
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    // Approximate number of bytes this group info allocated
+    // names are shared with the syntax tree, they are not counted
+    pub(crate) fn heap_size(&self) -> usize {
+        self.names.len() * std::mem::size_of::<Option<Arc<str>>>()
+            + self
+                .subtrees
+                .keys()
+                .map(|index_sequence| {
+                    std::mem::size_of::<(Vec<usize>, Range<usize>)>()
+                        + index_sequence.len() * std::mem::size_of::<usize>()
+                })
+                .sum::<usize>()
+    }
+
+    // Name of group number `index`, if any
+    pub fn name(&self, index: usize) -> Option<&str> {
+        self.names.get(index)?.as_deref()
+    }
+
+    // Number of the group named `name`, if any
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.names
+            .iter()
+            .position(|group_name| group_name.as_deref() == Some(name))
+    }
+
+    // Numbers of groups inside the expression with index sequence `index_sequence`
+    // None if it has no groups
+    pub(crate) fn subtree_groups(&self, index_sequence: &[usize]) -> Option<Range<usize>> {
+        self.subtrees.get(index_sequence).cloned()
+    }
+}
+
+// What each group matched within one match
+#[derive(Debug, Clone)]
+pub struct Captures {
+    // Target the match was found in
+    target: Arc<Vec<char>>,
+    // Range matched by each group, None if the group did not take part in the match
+    // item 0 is the whole match
+    groups: Vec<Option<Match>>,
+    // Numbers and names of groups
+    info: Arc<GroupInfo>,
+}
+
+impl Captures {
+    pub(crate) fn new(
+        target: Arc<Vec<char>>,
+        groups: Vec<Option<Match>>,
+        info: Arc<GroupInfo>,
+    ) -> Captures {
+        Captures {
+            target,
+            groups,
+            info,
+        }
+    }
+
+    // Range matched by group number `index`
+    // group 0 is the whole match
+    pub fn get(&self, index: usize) -> Option<Match> {
+        self.groups.get(index)?.clone()
+    }
+
+    // Range of the whole match
+    pub fn range(&self) -> Match {
+        // Group 0 always takes part in the match
+        self.groups[0].clone().unwrap()
+    }
+
+    // Text of the whole match
+    pub fn as_string(&self) -> String {
+        self.target[self.range()].iter().collect()
+    }
+
+    // Range matched by the group named `name`
+    pub fn name(&self, name: &str) -> Option<Match> {
+        self.get(self.info.index_of(name)?)
+    }
+
+    // Text matched by group number `index`
+    pub fn text(&self, index: usize) -> Option<String> {
+        self.get(index)
+            .map(|range| self.target[range].iter().collect())
+    }
+
+    // How many groups, counting group 0 (the whole match)
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    // Always false, there is at least group 0 (the whole match)
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    // Numbers and names of groups
+    pub fn group_info(&self) -> &GroupInfo {
+        &self.info
+    }
+
+    // Characters of the target the match was found in
+    pub(crate) fn target(&self) -> &[char] {
+        &self.target
+    }
+}
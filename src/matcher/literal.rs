@@ -0,0 +1,166 @@
+// Substring search for patterns matching a string verbatim
+// Uses the Two-Way algorithm (Crochemore and Perrin, 1991)
+// which runs in linear time and constant extra space
+
+#[derive(Debug, Clone)]
+pub struct LiteralSearcher {
+    // The string to search for
+    needle: Vec<char>,
+    // Critical position, the needle is split into two halves
+    // needle[..crit_pos] (left) and needle[crit_pos..] (right)
+    crit_pos: usize,
+    // Period of the needle (or a lower bound of it when `long_period` is true)
+    period: usize,
+    // True if the left half is NOT a suffix of the first period of the needle
+    // in that case the searcher has no memory of previously matched characters
+    long_period: bool,
+}
+
+impl LiteralSearcher {
+    pub fn new(needle: &[char]) -> LiteralSearcher {
+        let needle = needle.to_vec();
+        if needle.is_empty() {
+            return LiteralSearcher {
+                needle,
+                crit_pos: 0,
+                period: 1,
+                long_period: true,
+            };
+        }
+
+        // Critical factorization is the later of the two maximal suffixes
+        // computed for the reverse orders of the alphabet
+        let (crit_pos_less, period_less) = Self::maximal_suffix(&needle, false);
+        let (crit_pos_greater, period_greater) = Self::maximal_suffix(&needle, true);
+        let (crit_pos, period) = if crit_pos_less > crit_pos_greater {
+            (crit_pos_less, period_less)
+        } else {
+            (crit_pos_greater, period_greater)
+        };
+
+        if period + crit_pos <= needle.len()
+            && needle[..crit_pos] == needle[period..period + crit_pos]
+        {
+            // The needle is periodic, remember how much of it matched
+            // to avoid comparing the same characters twice
+            LiteralSearcher {
+                needle,
+                crit_pos,
+                period,
+                long_period: false,
+            }
+        } else {
+            // No usable period, shift by a safe lower bound instead
+            let period = std::cmp::max(crit_pos, needle.len() - crit_pos) + 1;
+            LiteralSearcher {
+                needle,
+                crit_pos,
+                period,
+                long_period: true,
+            }
+        }
+    }
+
+    // Start index and period of the maximal suffix of `needle`
+    // under the usual order of characters (`order_greater` false)
+    // or its reverse (`order_greater` true)
+    fn maximal_suffix(needle: &[char], order_greater: bool) -> (usize, usize) {
+        let mut left = 0; // start of current maximal suffix
+        let mut right = 1; // start of the suffix compared against it
+        let mut offset = 0; // characters matched so far
+        let mut period = 1; // period of current maximal suffix
+
+        while let Some(&a) = needle.get(right + offset) {
+            let b = needle[left + offset];
+            if (a < b && !order_greater) || (a > b && order_greater) {
+                // Suffix at `right` is smaller, jump past the compared region
+                right += offset + 1;
+                offset = 0;
+                period = right - left;
+            } else if a == b {
+                if offset + 1 == period {
+                    // Completed one more period
+                    right += offset + 1;
+                    offset = 0;
+                } else {
+                    offset += 1;
+                }
+            } else {
+                // Suffix at `right` is larger, it becomes the new maximal suffix
+                left = right;
+                right += 1;
+                offset = 0;
+                period = 1;
+            }
+        }
+        (left, period)
+    }
+
+    // Length of the string searched for
+    pub fn len(&self) -> usize {
+        self.needle.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.needle.is_empty()
+    }
+
+    pub fn needle(&self) -> &[char] {
+        &self.needle
+    }
+
+    // Approximate number of bytes this searcher allocated
+    pub(crate) fn heap_size(&self) -> usize {
+        self.needle.len() * std::mem::size_of::<char>()
+    }
+
+    // Index of the first occurrence of the needle in `haystack`
+    // starting at or after index `start`
+    pub fn find(&self, haystack: &[char], start: usize) -> Option<usize> {
+        let needle = &self.needle;
+        let needle_len = needle.len();
+        if start > haystack.len() {
+            return None;
+        }
+        if needle_len == 0 {
+            // The empty string occurs everywhere
+            return Some(start);
+        }
+
+        let mut position = start;
+        // How many characters of the needle are known to match at `position`
+        // always zero when `long_period` is true
+        let mut memory = 0;
+        'search: while position + needle_len <= haystack.len() {
+            // Match right half from left to right
+            let right_start = if self.long_period {
+                self.crit_pos
+            } else {
+                std::cmp::max(self.crit_pos, memory)
+            };
+            for i in right_start..needle_len {
+                if needle[i] != haystack[position + i] {
+                    position += i - self.crit_pos + 1;
+                    memory = 0;
+                    continue 'search;
+                }
+            }
+
+            // Match left half from right to left
+            let left_end = if self.long_period { 0 } else { memory };
+            for i in (left_end..self.crit_pos).rev() {
+                if needle[i] != haystack[position + i] {
+                    position += self.period;
+                    if !self.long_period {
+                        memory = needle_len - self.period;
+                    }
+                    continue 'search;
+                }
+            }
+
+            return Some(position);
+        }
+
+        None
+    }
+}
@@ -1,25 +1,46 @@
 // Use a parsed regular expression to match against strings
 
-use std::sync::{Arc, RwLock};
+// Substring search for literal patterns
+pub mod literal;
 
-use crate::parser::{syntax_tree::*, Parser};
+// Literal analysis to skip positions where no match can start
+pub mod prefilter;
 
-const METACHARACTERS: [char; 7] = ['(', ')', '\\', '|', '*', '.', '?'];
+// Aho-Corasick automata for alternations of plain strings
+pub mod aho_corasick;
+
+// What groups of a pattern matched
+pub mod captures;
+
+// Replacement strings referring to groups, like `$1` or `${name}`
+pub mod template;
+
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use aho_corasick::LiteralAlternations;
+use captures::{Captures, GroupInfo};
+use prefilter::{Prefilter, PrefilterState};
+use template::{Template, TemplateError};
+
+use crate::parser::{error::SyntaxError, syntax_tree::*, Parser};
+use crate::scanner::METACHARACTERS;
 
 pub fn escape(pattern: &str) -> String {
     // Escape all metacharacters in `pattern`
+    // The escaped string is a pattern matching `pattern` literally
+    // even when parsed with strict escapes, because a slash is
+    // only ever placed before a metacharacter
     let mut escaped = String::with_capacity(
         // Possible each character is a metacharacter
-        // requiring two slashes
-        3 * pattern.len(),
+        // requiring a slash
+        2 * pattern.len(),
     );
     for ch in pattern.chars() {
         if METACHARACTERS.contains(&ch) {
             // Add a slash to escaped the metacharacter
-            // You need to write one slash BUT Rust needs you to escape this one slash
-            // so actually we need 2 slashes
-            escaped.push('\\'); // Rust escaping slash
-            escaped.push('\\'); // ParsedRegexp escaping slash
+            // Note that this is one slash character, the scanner receives it as is
+            escaped.push('\\');
         }
         escaped.push(ch);
     }
@@ -27,9 +48,99 @@ pub fn escape(pattern: &str) -> String {
     escaped
 }
 
+// Lowercase form of `ch` used to compare characters regardless of case
+// `ch` itself if it has no lowercase form made of a single character
+fn fold_case(ch: char) -> char {
+    let mut lowercase = ch.to_lowercase();
+    match (lowercase.next(), lowercase.next()) {
+        (Some(folded), None) => folded,
+        _ => ch,
+    }
+}
+
 // Match operation outcome
 pub type Match = std::ops::Range<usize>;
 
+// What a matcher knows about its pattern beyond the syntax tree
+// computed once per pattern and shared by all matchers of that pattern (see `Regex`)
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PatternAnalysis {
+    // Literals required by the pattern, if any
+    pub(crate) prefilter: Option<Arc<Prefilter>>,
+    // Alternations of plain strings within the pattern
+    pub(crate) literal_alternations: Arc<LiteralAlternations>,
+    // Whether each expression can backtrack, indexed by node id
+    // so matching never walks a subexpression to find out
+    pub(crate) backtracking: Arc<Vec<bool>>,
+    // Numbers and names of groups of the pattern
+    pub(crate) groups: Arc<GroupInfo>,
+    // If true, characters match regardless of their case, like `x` matching X
+    pub(crate) case_insensitive: bool,
+}
+
+impl PatternAnalysis {
+    pub(crate) fn new(regexp: &SyntaxTree) -> PatternAnalysis {
+        let mut analysis = PatternAnalysis::case_insensitive(regexp);
+        analysis.prefilter = Prefilter::new(regexp).map(Arc::new);
+        analysis.literal_alternations =
+            Arc::new(LiteralAlternations::new(regexp, &analysis.groups));
+        analysis.case_insensitive = false;
+        analysis
+    }
+
+    // Analysis of a pattern matched regardless of case
+    // Literals are searched for exactly as written, so they are not used
+    pub(crate) fn case_insensitive(regexp: &SyntaxTree) -> PatternAnalysis {
+        PatternAnalysis {
+            prefilter: None,
+            literal_alternations: Arc::default(),
+            backtracking: Arc::new(Self::backtracking(regexp)),
+            groups: Arc::new(GroupInfo::new(regexp)),
+            case_insensitive: true,
+        }
+    }
+
+    // Approximate number of bytes this analysis allocated
+    pub(crate) fn heap_size(&self) -> usize {
+        self.prefilter
+            .as_ref()
+            .map_or(0, |prefilter| prefilter.heap_size())
+            + self.literal_alternations.heap_size()
+            + self.backtracking.len() * std::mem::size_of::<bool>()
+            + self.groups.heap_size()
+    }
+
+    // Whether each expression of `regexp` can backtrack, indexed by node id
+    fn backtracking(regexp: &SyntaxTree) -> Vec<bool> {
+        // An arbitrary expression E supports backtracking if:
+        // 1 - It's quantified, in other words it's succeeded by a quantifier, like `.*`
+        // 2 - At least one of its children supports backtracking, like `(a+|c)` because a+ can backtrack
+        let mut backtracking = vec![false; regexp.len()];
+        // Nodes are in pre-order, so in reverse order
+        // children are always visited before their parent
+        for (index, node) in regexp.nodes().iter().enumerate().rev() {
+            let quantified = match node.expression_type {
+                // The empty expression can match anywhere
+                // It doesn't need backtracking
+                ExpressionType::EmptyExpression => false,
+                // . or x or a group are quantified with one of ? \ * \ +
+                // Variant Quantifier::None represent the idea of `no quantifier`
+                ExpressionType::CharacterExpression { quantifier, .. }
+                | ExpressionType::CharacterSet { quantifier }
+                | ExpressionType::Group { quantifier, .. } => quantifier != Quantifier::None,
+                // Alternation and concatenation are never quantified
+                _ => false,
+            };
+            backtracking[index] = quantified
+                || node
+                    .children
+                    .iter()
+                    .any(|child| backtracking[child.index()]);
+        }
+        backtracking
+    }
+}
+
 #[allow(dead_code)]
 // If an expression E can backtrack (like a+)
 // then each time it successfully matches a range
@@ -68,13 +179,17 @@ enum MatchPhase {
 
 // Coordinator of the matching process
 pub struct Matcher {
-    // Currently processed node of the given pattern syntax tree
-    pattern: Arc<RwLock<ParsedRegexp>>,
+    // Syntax tree of the given pattern
+    regexp: Arc<SyntaxTree>,
+
+    // Currently processed node of field `regexp`
+    pattern: NodeId,
 
     // String on which the search (pattern matching) is done
-    target: Vec<char>,
+    target: Arc<Vec<char>>,
     // Direct indexing, not supported by String, is usually needed
-    // so it's better to use a Vec rather than a String
+    // so it's better to use a slice rather than a String
+    // It's shared so that many matchers can search the same target (see `RegexSet`)
 
     // Current position in target string (Vec field `target`)
     pos: usize,
@@ -108,14 +223,52 @@ pub struct Matcher {
 
     // Target substring containing all matches end index
     matches_substring_end: usize,
+
+    // Analysis of field `pattern`
+    // its prefilter is used to jump to positions where a match may start
+    // instead of trying `compute_match` at every position
+    // and its literal alternations are matched with automata
+    // instead of trying their branches one by one
+    analysis: PatternAnalysis,
+
+    // Progress of prefilter of field `analysis` over current target
+    prefilter_state: PrefilterState,
+
+    // If true, record in field `captures` what each group matched
+    // off by default because it's only needed when groups are requested
+    track_captures: bool,
+
+    // Range matched by each group in ongoing match, indexed by group number
+    captures: Vec<Option<Match>>,
 }
 
 impl Matcher {
     // Create a new matcher from `pattern`
     // which is matched against `target`
-    pub fn new(pattern: &str, target: &str) -> Result<Matcher, String> {
+    // Errors are a `SyntaxError` giving the kind and position of the error, they used to be
+    // a `String`: use `error.message` or `error.to_string()` for the old text, and `?` still
+    // works in functions returning `Result<_, String>`
+    pub fn new(pattern: &str, target: &str) -> Result<Matcher, SyntaxError> {
         let pattern = Parser::parse(pattern)?;
-        let target = target.chars().collect::<Vec<_>>();
+        Ok(Matcher::from_regexp(&pattern, target))
+    }
+
+    // Create a new matcher from an already parsed pattern
+    // like one parsed with `ParserBuilder`
+    pub fn from_regexp(regexp: &Arc<SyntaxTree>, target: &str) -> Matcher {
+        let analysis = PatternAnalysis::new(regexp);
+        Matcher::with_analysis(regexp, Arc::new(target.chars().collect()), analysis)
+    }
+
+    // Create a new matcher using an already computed analysis
+    // `analysis` MUST be computed from `regexp`
+    pub(crate) fn with_analysis(
+        regexp: &Arc<SyntaxTree>,
+        target: Arc<Vec<char>>,
+        analysis: PatternAnalysis,
+    ) -> Matcher {
+        let pattern = regexp.root();
+        let regexp = Arc::clone(regexp);
         let pos = 0;
         let next_match_phase = MatchPhase::Normal;
         let pattern_index_sequence = vec![];
@@ -124,8 +277,12 @@ impl Matcher {
         let match_cache = vec![];
         let matches_substring_start = Option::<usize>::None;
         let matches_substring_end = 0;
+        let prefilter_state = PrefilterState::default();
+        let track_captures = false;
+        let captures = vec![];
 
-        Ok(Matcher {
+        Matcher {
+            regexp,
             pattern,
             target,
             pos,
@@ -136,7 +293,11 @@ impl Matcher {
             match_cache,
             matches_substring_start,
             matches_substring_end,
-        })
+            analysis,
+            prefilter_state,
+            track_captures,
+            captures,
+        }
     }
 
     // Current "normalized" position
@@ -163,25 +324,29 @@ impl Matcher {
 
     // Assign a new target to match on
     pub fn assign_match_target(&mut self, target: &str) {
-        self.target = target.chars().collect();
+        self.target = Arc::new(target.chars().collect());
+        // Old bound may be shorter than new target
+        self.match_bound = self.target.len() + 1;
         self.match_cache.clear();
         self.reset();
     }
 
     // Assign a new pattern to match against
-    pub fn assign_pattern_string(&mut self, pattern: &str) -> Result<(), String> {
-        self.pattern = Parser::parse(pattern)?;
+    pub fn assign_pattern_string(&mut self, pattern: &str) -> Result<(), SyntaxError> {
+        self.regexp = Parser::parse(pattern)?;
+        self.pattern = self.regexp.root();
+        self.analysis = PatternAnalysis::new(&self.regexp);
         self.match_cache.clear();
         self.reset();
         Ok(())
     }
 
     // Assign a new pattern to match against
-    pub fn assign_pattern_regexp(&mut self, regexp: &Arc<RwLock<ParsedRegexp>>) {
-        self.pattern = {
-            let regexp = regexp.read().unwrap();
-            regexp.deep_copy()
-        };
+    pub fn assign_pattern_regexp(&mut self, regexp: &Arc<SyntaxTree>) {
+        // Syntax trees are never modified, no need to copy it
+        self.regexp = Arc::clone(regexp);
+        self.pattern = self.regexp.root();
+        self.analysis = PatternAnalysis::new(&self.regexp);
         self.match_cache.clear();
         self.reset();
     }
@@ -200,80 +365,125 @@ impl Matcher {
         self.pattern_index_sequence.clear();
         // Do not use old backtrack info
         self.backtrack_table.clear();
+        // Position may have moved backwards
+        self.prefilter_state.reset();
     }
 
-    fn supports_backtracking(expr: &Arc<RwLock<ParsedRegexp>>) -> bool {
-        // An arbitrary expression E supports backtracking if:
-        // 1 - It's quantified, in other words it's succeeded by a quantifier, like `.*`
-        // 2 - At least one of its children supports backtracking, like `(a+|c)` because a+ can backtrack
-
-        let parsed_expr = expr.read().unwrap();
-        let expr_type = parsed_expr.expression_type;
-        match expr_type {
-            // The empty expression can match anywhere
-            // It doesn't need backtracking
-            ExpressionType::EmptyExpression => false,
-
-            ExpressionType::CharacterExpression { quantifier, .. } => {
-                // . or x are quantified
+    // Position where next search starts
+    pub(crate) fn position(&self) -> usize {
+        self.pos
+    }
 
-                // It's not the case that this expression has no quantifier
-                // in other words, it's quantified with one of ? \ * \ +
-                !matches!(quantifier, Quantifier::None)
-                // Variant Quantifier::None represent the idea of `no quantifier`
-            }
+    // Attempt to match pattern starting exactly at `position`
+    // without moving on to later positions if that fails
+    pub(crate) fn match_at(&mut self, position: usize) -> Option<Match> {
+        self.set_position(position);
+        // Track root expression
+        self.dive();
+        let match_attempt = self.compute_match();
+        // Remove old backtrack info
+        self.backtrack_table.clear();
+        // Abandon root expression
+        self.bubble_up();
+        match_attempt
+    }
 
-            ExpressionType::Group { quantifier } => {
-                // The group itself is quantified or the grouped expression
-                // inside supports backtracking
+    // First position at or after `position` where a match may start
+    // None if no match can start there or later
+    // Successive calls MUST NOT decrease `position` unless matcher was reset
+    pub(crate) fn next_candidate(&mut self, position: usize) -> Option<usize> {
+        match &self.analysis.prefilter {
+            Some(prefilter) => prefilter
+                .next_candidate(&self.target, position, &mut self.prefilter_state)
+                .map(|candidate| candidate.start),
+            None => (position <= self.target.len()).then_some(position),
+        }
+    }
 
-                // It's not the case that this expression has no quantifier
-                // in other words, it's quantified with one of ? \ * \ +
-                !matches!(quantifier, Quantifier::None)
-                    || Self::supports_backtracking(&parsed_expr.children.read().unwrap()[0])
-                // Variant Quantifier::None represent the idea of `no quantifier`
-            }
+    // Attempt to match pattern starting exactly at `position`
+    // recording what each group matched, indexed by group number
+    // Matcher position is left unchanged
+    fn captures_at(&mut self, position: usize) -> Vec<Option<Match>> {
+        let old_position = self.pos;
+        self.track_captures = true;
+        self.captures = vec![None; self.analysis.groups.len()];
+        let match_attempt = self.match_at(position);
+        self.track_captures = false;
+        self.set_position(old_position);
+
+        let mut captures = std::mem::take(&mut self.captures);
+        captures[0] = match_attempt;
+        captures
+    }
 
-            // Alternation and concatenation
-            _ => {
-                // At least one child supports backtracking
-                parsed_expr
-                    .children
-                    .read()
-                    .unwrap()
-                    .iter()
-                    .any(Self::supports_backtracking)
-            }
-        }
+    // Can currently processed expression backtrack?
+    #[inline(always)]
+    fn current_supports_backtracking(&self) -> bool {
+        self.analysis.backtracking[self.pattern.index()]
     }
 
     // ALL EXPRESSIONS MUST RESTORE OLD POSITION WHEN FAILING TO MATCH
     fn compute_match(&mut self) -> Option<Match> {
-        let parsed_pattern = Arc::clone(&self.pattern);
-        let parsed_pattern = parsed_pattern.read().unwrap();
+        let parsed_pattern = &self.regexp[self.pattern];
         let pattern_type = parsed_pattern.expression_type;
+        let parent = parsed_pattern.parent;
+
+        // Forget what groups inside this expression matched before
+        // but keep a copy to restore if this expression fails to match
+        let capture_groups = if self.track_captures {
+            self.analysis
+                .groups
+                .subtree_groups(&self.pattern_index_sequence)
+        } else {
+            None
+        };
+        let old_captures = capture_groups.clone().map(|groups| {
+            let old_captures = self.captures[groups.clone()].to_vec();
+            self.captures[groups].fill(None);
+            old_captures
+        });
+
+        // Backtracking an expression matches it again from scratch with a smaller bound
+        let backtracking = self
+            .backtrack_table
+            .binary_search_by(|info_entry| {
+                info_entry.index_sequence.cmp(&self.pattern_index_sequence)
+            })
+            .is_ok();
+        if backtracking && !parsed_pattern.children.is_empty() {
+            self.forget_inner_matches();
+        }
 
         let computed_match = match pattern_type {
             ExpressionType::EmptyExpression => self.empty_expression_match(),
 
             ExpressionType::CharacterExpression { value, quantifier } => {
-                self.character_expression_match(value, quantifier)
+                self.character_expression_match(value.is_none(), quantifier)
+            }
+            ExpressionType::CharacterSet { quantifier } => {
+                self.character_expression_match(false, quantifier)
             }
 
-            ExpressionType::Group { quantifier } => self.group_match(quantifier),
+            ExpressionType::Group { quantifier, index } => self.group_match(quantifier, index),
 
             ExpressionType::Alternation => self.alternation_match(),
             ExpressionType::Concatenation => self.concatenation_match(),
         };
 
+        if computed_match.is_none() {
+            if let (Some(groups), Some(old_captures)) = (capture_groups, old_captures) {
+                // Failed expressions capture nothing
+                self.captures[groups].clone_from_slice(&old_captures);
+            }
+        }
+
         // Grouped expressions do not have entries in backtrack table `self.backtrack_table`
         // but they MUST never give back a match whose end index >= match bound of their group parent
         let expression_not_grouped = {
-            match &parsed_pattern.parent {
-                Some(parent_weak_ref) => {
-                    let parent = parent_weak_ref.upgrade().unwrap();
+            match parent {
+                Some(parent) => {
                     let parent_is_a_group = matches!(
-                        parent.read().unwrap().expression_type,
+                        self.regexp[parent].expression_type,
                         ExpressionType::Group { .. }
                     );
                     !parent_is_a_group
@@ -288,18 +498,13 @@ impl Matcher {
         // If current expression successfully matched AND
         // It can backtrack (like .?) AND
         // It's not root expression (it makes no sense to have root expression request a backtrack, it has no siblings)
-        if computed_match.is_some()
-            && Self::supports_backtracking(&self.pattern)
+        if let Some(temp) = computed_match.as_ref().filter(|_| {
             // Root expression does not backtrack
-            && parsed_pattern.parent.is_some()
-            && expression_not_grouped
-        {
+            parent.is_some() && expression_not_grouped && self.current_supports_backtracking()
+        }) {
             // Record first match info for later use when backtracking
 
-            let (start, end) = {
-                let temp = computed_match.as_ref().unwrap();
-                (temp.start, temp.end)
-            };
+            let (start, end) = (temp.start, temp.end);
 
             // Attempt to find current expression info entry
             let search_index = self.backtrack_table.binary_search_by(|info_entry| {
@@ -340,6 +545,16 @@ impl Matcher {
         computed_match
     }
 
+    // Drop entries of expressions inside current expression from `self.backtrack_table`
+    // what they matched before is not theirs to backtrack in a new match of current expression
+    fn forget_inner_matches(&mut self) {
+        let sequence = &self.pattern_index_sequence;
+        self.backtrack_table.retain(|entry| {
+            entry.index_sequence.len() <= sequence.len()
+                || !entry.index_sequence.starts_with(sequence)
+        });
+    }
+
     #[inline(always)]
     fn dive(&mut self) {
         // Begin matching a child of current patttern
@@ -387,6 +602,7 @@ impl Matcher {
     // Also, x is not a metacharacter or it's an escaped metacharacter
     // metacharacters are defined in file `grammar`
     // for instance, k+ is a character expression
+    // Character sets are matched the same way, x being any of their characters
 
     // HOW TO MATCH CHARACTER & DOT EXPRESSIONS?
     // If field `value`, found in field `tag` of this expression, is Option::<char>::None
@@ -404,11 +620,7 @@ impl Matcher {
 
     // Return Option::<std::ops::Range>::Some(...) on success
     // Return Option::<std::ops::Range>::None on failure
-    fn character_expression_match(
-        &mut self,
-        value: Option<char>,
-        quantifier: Quantifier,
-    ) -> Option<Match> {
+    fn character_expression_match(&mut self, dot: bool, quantifier: Quantifier) -> Option<Match> {
         let old_match_bound = self.match_bound;
         self.match_bound = {
             // Find backtrack entry (in self.backtrack_table) of this character/dot expression
@@ -432,7 +644,11 @@ impl Matcher {
         let expr_match = match quantifier {
             Quantifier::None | Quantifier::ZeroOrOne => {
                 // Match `x`\`x?` (value = Some('x')) or `.`\`.?` (value = None)
-                if self.has_next() && (value.is_none() || self.target[self.pos] == value.unwrap()) {
+                // A backtracking `x?` consumes nothing when its bound leaves no room
+                // otherwise it would match the same character again and again
+                let bounded =
+                    matches!(quantifier, Quantifier::ZeroOrOne) && self.pos >= self.match_bound;
+                if !bounded && self.has_next() && self.accepts(self.target[self.pos]) {
                     Option::<Match>::Some(Match {
                         start: self.current(),
                         end: {
@@ -450,18 +666,19 @@ impl Matcher {
             _ => {
                 // Match `x*` \ `x+` (value = Some('x')) or `.*` \ `.+` (value = None)
                 let start = self.current();
-                if value.is_none() {
-                    // Matching `.*` or `.+`
-                    // Just move `self.pos`
-                    self.set_position(self.match_bound.saturating_sub(1));
-                } else {
-                    let value = value.unwrap();
+                if !dot {
                     while let Some(target_char) = self.target.get(self.pos) {
-                        if *target_char != value || self.pos >= self.match_bound {
+                        if !self.accepts(*target_char) || self.pos >= self.match_bound {
                             break;
                         }
                         self.advance();
                     }
+                } else {
+                    // Matching `.*` or `.+`
+                    // Just move `self.pos` as far as `x*` would go
+                    // never backwards, the bound may be behind current position
+                    let end = self.match_bound.min(self.target.len());
+                    self.set_position(end.max(self.pos));
                 }
                 let end = self.current();
 
@@ -481,6 +698,37 @@ impl Matcher {
         expr_match
     }
 
+    // Does `found` (a target character) match the current character, dot or character set?
+    fn accepts(&self, found: char) -> bool {
+        let node = &self.regexp[self.pattern];
+        match node.expression_type {
+            ExpressionType::CharacterExpression { value: None, .. } => true,
+            ExpressionType::CharacterExpression {
+                value: Some(expected),
+                ..
+            } => self.same_character(expected, found),
+            ExpressionType::CharacterSet { .. } => {
+                node.children
+                    .iter()
+                    .any(|child| match self.regexp[*child].expression_type {
+                        ExpressionType::CharacterExpression {
+                            value: Some(expected),
+                            ..
+                        } => self.same_character(expected, found),
+                        _ => false,
+                    })
+            }
+            _ => false,
+        }
+    }
+
+    // Does `found` (a target character) match `expected` (a pattern character)?
+    #[inline(always)]
+    fn same_character(&self, expected: char, found: char) -> bool {
+        expected == found
+            || (self.analysis.case_insensitive && fold_case(expected) == fold_case(found))
+    }
+
     // GROUP/GROUPED EXPRESSIONS:
     // (E) where E is also an expression
     // for instance, (a+|b) is group/grouped expression
@@ -489,30 +737,31 @@ impl Matcher {
     // Match whatever grouped expression matched
     // and then apply the quantifiers after the group itself
 
+    // When tracking captures, the group captures what its grouped expression
+    // matched the last time, nothing if it matched zero times
+
     // Return Option::<std::ops::Range>::Some(...) on success
     // Return Option::<std::ops::Range>::None on failure
-    fn group_match(&mut self, quantifier: Quantifier) -> Option<Match> {
+    fn group_match(&mut self, quantifier: Quantifier, index: usize) -> Option<Match> {
         let old_match_bound = self.match_bound;
-        self.match_bound = {
-            // Find backtrack entry (in self.backtrack_table) of this group expression
-            let table_entry_index = self.backtrack_table.binary_search_by(|info_entry| {
-                info_entry.index_sequence.cmp(&self.pattern_index_sequence)
-            });
-            match table_entry_index {
-                // This expression matched/backtracked before
-                Ok(entry_index) => self.backtrack_table[entry_index]
-                    .last_match_end
-                    .saturating_sub(1),
-                // This expression NEVER matched/backtracked before
-                _ => old_match_bound,
-            }
+        // Find backtrack entry (in self.backtrack_table) of this group expression
+        let table_entry_index = self.backtrack_table.binary_search_by(|info_entry| {
+            info_entry.index_sequence.cmp(&self.pattern_index_sequence)
+        });
+        self.match_bound = match table_entry_index {
+            // This expression matched/backtracked before
+            Ok(entry_index) => self.backtrack_table[entry_index]
+                .last_match_end
+                .saturating_sub(1),
+            // This expression NEVER matched/backtracked before
+            _ => old_match_bound,
         };
 
-        let old_pattern = Arc::clone(&self.pattern);
-        let pattern = Arc::clone(&old_pattern);
-        let pattern = pattern.read().unwrap();
-        let pattern = &pattern.children;
-        self.pattern = Arc::clone(&pattern.read().unwrap()[0]);
+        let old_pattern = self.pattern;
+        self.pattern = self.regexp[old_pattern].children[0];
+
+        // Last range matched by the grouped expression
+        let mut capture = None;
 
         let grouped_expression_mactch = {
             // Start tracking your child
@@ -526,21 +775,39 @@ impl Matcher {
                 Quantifier::None => {
                     // Matching `(E)`
                     // return whatever expression `E` returns
-                    self.compute_match()
+                    let start = self.current();
+                    capture = self.compute_match();
+                    if table_entry_index.is_ok()
+                        && capture.as_ref().is_some_and(|m| m.end > self.match_bound)
+                    {
+                        // Backtracking this group but `E` can't give back a shorter match
+                        // fail and let it start over when matched again
+                        // otherwise it would give back the same match again and again
+                        self.set_position(start);
+                        capture = None;
+                        if let Ok(entry_index) = table_entry_index {
+                            self.backtrack_table[entry_index].backtracked_to_last_match_start =
+                                true;
+                        }
+                    }
+                    capture.clone()
                 }
 
                 Quantifier::ZeroOrOne => {
                     // Matching `(E)?`
+                    let start = self.current();
                     match self.compute_match() {
-                        Some(inner_expression_match) => {
-                            if inner_expression_match.end >= self.match_bound {
-                                // Match bound exceeded/reached, abort
-                                Option::<Match>::None
-                            } else {
-                                Some(inner_expression_match)
-                            }
+                        Some(inner_expression_match)
+                            if inner_expression_match.end <= self.match_bound =>
+                        {
+                            capture = Some(inner_expression_match.clone());
+                            Some(inner_expression_match)
+                        }
+                        _ => {
+                            // `E` failed or exceeded match bound, match zero times instead
+                            self.set_position(start);
+                            self.empty_expression_match()
                         }
-                        None => self.empty_expression_match(),
                     }
                 }
 
@@ -553,13 +820,25 @@ impl Matcher {
 
                     let start = self.current();
                     let mut end = self.current();
+                    // What groups inside matched before current iteration
+                    let mut old_captures = self.track_captures.then(|| self.captures.clone());
                     // Keep matching inner expression unless match bound is exceeded
                     // or the inner expression matched the empty string at least once
-                    while let Some(new_match) = self.compute_match() {
+                    loop {
+                        // Each repetition matches `E` from scratch
+                        self.forget_inner_matches();
+                        let new_match = match self.compute_match() {
+                            Some(new_match) => new_match,
+                            None => break,
+                        };
                         if self.pos > self.match_bound {
                             // Match bound exceeded while matching inner expression
                             // Roll back to end of most recent successful match
                             self.set_position(end);
+                            if let Some(old_captures) = old_captures {
+                                // Forget what groups inside matched in this iteration
+                                self.captures = old_captures;
+                            }
                             break;
                         }
                         if new_match.is_empty() && matched_empty_string {
@@ -575,6 +854,10 @@ impl Matcher {
                         // Update match end index of this group expression
                         end = new_match.end;
                         matched_empty_string = new_match.is_empty();
+                        capture = Some(new_match);
+                        if self.track_captures {
+                            old_captures = Some(self.captures.clone());
+                        }
                     }
 
                     // Matched empty range BUT that empty range is NOT the empty string
@@ -594,6 +877,10 @@ impl Matcher {
             }
         };
 
+        if self.track_captures && grouped_expression_mactch.is_some() {
+            self.captures[index] = capture;
+        }
+
         self.match_bound = old_match_bound;
         // Restore parent pattern to process remaining siblings of current pattern
         self.pattern = old_pattern;
@@ -611,30 +898,73 @@ impl Matcher {
     // Match children in order from first to last
     // return the match of the first matching child
 
+    // An alternation right inside a concatenation (only in optimized trees, the parser
+    // always encloses it in a group) is backtracked like a group without quantifier
+
     // Return Option::<std::ops::Range>::Some(...) on success
     // Return Option::<std::ops::Range>::None on failure
     fn alternation_match(&mut self) -> Option<Match> {
+        let old_match_bound = self.match_bound;
+        // Find backtrack entry (in self.backtrack_table) of this alternation expression
+        let table_entry_index = self.backtrack_table.binary_search_by(|info_entry| {
+            info_entry.index_sequence.cmp(&self.pattern_index_sequence)
+        });
+
+        // All branches are plain strings, let the automaton choose
+        // the branch which would match first if tried one by one
+        let literal_alternations = Arc::clone(&self.analysis.literal_alternations);
+        if let Some(automaton) = literal_alternations.get(&self.pattern_index_sequence) {
+            let bound = match table_entry_index {
+                // Backtracking, give back a shorter match
+                Ok(entry_index) => self.backtrack_table[entry_index]
+                    .last_match_end
+                    .saturating_sub(1),
+                Err(_) => self.match_bound,
+            };
+            let start = self.current();
+            // Branches ending after `bound` never read past it, leave it out
+            let found = match start <= bound {
+                true => {
+                    let target = &self.target[..bound.min(self.target.len())];
+                    automaton.anchored_match(target, start)
+                }
+                false => None,
+            };
+            return match found {
+                Some((_, end)) => {
+                    self.set_position(end);
+                    Some(Match { start, end })
+                }
+                None => {
+                    if let Ok(entry_index) = table_entry_index {
+                        // Let it start over when matched again, like below
+                        self.backtrack_table[entry_index].backtracked_to_last_match_start = true;
+                    }
+                    None
+                }
+            };
+        }
+
+        if let Ok(entry_index) = table_entry_index {
+            // Backtracking, give back a shorter match
+            self.match_bound = self.backtrack_table[entry_index]
+                .last_match_end
+                .saturating_sub(1);
+        }
+
         // Start tracking your children
         self.dive();
 
         let old_position = self.current();
-        let old_pattern = self.pattern.clone();
+        let old_pattern = self.pattern;
 
         let alternation_match = {
-            let children = Arc::clone(&old_pattern);
-            let children = children
-                .read()
-                .unwrap()
-                .children
-                .read()
-                .unwrap()
-                .iter()
-                .map(Arc::clone)
-                .collect::<Vec<_>>();
+            let regexp = Arc::clone(&self.regexp);
+            let children = &regexp[old_pattern].children;
 
             let mut child_match = None;
             for child in children {
-                self.pattern = child;
+                self.pattern = *child;
                 child_match = self.compute_match();
                 if child_match.is_none() {
                     // Return to original position this alternation expression started at
@@ -663,6 +993,20 @@ impl Matcher {
         // Abandon your children
         self.bubble_up();
 
+        let alternation_match = match alternation_match {
+            Some(found) if table_entry_index.is_ok() && found.end > self.match_bound => {
+                // Backtracking this alternation but no branch gives back a shorter match
+                // fail and let it start over when matched again, see `group_match`
+                self.set_position(old_position);
+                if let Ok(entry_index) = table_entry_index {
+                    self.backtrack_table[entry_index].backtracked_to_last_match_start = true;
+                }
+                None
+            }
+            found => found,
+        };
+        self.match_bound = old_match_bound;
+
         alternation_match
     }
 
@@ -702,17 +1046,16 @@ impl Matcher {
         self.dive();
 
         let old_position = self.current();
-        let old_pattern = self.pattern.clone();
+        let old_pattern = self.pattern;
 
         let concatenation_match = {
-            let children = Arc::clone(&old_pattern);
-            let children = children.read().unwrap();
-            let children = children.children.read().unwrap();
-            let mut children = children
+            let regexp = Arc::clone(&self.regexp);
+            let mut children = regexp[old_pattern]
+                .children
                 .iter()
-                .map(|arc| {
+                .map(|child| {
                     // (expression, backtrack table (self.backtrack_table) associated entry index)
-                    (arc, Option::<usize>::None)
+                    (*child, Option::<usize>::None)
                 })
                 .collect::<Vec<_>>();
 
@@ -720,9 +1063,22 @@ impl Matcher {
             let mut child_index = 0usize;
 
             while child_index < children.len() {
+                // Entries of expressions inside the children are inserted in between
+                // so recorded entry indices of children may have moved, find them again
+                let mut child_sequence = self.pattern_index_sequence.clone();
+                for (idx, (_, table_entry)) in children.iter_mut().enumerate() {
+                    if table_entry.is_some() {
+                        *child_sequence.last_mut().unwrap() = idx;
+                        *table_entry = self
+                            .backtrack_table
+                            .binary_search_by(|item| item.index_sequence.cmp(&child_sequence))
+                            .ok();
+                    }
+                }
+
                 let (child, table_info_pos) = {
                     let child_entry = &mut children[child_index];
-                    (child_entry.0.clone(), child_entry.1)
+                    (child_entry.0, child_entry.1)
                 };
 
                 // First preceeding sibling which can backtrack
@@ -741,7 +1097,7 @@ impl Matcher {
                     .map(|(idx, (_, table_entry))| (idx, table_entry))
                     .next_back();
 
-                self.pattern = child.clone();
+                self.pattern = child;
                 if let Some(table_pos) = table_info_pos {
                     // Rust won't allow (self.current()) after (&mut self.backtrack_table)
                     let cur = self.current();
@@ -759,7 +1115,11 @@ impl Matcher {
                 }
 
                 // Attempt to match current child
-                match self.compute_match() {
+                // a child ending after match bound fails, what follows only ends further
+                match self
+                    .compute_match()
+                    .filter(|child_match| child_match.end <= self.match_bound)
+                {
                     Some(child_match) => {
                         // Child match succeeded
 
@@ -768,8 +1128,7 @@ impl Matcher {
 
                         // Store backtrack info if this child supports backtracking
                         let table_entry_index = &mut children[child_index].1;
-                        if table_entry_index.is_none() && Self::supports_backtracking(&self.pattern)
-                        {
+                        if table_entry_index.is_none() && self.current_supports_backtracking() {
                             // Store backtrack info entry index of this expression
                             let table_pos = self
                                 .backtrack_table
@@ -831,7 +1190,7 @@ impl Matcher {
             })
         };
 
-        self.pattern = old_pattern.clone();
+        self.pattern = old_pattern;
         // Abandon your children
         self.bubble_up();
 
@@ -887,8 +1246,35 @@ impl Iterator for Matcher {
         // and hence we need to keep matching until we hit the
         // first successful match or reach end of target
         let mut match_attempt;
+        let prefilter = self.analysis.prefilter.clone();
         loop {
-            match_attempt = self.compute_match();
+            match_attempt = match &prefilter {
+                // Pattern requires some literals, no need to try every position
+                // jump straight to next position where a match may start
+                Some(prefilter) => match prefilter.next_candidate(
+                    &self.target,
+                    self.current(),
+                    &mut self.prefilter_state,
+                ) {
+                    Some(candidate) if prefilter.is_complete() => {
+                        // Pattern is a plain string (or an alternation of them)
+                        // the candidate is a match
+                        self.set_position(candidate.end);
+                        Some(candidate)
+                    }
+                    Some(candidate) => {
+                        self.set_position(candidate.start);
+                        self.compute_match()
+                    }
+                    None => {
+                        // No match starts after current position
+                        // pretend all positions were tried
+                        self.set_position(self.target.len());
+                        None
+                    }
+                },
+                None => self.compute_match(),
+            };
             // Remove old backtrack info
             self.backtrack_table.clear();
             if match_attempt.is_none() {
@@ -996,6 +1382,87 @@ impl Matcher {
         self.splitn(self.target.len() + 1)
     }
 
+    // Find the next match, like `next`, along with what each group matched
+    pub fn captures(&mut self) -> Option<Captures> {
+        let next_match = self.next()?;
+        let mut groups = if self.analysis.groups.len() > 1 {
+            self.captures_at(next_match.start)
+        } else {
+            // Pattern has no groups, no need to match again
+            vec![None]
+        };
+        // The whole match is what `next` found
+        groups[0] = Some(next_match);
+        Some(Captures::new(
+            Arc::clone(&self.target),
+            groups,
+            Arc::clone(&self.analysis.groups),
+        ))
+    }
+
+    // Numbers and names of groups of pattern
+    pub fn group_info(&self) -> &GroupInfo {
+        &self.analysis.groups
+    }
+
+    // Return copy of target with `subs_count` substitutions replacing
+    // each match with `repl` expanded, like `$1` or `${name}` (see `Template`)
+    // Fail before any search if `repl` refers to a group pattern does not have
+    pub fn subn_template(
+        &mut self,
+        repl: &str,
+        subs_count: usize,
+    ) -> Result<String, TemplateError> {
+        let template = Template::new(repl, &self.analysis.groups)?;
+        Ok(self.replacen(
+            |captures| {
+                let mut expanded = String::new();
+                template.expand(captures, &mut expanded);
+                expanded.into()
+            },
+            subs_count,
+        ))
+    }
+
+    // Return copy of target with each match replaced with `repl` expanded
+    pub fn sub_template(&mut self, repl: &str) -> Result<String, TemplateError> {
+        self.subn_template(repl, self.target.len() + 1)
+    }
+
+    // Return copy of target with `subs_count` substitutions replacing
+    // each match with whatever `replacer` computes for it
+    // `replacer` receives the match range and text along with what each group matched
+    // and returns a `String` or a `&str`, which may borrow from the captures it was given
+    pub fn replacen<F>(&mut self, mut replacer: F, mut subs_count: usize) -> String
+    where
+        F: for<'c> FnMut(&'c Captures) -> Cow<'c, str>,
+    {
+        let mut result = String::with_capacity(self.target.len());
+        let mut split_start = 0;
+        while subs_count > 0 {
+            let captures = match self.captures() {
+                Some(captures) => captures,
+                None => break,
+            };
+            let whole_match = captures.range();
+            result.extend(&self.target[split_start..whole_match.start]);
+            result.push_str(&replacer(&captures));
+            split_start = whole_match.end;
+            subs_count -= 1;
+        }
+        result.extend(&self.target[split_start..]);
+
+        result
+    }
+
+    // Return copy of target with each match replaced with whatever `replacer` computes for it
+    pub fn replace_all_with<F>(&mut self, replacer: F) -> String
+    where
+        F: for<'c> FnMut(&'c Captures) -> Cow<'c, str>,
+    {
+        self.replacen(replacer, self.target.len() + 1)
+    }
+
     // Return copy of target with `subs_count` substitutions replacing
     // each match with `repl`
     pub fn subn(&mut self, repl: &str, mut subs_count: usize) -> String {
@@ -0,0 +1,406 @@
+// Literal analysis of parsed patterns
+// Find strings every match of a pattern must contain
+// and use them to skip target positions where no match can start
+
+use std::collections::HashSet;
+
+use super::{aho_corasick::AhoCorasick, literal::LiteralSearcher, Match};
+use crate::parser::syntax_tree::*;
+
+// Literals required by all matches of a pattern
+#[derive(Debug, Clone, PartialEq)]
+pub struct Literals {
+    // If Some, the pattern matches exactly this string and nothing else
+    pub exact: Option<Vec<char>>,
+    // If Some, the pattern matches exactly one of these strings and nothing else
+    // trying them in order, like alternation `error|warn|fatal`
+    pub alternatives: Option<Vec<Vec<char>>>,
+    // Every match starts with this string
+    pub prefix: Vec<char>,
+    // Every match ends with this string
+    pub suffix: Vec<char>,
+    // Every match contains this string (longest one found)
+    pub inner: Vec<char>,
+}
+
+impl Literals {
+    // Literals of a pattern matching exactly `string`
+    pub fn exact(string: Vec<char>) -> Literals {
+        Literals {
+            prefix: string.clone(),
+            suffix: string.clone(),
+            inner: string.clone(),
+            alternatives: Some(vec![string.clone()]),
+            exact: Some(string),
+        }
+    }
+
+    // Literals of a pattern which may match anything
+    pub fn none() -> Literals {
+        Literals {
+            exact: None,
+            alternatives: None,
+            prefix: vec![],
+            suffix: vec![],
+            inner: vec![],
+        }
+    }
+
+    // Forget the exact string (if any) but keep what all matches share
+    // used for repetitions like `(abc)+`
+    fn inexact(self) -> Literals {
+        Literals {
+            exact: None,
+            alternatives: None,
+            ..self
+        }
+    }
+}
+
+// Extract literals required by all matches of expression `id` of `regexp`
+pub fn extract_literals(regexp: &SyntaxTree, id: NodeId) -> Literals {
+    let node = &regexp[id];
+    match node.expression_type {
+        ExpressionType::EmptyExpression => Literals::exact(vec![]),
+
+        ExpressionType::CharacterExpression { value, quantifier } => match (value, quantifier) {
+            // `x`
+            (Some(value), Quantifier::None) => Literals::exact(vec![value]),
+            // `x+` contains at least one x
+            (Some(value), Quantifier::OneOrMore) => Literals::exact(vec![value]).inexact(),
+            // `.` or x? or x*
+            _ => Literals::none(),
+        },
+
+        ExpressionType::Group { quantifier, .. } => {
+            let grouped = extract_literals(regexp, node.children[0]);
+            match quantifier {
+                // (E) matches whatever E matches
+                Quantifier::None => grouped,
+                // (E)+ matches E at least once
+                Quantifier::OneOrMore => grouped.inexact(),
+                // (E)? and (E)* may match the empty string
+                _ => Literals::none(),
+            }
+        }
+
+        ExpressionType::Concatenation => {
+            let children = node
+                .children
+                .iter()
+                .map(|child| extract_literals(regexp, *child))
+                .collect::<Vec<_>>();
+            concatenation_literals(&children)
+        }
+
+        ExpressionType::Alternation => {
+            let children = node
+                .children
+                .iter()
+                .map(|child| extract_literals(regexp, *child))
+                .collect::<Vec<_>>();
+            alternation_literals(&children)
+        }
+
+        ExpressionType::CharacterSet { quantifier } => {
+            // Like an alternation of its characters
+            let characters = node
+                .children
+                .iter()
+                .map(|child| extract_literals(regexp, *child))
+                .collect::<Vec<_>>();
+            match quantifier {
+                Quantifier::None => alternation_literals(&characters),
+                Quantifier::OneOrMore => alternation_literals(&characters).inexact(),
+                _ => Literals::none(),
+            }
+        }
+    }
+}
+
+fn concatenation_literals(children: &[Literals]) -> Literals {
+    // Prefix: exact strings of leading children followed by
+    // the prefix of the first child which is not exact
+    let mut prefix = vec![];
+    let mut all_exact = true;
+    for child in children {
+        match &child.exact {
+            Some(string) => prefix.extend_from_slice(string),
+            None => {
+                prefix.extend_from_slice(&child.prefix);
+                all_exact = false;
+                break;
+            }
+        }
+    }
+    if all_exact {
+        return Literals::exact(prefix);
+    }
+
+    // Suffix: same as prefix but from last child backwards
+    let mut suffix = vec![];
+    for child in children.iter().rev() {
+        let (string, inexact) = match &child.exact {
+            Some(string) => (string, false),
+            None => (&child.suffix, true),
+        };
+        suffix.splice(0..0, string.iter().copied());
+        if inexact {
+            break;
+        }
+    }
+
+    // Inner: longest run of adjacent required strings
+    // A run begins with the suffix of some inexact child, continues through exact children
+    // and ends with the prefix of the next inexact child
+    let mut inner: Vec<char> = vec![];
+    let mut run: Vec<char> = vec![];
+    for child in children {
+        match &child.exact {
+            Some(string) => run.extend_from_slice(string),
+            None => {
+                run.extend_from_slice(&child.prefix);
+                for candidate in [&run, &child.inner] {
+                    if candidate.len() > inner.len() {
+                        inner = candidate.clone();
+                    }
+                }
+                run = child.suffix.clone();
+            }
+        }
+    }
+    if run.len() > inner.len() {
+        inner = run;
+    }
+
+    Literals {
+        exact: None,
+        alternatives: None,
+        prefix,
+        suffix,
+        inner,
+    }
+}
+
+fn alternation_literals(children: &[Literals]) -> Literals {
+    let first = match children.first() {
+        Some(first) => first,
+        None => return Literals::none(),
+    };
+
+    // Same exact string in all branches, like `a|a`
+    if first.exact.is_some() && children.iter().all(|child| child.exact == first.exact) {
+        return first.clone();
+    }
+
+    // Longest common prefix and suffix of all branches
+    let mut prefix = first.prefix.clone();
+    let mut suffix = first.suffix.clone();
+    for child in &children[1..] {
+        let common = prefix
+            .iter()
+            .zip(child.prefix.iter())
+            .take_while(|(a, b)| a == b)
+            .count();
+        prefix.truncate(common);
+
+        let common = suffix
+            .iter()
+            .rev()
+            .zip(child.suffix.iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        suffix.drain(..suffix.len() - common);
+    }
+
+    // The longer of prefix and suffix is contained in all matches
+    let inner = if prefix.len() >= suffix.len() {
+        prefix.clone()
+    } else {
+        suffix.clone()
+    };
+
+    // All branches match plain strings, like `error|warn|(fatal|panic)`
+    // keep the order in which the branches are tried
+    let alternatives = children
+        .iter()
+        .map(|child| child.alternatives.as_ref())
+        .collect::<Option<Vec<_>>>()
+        .map(|branches| {
+            // A repeated string is never chosen again
+            let mut seen = HashSet::new();
+            branches
+                .into_iter()
+                .flatten()
+                .filter(|string| seen.insert(*string))
+                .cloned()
+                .collect()
+        });
+
+    Literals {
+        exact: None,
+        alternatives,
+        prefix,
+        suffix,
+        inner,
+    }
+}
+
+// How a prefilter finds candidate positions
+#[derive(Debug, Clone)]
+enum Searcher {
+    // Every match starts with this string
+    Prefix(LiteralSearcher),
+    // Every match is one of these strings, the automaton finds them
+    Alternatives(AhoCorasick, Vec<Vec<char>>),
+}
+
+// Skip target positions where a pattern can not match
+#[derive(Debug, Clone)]
+pub struct Prefilter {
+    searcher: Searcher,
+    // Every match contains this string
+    // None if it's empty or already covered by `searcher`
+    required: Option<LiteralSearcher>,
+    // True if pattern matches exactly what `searcher` finds and nothing else
+    // so a candidate is always a match
+    complete: bool,
+}
+
+// Progress of a prefilter over one target
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PrefilterState {
+    // Position of first occurrence of the required literal
+    // found by the most recent search, if any
+    required_at: Option<usize>,
+}
+
+impl PrefilterState {
+    // Forget everything, call when position in target moves backwards
+    // or target changes
+    pub fn reset(&mut self) {
+        self.required_at = None;
+    }
+}
+
+impl Prefilter {
+    // Analyze `regexp`, return None if it has no literals worth searching for
+    pub fn new(regexp: &SyntaxTree) -> Option<Prefilter> {
+        Self::from_literals(extract_literals(regexp, regexp.root()))
+    }
+
+    pub fn from_literals(literals: Literals) -> Option<Prefilter> {
+        if let Some(exact) = &literals.exact {
+            // The whole pattern is a string, no matching engine needed
+            return Some(Prefilter {
+                searcher: Searcher::Prefix(LiteralSearcher::new(exact)),
+                required: None,
+                complete: true,
+            });
+        }
+
+        if let Some(alternatives) = &literals.alternatives {
+            // The whole pattern is an alternation of strings
+            // an Aho-Corasick automaton finds which one matches first
+            return Some(Prefilter {
+                searcher: Searcher::Alternatives(
+                    AhoCorasick::new(alternatives),
+                    alternatives.clone(),
+                ),
+                required: None,
+                complete: true,
+            });
+        }
+
+        // Any match also contains the prefix, so only look for
+        // another literal if it's longer than the prefix
+        let required = [literals.inner, literals.suffix]
+            .into_iter()
+            .filter(|literal| literal.len() > literals.prefix.len())
+            .max_by_key(|literal| literal.len())
+            .map(|literal| LiteralSearcher::new(&literal));
+
+        if literals.prefix.is_empty() && required.is_none() {
+            // Nothing to search for
+            return None;
+        }
+
+        Some(Prefilter {
+            searcher: Searcher::Prefix(LiteralSearcher::new(&literals.prefix)),
+            required,
+            complete: false,
+        })
+    }
+
+    // Is a candidate always a match?
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    // Strings one of which every match starts with
+    // None if a match may start with anything, when one of them is empty
+    pub fn starts(&self) -> Option<Vec<Vec<char>>> {
+        let starts = match &self.searcher {
+            Searcher::Prefix(prefix) => vec![prefix.needle().to_vec()],
+            Searcher::Alternatives(_, strings) => strings.clone(),
+        };
+        match starts.iter().any(|start| start.is_empty()) {
+            true => None,
+            false => Some(starts),
+        }
+    }
+
+    // Approximate number of bytes this prefilter allocated
+    pub(crate) fn heap_size(&self) -> usize {
+        let searcher = match &self.searcher {
+            Searcher::Prefix(searcher) => searcher.heap_size(),
+            Searcher::Alternatives(automaton, strings) => {
+                automaton.heap_size()
+                    + strings
+                        .iter()
+                        .map(|string| {
+                            std::mem::size_of::<Vec<char>>()
+                                + string.len() * std::mem::size_of::<char>()
+                        })
+                        .sum::<usize>()
+            }
+        };
+        searcher + self.required.as_ref().map_or(0, LiteralSearcher::heap_size)
+    }
+
+    // First literal occurrence at or after `start` where a match may start
+    // the returned range is a match if `self.is_complete()`
+    // None if no match starts at or after `start`
+    // Successive calls on the same target MUST NOT decrease `start` unless `state` was reset
+    pub fn next_candidate(
+        &self,
+        target: &[char],
+        start: usize,
+        state: &mut PrefilterState,
+    ) -> Option<Match> {
+        if let Some(required) = &self.required {
+            // A match starting at or after `start` contains the required literal
+            // at or after `start`, if there is no such occurrence then there is no match
+            let required_at = match state.required_at {
+                // Last occurrence is still ahead, no need to search again
+                Some(position) if position >= start => position,
+                _ => required.find(target, start)?,
+            };
+            state.required_at = Some(required_at);
+        }
+        match &self.searcher {
+            Searcher::Prefix(prefix) => prefix.find(target, start).map(|position| Match {
+                start: position,
+                end: position + prefix.len(),
+            }),
+            Searcher::Alternatives(automaton, _) => {
+                automaton
+                    .find(target, start)
+                    .map(|(_, match_start, match_end)| Match {
+                        start: match_start,
+                        end: match_end,
+                    })
+            }
+        }
+    }
+}
@@ -0,0 +1,242 @@
+// Replacement templates
+// Replacement strings referring to what groups of a pattern matched:
+// `$1` or `${1}` is the text matched by group 1 (`$0` is the whole match)
+// `${name}` is the text matched by the group named `name`
+// `$$` is a literal dollar sign
+// A group which did not take part in a match is replaced with nothing
+
+use std::fmt::Display;
+
+use super::captures::{Captures, GroupInfo};
+use crate::format_error;
+
+// What went wrong while reading a replacement template
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TemplateErrorKind {
+    // A group number the pattern does not have, like `$3` with pattern `(a)(b)`
+    UnknownGroup,
+    // A group name the pattern does not have, like `${year}` with pattern `(?<day>..)`
+    UnknownGroupName,
+    // A ${ without its closing }, like `${name`
+    UnclosedBrace,
+    // A $ followed by neither a number, a {, nor another $, like `$x` or `${a-b}`
+    InvalidReference,
+}
+
+// An error in a replacement template
+#[derive(Debug, Clone)]
+pub struct TemplateError {
+    // Which error this is
+    pub kind: TemplateErrorKind,
+    // Index of the offending $ in template string
+    pub position: usize,
+    // Human readable error produced by `format_error`
+    // with carets `^` pointing at the offending reference
+    pub message: String,
+}
+
+impl Display for TemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+// Allow code expecting errors as plain strings to keep using `?`
+impl From<TemplateError> for String {
+    fn from(error: TemplateError) -> String {
+        error.message
+    }
+}
+
+// Hints shown below template errors
+const REFERENCE_HINTS: &str = "Refer to groups like $1 or ${1} or ${name}\n\
+    To insert a literal $, use $$";
+const GROUP_NUMBER_HINTS: &str =
+    "Groups are numbered from 1 in the order their ( appear in pattern\n\
+    $0 is the whole match";
+
+// A piece of a template
+#[derive(Debug, Clone)]
+enum Piece {
+    // Copied as is
+    Literal(String),
+    // Replaced with the text matched by group with this number
+    Group(usize),
+}
+
+// A replacement template checked against groups of a pattern
+#[derive(Debug, Clone)]
+pub struct Template {
+    pieces: Vec<Piece>,
+}
+
+impl Template {
+    // Read `template`, fail if it refers to a group not found in `groups`
+    pub fn new(template: &str, groups: &GroupInfo) -> Result<Template, TemplateError> {
+        let characters = template.chars().collect::<Vec<_>>();
+        let mut pieces = vec![];
+        let mut literal = String::new();
+        let mut current = 0;
+
+        while let Some(&ch) = characters.get(current) {
+            if ch != '$' {
+                literal.push(ch);
+                current += 1;
+                continue;
+            }
+
+            let start = current;
+            let (reference, end) = match characters.get(start + 1) {
+                Some('$') => {
+                    // $$, a literal dollar sign
+                    literal.push('$');
+                    current = start + 2;
+                    continue;
+                }
+                Some(digit) if digit.is_ascii_digit() => {
+                    // $N, all digits which follow
+                    let digits = characters[start + 1..]
+                        .iter()
+                        .take_while(|ch| ch.is_ascii_digit())
+                        .count();
+                    let end = start + 1 + digits;
+                    (&characters[start + 1..end], end)
+                }
+                Some('{') => {
+                    // ${N} or ${name}
+                    let closing = characters[start + 2..]
+                        .iter()
+                        .position(|ch| *ch == '}')
+                        .map(|offset| start + 2 + offset);
+                    match closing {
+                        Some(closing) => (&characters[start + 2..closing], closing + 1),
+                        None => {
+                            return Err(Self::error(
+                                TemplateErrorKind::UnclosedBrace,
+                                template,
+                                start,
+                                characters.len() - start,
+                                "Missing } after ${",
+                                "Close the group reference with a }, like ${name}",
+                            ))
+                        }
+                    }
+                }
+                _ => {
+                    return Err(Self::error(
+                        TemplateErrorKind::InvalidReference,
+                        template,
+                        start,
+                        std::cmp::min(2, characters.len() - start),
+                        "Expected a group number, a group name or $ after $",
+                        REFERENCE_HINTS,
+                    ))
+                }
+            };
+
+            let length = end - start;
+            let reference = reference.iter().collect::<String>();
+            let index = if !reference.is_empty() && reference.chars().all(|ch| ch.is_ascii_digit())
+            {
+                match reference.parse::<usize>() {
+                    Ok(index) if index < groups.len() => index,
+                    _ => {
+                        return Err(Self::error(
+                            TemplateErrorKind::UnknownGroup,
+                            template,
+                            start,
+                            length,
+                            &format!(
+                                "No group {reference}, pattern has {} group(s)",
+                                groups.len() - 1
+                            ),
+                            GROUP_NUMBER_HINTS,
+                        ))
+                    }
+                }
+            } else if Self::is_group_name(&reference) {
+                match groups.index_of(&reference) {
+                    Some(index) => index,
+                    None => {
+                        return Err(Self::error(
+                            TemplateErrorKind::UnknownGroupName,
+                            template,
+                            start,
+                            length,
+                            &format!("No group named `{reference}`"),
+                            "Name a group like (?<name>...)",
+                        ))
+                    }
+                }
+            } else {
+                return Err(Self::error(
+                    TemplateErrorKind::InvalidReference,
+                    template,
+                    start,
+                    length,
+                    &format!("Invalid group reference `{reference}`"),
+                    "A group name is a letter or _ followed by letters, digits or _",
+                ));
+            };
+
+            if !literal.is_empty() {
+                pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+            }
+            pieces.push(Piece::Group(index));
+            current = end;
+        }
+
+        if !literal.is_empty() {
+            pieces.push(Piece::Literal(literal));
+        }
+        Ok(Template { pieces })
+    }
+
+    fn is_group_name(name: &str) -> bool {
+        let mut characters = name.chars();
+        match characters.next() {
+            Some(first) if first.is_ascii_alphabetic() || first == '_' => {
+                characters.all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+            }
+            _ => false,
+        }
+    }
+
+    fn error(
+        kind: TemplateErrorKind,
+        template: &str,
+        position: usize,
+        length: usize,
+        error: &str,
+        hints: &str,
+    ) -> TemplateError {
+        TemplateError {
+            kind,
+            position,
+            message: format_error(
+                &format!("Template error in position {position}: {error}"),
+                template,
+                // Place carets `^` below the whole reference
+                &[(position, std::cmp::min(length, u8::MAX as usize) as u8)],
+                hints,
+            ),
+        }
+    }
+
+    // Append this template to `destination` replacing
+    // each group reference with the text it matched in `captures`
+    pub fn expand(&self, captures: &Captures, destination: &mut String) {
+        for piece in &self.pieces {
+            match piece {
+                Piece::Literal(literal) => destination.push_str(literal),
+                Piece::Group(index) => {
+                    if let Some(range) = captures.get(*index) {
+                        destination.extend(&captures.target()[range]);
+                    }
+                }
+            }
+        }
+    }
+}
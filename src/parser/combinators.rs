@@ -0,0 +1,251 @@
+// Build patterns with combinators instead of writing pattern strings
+// like `literal("a.b").then(any().one_or_more()).or(literal("c"))`
+// Characters are always taken literally, so nothing ever needs escaping
+//
+// A built pattern gives the same syntax tree `Parser::parse` gives for its pattern string
+// (see `Pattern::to_pattern`), thus what the pattern syntax can't write directly
+// is written with a group, which is then numbered like any other group:
+// - An alternation followed or preceded by something, like `a(b|c)`
+// - A quantified string, alternation or already quantified expression, like `(ab)+`
+
+use std::sync::Arc;
+
+use super::error::{SyntaxError, SyntaxErrorKind};
+use super::syntax_tree::*;
+use super::Parser;
+use crate::format_error;
+use crate::scanner::is_group_name;
+
+// A pattern under construction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    expression: Expression,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expression {
+    Empty,
+    // None stands for the dot, any character
+    Character {
+        value: Option<char>,
+        quantifier: Quantifier,
+    },
+    // At least two patterns, none of them a concatenation nor empty
+    Concatenation(Vec<Pattern>),
+    // At least two patterns, none of them an alternation
+    Alternation(Vec<Pattern>),
+    Group {
+        pattern: Box<Pattern>,
+        name: Option<String>,
+        quantifier: Quantifier,
+    },
+}
+
+// Pattern matching `text` verbatim
+pub fn literal(text: &str) -> Pattern {
+    text.chars()
+        .map(character)
+        .reduce(Pattern::then)
+        .unwrap_or_else(empty)
+}
+
+// Pattern matching `ch` only
+pub fn character(ch: char) -> Pattern {
+    Pattern::new(Expression::Character {
+        value: Some(ch),
+        quantifier: Quantifier::None,
+    })
+}
+
+// Pattern matching any single character, like `.`
+pub fn any() -> Pattern {
+    Pattern::new(Expression::Character {
+        value: None,
+        quantifier: Quantifier::None,
+    })
+}
+
+// Pattern matching the empty string
+pub fn empty() -> Pattern {
+    Pattern::new(Expression::Empty)
+}
+
+// Group around `pattern`, like `(...)`
+pub fn group(pattern: Pattern) -> Pattern {
+    Pattern::new(Expression::Group {
+        pattern: Box::new(pattern),
+        name: None,
+        quantifier: Quantifier::None,
+    })
+}
+
+// Group named `name` around `pattern`, like `(?<name>...)`
+// `name` is checked when the pattern is built
+pub fn named_group(name: &str, pattern: Pattern) -> Pattern {
+    Pattern::new(Expression::Group {
+        pattern: Box::new(pattern),
+        name: Some(name.to_string()),
+        quantifier: Quantifier::None,
+    })
+}
+
+impl Pattern {
+    fn new(expression: Expression) -> Pattern {
+        Pattern { expression }
+    }
+
+    // This pattern followed by `next`
+    pub fn then(self, next: Pattern) -> Pattern {
+        let mut patterns = vec![];
+        for pattern in [self, next] {
+            match pattern.expression {
+                // The empty string changes nothing in a concatenation
+                Expression::Empty => {}
+                Expression::Concatenation(items) => patterns.extend(items),
+                _ => patterns.push(pattern),
+            }
+        }
+        match patterns.len() {
+            0 => empty(),
+            1 => patterns.pop().unwrap(),
+            _ => Pattern::new(Expression::Concatenation(patterns)),
+        }
+    }
+
+    // This pattern or else `other`, like `...|...`
+    // Branches are tried in order, so this pattern first
+    pub fn or(self, other: Pattern) -> Pattern {
+        let mut branches = vec![];
+        for pattern in [self, other] {
+            match pattern.expression {
+                Expression::Alternation(items) => branches.extend(items),
+                _ => branches.push(pattern),
+            }
+        }
+        Pattern::new(Expression::Alternation(branches))
+    }
+
+    // This pattern at most once, like `?`
+    pub fn optional(self) -> Pattern {
+        self.quantified(Quantifier::ZeroOrOne)
+    }
+
+    // This pattern any number of times, like `*`
+    pub fn zero_or_more(self) -> Pattern {
+        self.quantified(Quantifier::ZeroOrMore)
+    }
+
+    // This pattern at least once, like `+`
+    pub fn one_or_more(self) -> Pattern {
+        self.quantified(Quantifier::OneOrMore)
+    }
+
+    fn quantified(self, quantifier: Quantifier) -> Pattern {
+        match self.expression {
+            Expression::Character {
+                value,
+                quantifier: Quantifier::None,
+            } => Pattern::new(Expression::Character { value, quantifier }),
+            Expression::Group {
+                pattern,
+                name,
+                quantifier: Quantifier::None,
+            } => Pattern::new(Expression::Group {
+                pattern,
+                name,
+                quantifier,
+            }),
+            // Only characters and groups take a quantifier, and only one
+            _ => Pattern::new(Expression::Group {
+                pattern: Box::new(self),
+                name: None,
+                quantifier,
+            }),
+        }
+    }
+
+    // Pattern string of this pattern, escaped where needed
+    pub fn to_pattern(&self) -> String {
+        self.tree().print().0
+    }
+
+    // Syntax tree of this pattern, the one `Parser::parse` gives for its pattern string
+    // Fail if a group name is invalid or used twice
+    pub fn build(&self) -> Result<Arc<SyntaxTree>, SyntaxError> {
+        let tree = self.tree();
+        let (pattern, spans) = tree.print();
+        // An invalid name could make the pattern string mean something else
+        // so it's never parsed
+        for (node, span) in tree.nodes().iter().zip(&spans) {
+            match &node.group_name {
+                Some(name) if !is_group_name(name) => {
+                    // Carets below the whole group opening (?<name>
+                    let length = name.chars().count() + 4;
+                    return Err(SyntaxError::new(
+                        SyntaxErrorKind::InvalidGroupName,
+                        span.start,
+                        format_error(
+                            &format!(
+                                "Syntax error in position {}: Invalid group name",
+                                span.start
+                            ),
+                            &pattern,
+                            &[(span.start, std::cmp::min(length, u8::MAX as usize) as u8)],
+                            "A group name is a letter or _ followed by letters, digits or _",
+                        ),
+                    ));
+                }
+                _ => {}
+            }
+        }
+        Parser::parse(&pattern)
+    }
+
+    // Syntax tree of this pattern as it's written, printed to get its pattern string
+    fn tree(&self) -> SyntaxTree {
+        let mut nodes = vec![];
+        let root = self.add_nodes(&mut nodes);
+        SyntaxTree::from_nodes(Arc::from(""), nodes, root)
+    }
+
+    // Add expressions of this pattern to `nodes`, children first, return its id
+    fn add_nodes(&self, nodes: &mut Vec<Node>) -> NodeId {
+        let node = match &self.expression {
+            Expression::Empty => Node::new(ExpressionType::EmptyExpression),
+            Expression::Character { value, quantifier } => {
+                Node::new(ExpressionType::CharacterExpression {
+                    value: *value,
+                    quantifier: *quantifier,
+                })
+            }
+            Expression::Concatenation(patterns) | Expression::Alternation(patterns) => {
+                let expression_type = match self.expression {
+                    Expression::Concatenation(_) => ExpressionType::Concatenation,
+                    _ => ExpressionType::Alternation,
+                };
+                let mut node = Node::new(expression_type);
+                node.children = patterns
+                    .iter()
+                    .map(|pattern| pattern.add_nodes(nodes))
+                    .collect();
+                node
+            }
+            Expression::Group {
+                pattern,
+                name,
+                quantifier,
+            } => {
+                // Groups are numbered by the parser
+                let mut node = Node::new(ExpressionType::Group {
+                    quantifier: *quantifier,
+                    index: 0,
+                });
+                node.children = vec![pattern.add_nodes(nodes)];
+                node.group_name = name.as_deref().map(Arc::from);
+                node
+            }
+        };
+        nodes.push(node);
+        NodeId::new(nodes.len() - 1)
+    }
+}
@@ -0,0 +1,67 @@
+// Syntax errors reported by the parser
+
+use crate::scanner::tokens::EscapeError;
+use std::fmt::Display;
+
+// What went wrong while parsing a pattern
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SyntaxErrorKind {
+    // A token which can not begin an expression, like the + in `+a`
+    ExpectedExpression,
+    // A ( with nothing after it, like `a(`
+    ExpectedExpressionAfterLeftParen,
+    // A group missing its closing ), like `(a`
+    ExpectedRightParen,
+    // A ) without a matching (, like `a)`
+    UnbalancedRightParen,
+    // An escape sequence the scanner could not decode, like `\xZ1`
+    InvalidEscape(EscapeError),
+    // A malformed group name, like `(?<1st>a)`
+    InvalidGroupName,
+    // Two groups with the same name, like `(?<x>a)(?<x>b)`
+    DuplicateGroupName,
+    // More groups open at once than `ParserBuilder::nest_limit`, like `((((a))))` with a limit of 3
+    NestTooDeep,
+    // More expressions than `ParserBuilder::node_limit`
+    TooManyNodes,
+    // A compiled pattern taking more memory than `RegexBuilder::size_limit`
+    ProgramTooLarge,
+}
+
+// A syntax error in a pattern
+#[derive(Debug, Clone)]
+pub struct SyntaxError {
+    // Which error this is
+    pub kind: SyntaxErrorKind,
+    // Index of the offending character in pattern string
+    // equal to pattern length if the error is at end of pattern
+    pub position: usize,
+    // Human readable error produced by `format_error`
+    // with carets `^` pointing at the offending characters
+    pub message: String,
+}
+
+impl SyntaxError {
+    pub fn new(kind: SyntaxErrorKind, position: usize, message: String) -> SyntaxError {
+        SyntaxError {
+            kind,
+            position,
+            message,
+        }
+    }
+}
+
+impl Display for SyntaxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for SyntaxError {}
+
+// Allow code expecting errors as plain strings to keep using `?`
+impl From<SyntaxError> for String {
+    fn from(error: SyntaxError) -> String {
+        error.message
+    }
+}
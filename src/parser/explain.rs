@@ -0,0 +1,204 @@
+// Human readable views of a syntax tree
+// An indented tree showing every expression of a pattern
+// and an explanation of what a pattern matches in plain English
+
+use std::fmt::Display;
+
+use super::syntax_tree::*;
+
+impl SyntaxTree {
+    // Indented tree of all expressions of the pattern, one per line
+    // For instance, pattern `a+|(b.)*` gives:
+    // Alternation `a+|(b.)*` at 0..8
+    // |-- Character 'a', one or more (+) `a+` at 0..2
+    // `-- Group 1, zero or more (*) `(b.)*` at 3..8
+    //     `-- Concatenation `b.` at 4..6
+    //         |-- Character 'b' `b` at 4..5
+    //         `-- Any character `.` at 5..6
+    // where `start..end` are positions of characters of each expression in pattern
+    pub fn tree(&self) -> String {
+        let mut tree = String::new();
+        self.write_tree(self.root(), &mut tree, "", "");
+        tree
+    }
+
+    // Write line of expression `id` then lines of its children
+    // `first_prefix` goes before this expression, `prefix` before its children
+    fn write_tree(&self, id: NodeId, tree: &mut String, first_prefix: &str, prefix: &str) {
+        tree.push_str(first_prefix);
+        tree.push_str(&self.describe_node(id));
+        tree.push('\n');
+
+        let children = &self[id].children;
+        for (index, child) in children.iter().enumerate() {
+            let (branch, indent) = if index + 1 < children.len() {
+                ("|-- ", "|   ")
+            } else {
+                ("`-- ", "    ")
+            };
+            self.write_tree(
+                *child,
+                tree,
+                &format!("{prefix}{branch}"),
+                &format!("{prefix}{indent}"),
+            );
+        }
+    }
+
+    // Kind, quantifier, pattern and span of expression `id` alone
+    fn describe_node(&self, id: NodeId) -> String {
+        let node = &self[id];
+        let (kind, quantifier) = match node.expression_type {
+            ExpressionType::EmptyExpression => (String::from("Empty"), Quantifier::None),
+            ExpressionType::CharacterExpression {
+                value: Some(value),
+                quantifier,
+            } => (format!("Character {value:?}"), quantifier),
+            ExpressionType::CharacterExpression {
+                value: None,
+                quantifier,
+            } => (String::from("Any character"), quantifier),
+            ExpressionType::Concatenation => (String::from("Concatenation"), Quantifier::None),
+            ExpressionType::Alternation => (String::from("Alternation"), Quantifier::None),
+            ExpressionType::Group { quantifier, index } => match &node.group_name {
+                Some(name) => (format!("Group {index} <{name}>"), quantifier),
+                None => (format!("Group {index}"), quantifier),
+            },
+            ExpressionType::CharacterSet { quantifier } => {
+                (String::from("Character set"), quantifier)
+            }
+        };
+        let quantifier = match quantifier {
+            Quantifier::None => String::new(),
+            Quantifier::ZeroOrOne => format!(", optional ({quantifier})"),
+            Quantifier::ZeroOrMore => format!(", zero or more ({quantifier})"),
+            Quantifier::OneOrMore => format!(", one or more ({quantifier})"),
+        };
+        // Pattern as written, only control characters (like a new line) are escaped
+        let pattern = self
+            .pattern(id)
+            .chars()
+            .map(|ch| match ch.is_control() {
+                true => ch.escape_default().to_string(),
+                false => ch.to_string(),
+            })
+            .collect::<String>();
+        format!(
+            "{kind}{quantifier} `{pattern}` at {}..{}",
+            node.span.start, node.span.end
+        )
+    }
+
+    // What the pattern matches in plain English
+    // For instance, pattern `x(a|b)+` is explained as
+    // 'x', then one or more of: ('a' or 'b') [group 1]
+    pub fn explain(&self) -> Explanation<'_> {
+        Explanation { tree: self }
+    }
+
+    // Explanation of expression `id`
+    // along with whether it's made of many parts (needs parentheses when nested)
+    fn explanation(&self, id: NodeId) -> (String, bool) {
+        let node = &self[id];
+        let children = &node.children;
+        match node.expression_type {
+            ExpressionType::EmptyExpression => (String::from("the empty string"), false),
+
+            ExpressionType::CharacterExpression { value, quantifier } => {
+                let matched = match value {
+                    Some(value) => format!("{value:?}"),
+                    None => String::from("any character"),
+                };
+                (Self::quantified(quantifier, &matched), false)
+            }
+
+            ExpressionType::Concatenation => {
+                // Consecutive characters read better as one string, like "abc"
+                let mut parts = vec![];
+                let mut string = String::new();
+                for child in children {
+                    if let ExpressionType::CharacterExpression {
+                        value: Some(value),
+                        quantifier: Quantifier::None,
+                    } = self[*child].expression_type
+                    {
+                        string.push(value);
+                        continue;
+                    }
+                    Self::push_string(&mut parts, &mut string);
+                    parts.push(self.explanation(*child).0);
+                }
+                Self::push_string(&mut parts, &mut string);
+                let compound = parts.len() > 1;
+                (parts.join(", then "), compound)
+            }
+
+            ExpressionType::Alternation => {
+                let branches = children
+                    .iter()
+                    .map(|child| match self.explanation(*child) {
+                        (branch, true) => format!("({branch})"),
+                        (branch, false) => branch,
+                    })
+                    .collect::<Vec<_>>();
+                (branches.join(" or "), true)
+            }
+
+            ExpressionType::Group { quantifier, index } => {
+                let grouped = match self.explanation(children[0]) {
+                    (grouped, true) => format!("({grouped})"),
+                    (grouped, false) => grouped,
+                };
+                let group = match &node.group_name {
+                    Some(name) => format!("[group {index} <{name}>]"),
+                    None => format!("[group {index}]"),
+                };
+                (
+                    format!("{} {group}", Self::quantified(quantifier, &grouped)),
+                    false,
+                )
+            }
+
+            ExpressionType::CharacterSet { quantifier } => {
+                let characters = children
+                    .iter()
+                    .map(|child| self.explanation(*child).0)
+                    .collect::<Vec<_>>();
+                let matched = format!("one of {}", characters.join(", "));
+                (Self::quantified(quantifier, &matched), false)
+            }
+        }
+    }
+
+    // Add `string` (if any) to `parts` as a quoted string, leave `string` empty
+    fn push_string(parts: &mut Vec<String>, string: &mut String) {
+        match string.chars().count() {
+            0 => {}
+            // A single character is quoted like other characters, like 'a'
+            1 => parts.push(format!("{:?}", string.chars().next().unwrap())),
+            _ => parts.push(format!("{string:?}")),
+        }
+        string.clear();
+    }
+
+    // Explanation of `matched` repeated according to `quantifier`
+    fn quantified(quantifier: Quantifier, matched: &str) -> String {
+        match quantifier {
+            Quantifier::None => matched.to_string(),
+            Quantifier::ZeroOrOne => format!("optionally {matched}"),
+            Quantifier::ZeroOrMore => format!("zero or more of: {matched}"),
+            Quantifier::OneOrMore => format!("one or more of: {matched}"),
+        }
+    }
+}
+
+// What a pattern matches in plain English, see `SyntaxTree::explain`
+pub struct Explanation<'a> {
+    tree: &'a SyntaxTree,
+}
+
+impl Display for Explanation<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.tree.explanation(self.tree.root()).0)
+    }
+}
@@ -4,11 +4,42 @@
 // Syntax tree structs
 pub mod syntax_tree;
 
+// Syntax errors
+pub mod error;
+
+// Syntax tree printed as a tree or explained in English
+pub mod explain;
+
+// Visit and rewrite syntax trees
+pub mod visit;
+
+// Syntax trees printed back as patterns
+pub mod printer;
+
+// Patterns built with combinators instead of strings
+pub mod combinators;
+
+// Syntax trees simplified without changing what they match
+pub mod optimize;
+
+// Patterns which may backtrack exponentially or polynomially
+pub mod redos;
+
+// Syntax trees saved as bytes and loaded back
+pub mod serialize;
+
 use crate::scanner::{tokens::*, Scanner};
 use crate::{format_error, report_fatal_error};
-use std::sync::{Arc, RwLock};
+use error::*;
+use std::sync::Arc;
 use syntax_tree::*;
 
+// Default of `ParserBuilder::nest_limit`
+const DEFAULT_NEST_LIMIT: usize = 250;
+
+// Default of `ParserBuilder::node_limit`
+const DEFAULT_NODE_LIMIT: usize = 1 << 20;
+
 #[allow(dead_code)]
 // Mark where to a grouping begins
 enum GroupingMark {
@@ -30,27 +61,118 @@ pub struct Parser {
     // marks stack
     // we need a stack because groups (...) can nest
     grouping_marks: Vec<GroupingMark>,
+
+    // how many groups were parsed so far
+    // used to number groups in the order their ( appear
+    groups_count: usize,
+
+    // names of named groups parsed so far
+    // a name can not be used twice
+    group_names: Vec<Arc<str>>,
+
+    // at most how many groups can be open at once
+    nest_limit: usize,
+    // at most how many expressions can be parsed
+    node_limit: usize,
+
+    // source string, shared by all parsed expressions
+    // each expression keeps the span of its characters in it
+    source: Arc<str>,
+    // how many characters field `source` has
+    source_length: usize,
+
+    // expressions parsed so far, in the order they were completed
+    // a parent is completed after its children
+    nodes: Vec<Node>,
+}
+
+// Parsing options
+// Unlike `Parser::parse` which keeps its old lenient behavior
+// a new builder enables all validations by default
+#[derive(Debug, Clone, Copy)]
+pub struct ParserBuilder {
+    // Report unknown escapes like `\c` (and a trailing slash)
+    // as syntax errors instead of reading them as a literal slash
+    strict_escapes: bool,
+    // How many groups can be nested inside each other
+    // the parser recurses once per group, so without a limit
+    // a pattern like `((((...` could overflow the stack
+    nest_limit: usize,
+    // How many expressions (nodes of the syntax tree) a pattern can have
+    node_limit: usize,
+}
+
+impl Default for ParserBuilder {
+    fn default() -> Self {
+        ParserBuilder::new()
+    }
+}
+
+impl ParserBuilder {
+    pub fn new() -> ParserBuilder {
+        ParserBuilder {
+            strict_escapes: true,
+            nest_limit: DEFAULT_NEST_LIMIT,
+            node_limit: DEFAULT_NODE_LIMIT,
+        }
+    }
+
+    pub fn strict_escapes(&mut self, yes: bool) -> &mut ParserBuilder {
+        self.strict_escapes = yes;
+        self
+    }
+
+    // At most `limit` groups open at once, `((a))` has two
+    // 250 by default, deeper patterns are reported as `SyntaxErrorKind::NestTooDeep`
+    pub fn nest_limit(&mut self, limit: usize) -> &mut ParserBuilder {
+        self.nest_limit = limit;
+        self
+    }
+
+    // At most `limit` expressions in the syntax tree, `ab|c` has five
+    // 2^20 by default, larger patterns are reported as `SyntaxErrorKind::TooManyNodes`
+    pub fn node_limit(&mut self, limit: usize) -> &mut ParserBuilder {
+        self.node_limit = limit;
+        self
+    }
+
+    // parse source string into a `SyntaxTree` object using these options
+    pub fn parse(&self, source: &str) -> Result<Arc<SyntaxTree>, SyntaxError> {
+        Parser::new(source, self).parse_source()
+    }
 }
 
 impl Parser {
-    fn new(source: &str) -> Parser {
-        let scanner = Scanner::new(source);
+    fn new(source: &str, options: &ParserBuilder) -> Parser {
+        let scanner = Scanner::with_strict_escapes(source, options.strict_escapes);
         let current = None;
         let grouping_marks = vec![];
+        let groups_count = 0;
+        let group_names = vec![];
+        let source_length = source.chars().count();
+        let source = Arc::from(source);
         Parser {
             scanner,
             current,
             grouping_marks,
+            groups_count,
+            group_names,
+            nest_limit: options.nest_limit,
+            node_limit: options.node_limit,
+            source,
+            source_length,
+            nodes: vec![],
         }
     }
 
-    pub fn parse(source: &str) -> Result<Arc<RwLock<ParsedRegexp>>, String> {
-        // parse source string into a `ParsedRegexp` object
-        Parser::new(source).parse_source()
+    pub fn parse(source: &str) -> Result<Arc<SyntaxTree>, SyntaxError> {
+        // parse source string into a `SyntaxTree` object
+        // unknown escapes are read as a literal slash
+        ParserBuilder::new().strict_escapes(false).parse(source)
     }
 
     // Attempt to parse source string
-    fn parse_source(&mut self) -> Result<Arc<RwLock<ParsedRegexp>>, String> {
+    fn parse_source(&mut self) -> Result<Arc<SyntaxTree>, SyntaxError> {
         // Grab the first token in stream
         self.advance()?;
         match self.parse_expression() {
@@ -59,12 +181,16 @@ impl Parser {
 
             // Successfully parsed source string
             Ok(option_regexp) => {
-                // `option_regexp` has type Option<Arc<RwLock<ParsedRegexp>>>
+                // `option_regexp` has type Option<NodeId>
                 match option_regexp {
-                    Some(regexp) => {
-                        // Return the Arc itself otherwise it will dropped making direct child of
-                        // root expression hold invalid Weak references to their parent (root itself)
-                        Ok(regexp)
+                    Some(root) => {
+                        // Root expression was completed last, after all expressions inside it
+                        let nodes = std::mem::take(&mut self.nodes);
+                        Ok(Arc::new(SyntaxTree::from_nodes(
+                            Arc::clone(&self.source),
+                            nodes,
+                            root,
+                        )))
                     }
                     None => {
                         // Could not parse source string for some unknown reason
@@ -83,7 +209,7 @@ impl Parser {
     }
 
     // ParsedRegexp => Concatenation ( "|" Concatenation )*
-    fn parse_expression(&mut self) -> Result<Option<Arc<RwLock<ParsedRegexp>>>, String> {
+    fn parse_expression(&mut self) -> Result<Option<NodeId>, SyntaxError> {
         match self.current {
             None => {
                 // Reached end of input, no expression can be parsed
@@ -94,22 +220,20 @@ impl Parser {
                 match token.type_name {
                     // This token can begin a valid expression
                     TokenType::Empty
-                    | TokenType::Dot { .. }
+                    | TokenType::Dot
                     | TokenType::Character { .. }
-                    | TokenType::LeftParen => {
+                    | TokenType::LeftParen
+                    | TokenType::NamedLeftParen { .. } => {
                         // Attempt to parse an arbitrary expression
                         // But do that attempt to parse an alternation expression
                         // because alternation has the lowest precedence of all regular expressions operations
-                        let mut alternation_pattern = String::new();
-                        let mut alternation = ParsedRegexp::new(ExpressionType::Alternation);
+                        let start = self.position();
+                        let mut alternation = Node::new(ExpressionType::Alternation);
 
                         // First, attempt to parse one concatenation
                         if let Some(concatenation) = self.parse_concatenation()? {
                             // Parsed first concatenation
-                            // Append its pattern
-                            alternation_pattern
-                                .push_str(&format!("{}|", concatenation.read().unwrap().pattern));
-                            alternation.children.write().unwrap().push(concatenation);
+                            alternation.children.push(concatenation);
 
                             // As long as current token is |, keep parsing concatenations
                             while self.check(TokenType::Pipe) {
@@ -117,21 +241,13 @@ impl Parser {
                                 self.advance()?;
                                 if let Some(expression) = self.parse_concatenation()? {
                                     // Parsed a new expression
-                                    // Append its pattern
-                                    alternation_pattern.push_str(&format!(
-                                        "{}|",
-                                        expression.read().unwrap().pattern
-                                    ));
                                     // append it to field `children` of this `alternation`
-                                    alternation.children.write().unwrap().push(expression);
+                                    alternation.children.push(expression);
                                 }
                             }
                         }
 
-                        // Can't use `alternation.children.read().unwrap().len()` directly with `match`
-                        // because `alternation` is moved inside `match` body
-                        let parsed_expressions = alternation.children.read().unwrap().len();
-                        match parsed_expressions {
+                        match alternation.children.len() {
                             0 => {
                                 // No expression was parsed, possibly end of pattern
                                 Ok(None)
@@ -141,32 +257,16 @@ impl Parser {
                                 // of at least two expressions, thus it makes no sense to return this single
                                 // expression as an alternation
                                 // Return this expression verbatim
-                                Ok(alternation.children.write().unwrap().pop())
+                                Ok(alternation.children.pop())
                             }
                             _ => {
-                                // Remove trailing |
-                                alternation_pattern.pop();
-
                                 // At least two expressions were parsed
                                 // Composed an alternation expression
-                                // Its children are already inside it, in ParsedRegexp field `children`
-                                alternation.pattern = Arc::from(alternation_pattern);
-                                let alternation = Arc::new(RwLock::new(alternation));
-                                alternation
-                                    .write()
-                                    .unwrap()
-                                    .children
-                                    .write()
-                                    .unwrap()
-                                    .iter_mut()
-                                    .for_each(|child| {
-                                        // Make each child obtain a weak reference to its parent `alternation`
-                                        child.write().unwrap().parent =
-                                            Some(Arc::downgrade(&alternation));
-                                    });
+                                // Its children are already inside it, in Node field `children`
+                                self.locate(&mut alternation, start);
 
                                 // Successfully parsed an alternation expression
-                                Ok(Some(alternation))
+                                Ok(Some(self.add(alternation)?))
                             }
                         }
                     }
@@ -183,11 +283,15 @@ impl Parser {
                                 None => (source.len(), String::from("at end of pattern")), // in case parser reached end of input
                             }
                         };
-                        Err(format_error(
-                            &format!("Syntax error {error_position}: {error}"),
-                            &source,
-                            &[(error_index, 1_u8)],
-                            "",
+                        Err(SyntaxError::new(
+                            SyntaxErrorKind::ExpectedExpression,
+                            error_index,
+                            format_error(
+                                &format!("Syntax error {error_position}: {error}"),
+                                &source,
+                                &[(error_index, 1_u8)],
+                                "",
+                            ),
                         ))
                     }
                 }
@@ -196,27 +300,18 @@ impl Parser {
     }
 
     // Concatenation => Primary+
-    fn parse_concatenation(&mut self) -> Result<Option<Arc<RwLock<ParsedRegexp>>>, String> {
+    fn parse_concatenation(&mut self) -> Result<Option<NodeId>, SyntaxError> {
         // Attempt to parse a concatenation of regular expressions
 
-        let mut concatenation_pattern = String::new();
-        let mut concatenation = ParsedRegexp::new(ExpressionType::Concatenation);
+        let start = self.position();
+        let mut concatenation = Node::new(ExpressionType::Concatenation);
         while let Some(primary_expression) = self.parse_primary()? {
             // Parsed a new expression
-            // Append its pattern
-            concatenation_pattern.push_str(&primary_expression.read().unwrap().pattern);
-            // append it to field `children` of this `alternation`
-            concatenation
-                .children
-                .write()
-                .unwrap()
-                .push(primary_expression);
+            // append it to field `children` of this `concatenation`
+            concatenation.children.push(primary_expression);
         }
 
-        // Can't use `concatenation.children.read().unwrap().len()` directly with `match`
-        // because `concatenation` is moved inside `match` body
-        let parsed_expressions = concatenation.children.read().unwrap().len();
-        match parsed_expressions {
+        match concatenation.children.len() {
             0 => {
                 // No expression was parsed, possibly end of pattern
                 Ok(None)
@@ -226,34 +321,22 @@ impl Parser {
                 // of at least two expressions, thus it makes no sense to return this single
                 // expression as a concatenation
                 // Return this expression verbatim
-                Ok(concatenation.children.write().unwrap().pop())
+                Ok(concatenation.children.pop())
             }
             _ => {
                 // At least two expressions were parsed
                 // Composed a concatenation expression
-                // Its children are already inside it, in ParsedRegexp field `children`
-                concatenation.pattern = Arc::from(concatenation_pattern);
-                let concatenation = Arc::new(RwLock::new(concatenation));
-                concatenation
-                    .write()
-                    .unwrap()
-                    .children
-                    .write()
-                    .unwrap()
-                    .iter_mut()
-                    .for_each(|child| {
-                        // Make each child obtain a weak reference to its parent `concatenation`
-                        child.write().unwrap().parent = Some(Arc::downgrade(&concatenation));
-                    });
+                // Its children are already inside it, in Node field `children`
+                self.locate(&mut concatenation, start);
 
                 // Successfully parsed a concatenation expression
-                Ok(Some(concatenation))
+                Ok(Some(self.add(concatenation)?))
             }
         }
     }
 
     // Primary => Empty | Group | MatchCharacter | MatchAnyCharacter
-    fn parse_primary(&mut self) -> Result<Option<Arc<RwLock<ParsedRegexp>>>, String> {
+    fn parse_primary(&mut self) -> Result<Option<NodeId>, SyntaxError> {
         // WHAT DO YOU DO `parse_primary`?
         // I parse primary expressions, which are:
         // - The empty regular expression
@@ -267,7 +350,7 @@ impl Parser {
                     TokenType::Empty => self.parse_empty_expression(),
                     TokenType::Dot => self.parse_dot_expression(),
                     TokenType::Character { value, .. } => self.parse_character_expression(*value),
-                    TokenType::LeftParen => self.parse_group(),
+                    TokenType::LeftParen | TokenType::NamedLeftParen { .. } => self.parse_group(),
                     _ => Ok(None), // Current token can begin a valid expression
                 }
             }
@@ -275,8 +358,8 @@ impl Parser {
         }
     }
 
-    // Group => "(" ParsedRegexp ")"
-    fn parse_group(&mut self) -> Result<Option<Arc<RwLock<ParsedRegexp>>>, String> {
+    // Group => ( "(" | "(?<" Name ">" ) ParsedRegexp ")"
+    fn parse_group(&mut self) -> Result<Option<NodeId>, SyntaxError> {
         // Attempt to:
         // First : parse an arbitrary expression
         // Second: After `First` is finished, search for a )
@@ -287,43 +370,43 @@ impl Parser {
         // First : After `(` parser expects a `ParsedRegexp`
         // Second: After `ParsedRegexp` parser expects a `)`
 
-        // Move past opening (
+        let start = self.position();
+        // Name of this group, if it's a named group (?<name>...)
+        let group_name = self.read_group_name()?;
+        // Number this group before parsing groups nested inside it
+        self.groups_count += 1;
+        let index = self.groups_count;
+
+        // Move past opening ( or (?<name>
         self.advance()?;
 
         // parse an arbitrary expression or report error (? operator)
         match self.parse_expression()? {
             Some(parsed_expression) => {
-                // `parsed_expression` has type Arc<RwLock<ParsedRegexp>>
+                // `parsed_expression` has type NodeId
 
                 // Advance only when current item has name TokenName::RightParent
                 // or report error `Expected ) after expression` (? operator)
-                self.consume(TokenType::RightParen, "Expected ) after expression")?;
+                self.consume(
+                    TokenType::RightParen,
+                    SyntaxErrorKind::ExpectedRightParen,
+                    "Expected ) after expression",
+                )?;
                 // field `current` now points to the first character (or Empty token)
                 // after the closing )
 
                 // Consume group quantifier (if any)
                 let quantifier = self.consume_quantifier()?;
                 // Construct parsed grouped expression
-                let mut group = ParsedRegexp::new(ExpressionType::Group { quantifier });
-                // Surround parsed expression pattern with parentheses
-                // to create pattern of this group expression
-                group.pattern = {
-                    let parsed_expression_pattern = &parsed_expression.read().unwrap().pattern;
-                    let group_quantifier = quantifier;
-                    Arc::from(format!("({parsed_expression_pattern}){group_quantifier}"))
-                };
-                // let `group` take ownership of the expression it encloses
-                group.children.write().unwrap().push(parsed_expression);
-                // convert `group` to appropriate return type
-                let group = Arc::new(RwLock::new(group));
-                // make enclosed expression `parent` field points to `group`
-                group.write().unwrap().children.write().unwrap()[0]
-                    .write()
-                    .unwrap()
-                    .parent = Some(Arc::downgrade(&group));
+                let mut group = Node::new(ExpressionType::Group { quantifier, index });
+                // From ( to the closing ) and its quantifier
+                self.locate(&mut group, start);
+                group.group_name = group_name;
+                // let `group` enclose the expression
+                group.children.push(parsed_expression);
 
                 // Successfully parsed a grouped expression
-                Ok(Some(group))
+                Ok(Some(self.add(group)?))
             }
             None => {
                 // Syntax error: Expected expression after (
@@ -345,20 +428,70 @@ impl Parser {
                         None => (source.len(), String::from("at end of pattern")), // in case parser reached end of input
                     }
                 };
-                Err(format_error(
-                    &format!("Syntax error {error_position}: {error}"),
-                    &source,
-                    // Place one (1_u8) caret `^` below error position
-                    // in source string as a visual aid
-                    &[(error_index, 1_u8)],
-                    "", // Hints
+                Err(SyntaxError::new(
+                    SyntaxErrorKind::ExpectedExpressionAfterLeftParen,
+                    error_index,
+                    format_error(
+                        &format!("Syntax error {error_position}: {error}"),
+                        &source,
+                        // Place one (1_u8) caret `^` below error position
+                        // in source string as a visual aid
+                        &[(error_index, 1_u8)],
+                        "", // Hints
+                    ),
                 ))
             }
         }
     }
 
+    // Name => [a-zA-Z_] [a-zA-Z0-9_]*
+    // Read name of the group opened by current token, if it's a named group
+    fn read_group_name(&mut self) -> Result<Option<Arc<str>>, SyntaxError> {
+        let (position, name_length) = match self.current {
+            Some(Token {
+                type_name: TokenType::NamedLeftParen { name_length },
+                position,
+            }) => (position, name_length),
+            // An unnamed group
+            _ => return Ok(None),
+        };
+
+        // Name follows (?<
+        let source = self.scanner.get_source_string();
+        let name_start = position + 3;
+        let name = source
+            .chars()
+            .skip(name_start)
+            .take(name_length)
+            .collect::<String>();
+
+        if self.group_names.iter().any(|used| **used == name) {
+            return Err(SyntaxError::new(
+                SyntaxErrorKind::DuplicateGroupName,
+                name_start,
+                format_error(
+                    &format!(
+                        "Syntax error in position {name_start}: Duplicate group name `{name}`"
+                    ),
+                    &source,
+                    // Place carets `^` below the whole name
+                    &[(
+                        name_start,
+                        std::cmp::min(name_length, u8::MAX as usize) as u8,
+                    )],
+                    "Each group name can be used only once in a pattern",
+                ),
+            ));
+        }
+
+        let name: Arc<str> = Arc::from(name);
+        self.group_names.push(Arc::clone(&name));
+        Ok(Some(name))
+    }
+
     // Empty => ""
-    fn parse_empty_expression(&mut self) -> Result<Option<Arc<RwLock<ParsedRegexp>>>, String> {
+    fn parse_empty_expression(&mut self) -> Result<Option<NodeId>, SyntaxError> {
+        let start = self.position();
         // Move past Empty token
         self.advance()?;
         // field `current` now points to the first character after
@@ -367,53 +500,151 @@ impl Parser {
         // Empty token because the scanner never generates
         // two or more Empty tokens in row
 
-        let mut expr = ParsedRegexp::new(ExpressionType::EmptyExpression);
-        // Empty string pattern for the empty expression
-        expr.pattern = Arc::from("");
+        let mut expr = Node::new(ExpressionType::EmptyExpression);
+        // The empty expression spans no characters
+        expr.span = start..start;
 
         // Successfully parsed an empty expression
-        Ok(Some(Arc::new(RwLock::new(expr))))
+        Ok(Some(self.add(expr)?))
     }
 
     // MatchAnyCharacter => Dot
-    fn parse_dot_expression(&mut self) -> Result<Option<Arc<RwLock<ParsedRegexp>>>, String> {
+    fn parse_dot_expression(&mut self) -> Result<Option<NodeId>, SyntaxError> {
+        let start = self.position();
         // Move past Dot token
         self.advance()?;
 
         let value = None;
         let quantifier = self.consume_quantifier()?;
-        let mut expr = ParsedRegexp::new(ExpressionType::CharacterExpression { value, quantifier });
-        // A dot for dot expressions succeeded with a quantifier (if any)
-        expr.pattern = Arc::from(format!(".{quantifier}").as_str());
+        let mut expr = Node::new(ExpressionType::CharacterExpression { value, quantifier });
+        // A dot succeeded with a quantifier (if any)
+        self.locate(&mut expr, start);
 
         // Successfully parsed a dot expression
-        Ok(Some(Arc::new(RwLock::new(expr))))
+        Ok(Some(self.add(expr)?))
     }
 
     // Character => OrdinaryCharacter | EscapedMetacharacter
-    fn parse_character_expression(
-        &mut self,
-        value: char,
-    ) -> Result<Option<Arc<RwLock<ParsedRegexp>>>, String> {
+    fn parse_character_expression(&mut self, value: char) -> Result<Option<NodeId>, SyntaxError> {
+        let start = self.position();
         // Move past `Character` token
         self.advance()?;
 
         let quantifier = self.consume_quantifier()?;
-        let mut expr = ParsedRegexp::new(ExpressionType::CharacterExpression {
+        let mut expr = Node::new(ExpressionType::CharacterExpression {
             value: Some(value),
             quantifier,
         });
 
-        // Use given character for this character expression succeeded with a quantifier (if any)
-        expr.pattern = Arc::from(format!("{value}{quantifier}").as_str());
+        // A character (or an escape sequence) succeeded with a quantifier (if any)
+        self.locate(&mut expr, start);
 
         // Successfully parsed a character expression
-        Ok(Some(Arc::new(RwLock::new(expr))))
+        Ok(Some(self.add(expr)?))
+    }
+
+    // Position of current token in source string
+    // source string length at end of pattern
+    fn position(&self) -> usize {
+        match self.current {
+            Some(Token { position, .. }) => position,
+            None => self.source_length,
+        }
+    }
+
+    // Make `expression` span source string from `start`
+    // up to current token, the first one after `expression`
+    fn locate(&self, expression: &mut Node, start: usize) {
+        expression.span = start..self.position();
+    }
+
+    // Keep a completed expression, return its id
+    fn add(&mut self, expression: Node) -> Result<NodeId, SyntaxError> {
+        if self.nodes.len() == self.node_limit {
+            // Report where the first expression beyond the limit starts
+            let position = expression.span.start;
+            let source = self.scanner.get_source_string();
+            return Err(SyntaxError::new(
+                SyntaxErrorKind::TooManyNodes,
+                position,
+                format_error(
+                    &format!(
+                        "Syntax error in position {position}: Pattern has more than {} expressions",
+                        self.node_limit
+                    ),
+                    &source,
+                    &[(position, 1_u8)],
+                    "Each character, group, concatenation and alternation is an expression\n\
+                    Raise the limit with `node_limit` or split the pattern",
+                ),
+            ));
+        }
+        self.nodes.push(expression);
+        Ok(NodeId::new(self.nodes.len() - 1))
     }
 
     // Read next token in stream
-    fn advance(&mut self) -> Result<(), String> {
+    fn advance(&mut self) -> Result<(), SyntaxError> {
         self.current = self.scanner.next();
+        if let Some(Token {
+            type_name: TokenType::InvalidEscape { error, length },
+            position,
+        }) = self.current
+        {
+            // The scanner could not decode an escape sequence
+            // like `\xG1` or `\u{D800}`
+            let source = self.scanner.get_source_string();
+            let hints = match error {
+                EscapeError::MalformedHexEscape => {
+                    "\\x takes exactly two hexadecimal digits, like \\x41"
+                }
+                EscapeError::MalformedUnicodeEscape => {
+                    "\\u takes one to six hexadecimal digits inside braces, like \\u{1F600}"
+                }
+                EscapeError::InvalidCodePoint => {
+                    "Code points must be at most 10FFFF and not in range D800 to DFFF"
+                }
+                EscapeError::UnknownEscape => {
+                    "Only metacharacters, \\n \\t \\r \\f \\v \\0 \\xHH and \\u{H...} can follow a slash\n\
+                    To match a literal slash, use \\\\"
+                }
+                EscapeError::TrailingSlash => {
+                    "A slash must be followed by the character it escapes\n\
+                    To match a literal slash, use \\\\"
+                }
+            };
+            return Err(SyntaxError::new(
+                SyntaxErrorKind::InvalidEscape(error),
+                position,
+                format_error(
+                    &format!("Syntax error in position {position}: {error}"),
+                    &source,
+                    // Place carets `^` below the whole escape sequence
+                    &[(position, std::cmp::min(length, u8::MAX as usize) as u8)],
+                    hints,
+                ),
+            ));
+        }
+        if let Some(Token {
+            type_name: TokenType::InvalidGroupName { length },
+            position,
+        }) = self.current
+        {
+            // The scanner found (?< not followed by a valid name and a >
+            let source = self.scanner.get_source_string();
+            return Err(SyntaxError::new(
+                SyntaxErrorKind::InvalidGroupName,
+                position,
+                format_error(
+                    &format!("Syntax error in position {position}: Invalid group name"),
+                    &source,
+                    // Place carets `^` below the whole group opening
+                    &[(position, std::cmp::min(length, u8::MAX as usize) as u8)],
+                    "A group name is a letter or _ followed by letters, digits or _\n\
+                    and it's closed with a >, like (?<year>...)",
+                ),
+            ));
+        }
         if self.check(TokenType::RightParen) && self.grouping_marks.pop().is_none() {
             // There is no group expression currently processed
             // Thus ) was used without its matching (
@@ -426,29 +657,56 @@ impl Parser {
                     None => (source.len(), String::from("at end of pattern")), // in case parser reached end of input
                 }
             };
-            return Err(format_error(
-                &format!("Syntax error {error_position}: {error}"),
-                &source,
-                // Place one (1_u8) caret `^` below error position
-                // in source string as a visual aid
-                &[(error_index, 1_u8)],
-                // Hints
-                "\nTo match a literal ) use \\)\n\
-                To match a metacharacter, precede it with a slash in your pattern \\\n\
-                To match a *, for instance, use \\* in your pattern\n\n\
-                But remember, \\\\ inside your (rust non-raw string) pattern is one slash for the regular expressions engine\n\
-                Hence to match a single literal slash, you write pattern \\\\\\\\\n\
-                The first pair (one slash, operator) escape the second pair (one slash, operand)\n\
-                Or, you can use a raw string r\"\\\\\"",
+            return Err(SyntaxError::new(
+                SyntaxErrorKind::UnbalancedRightParen,
+                error_index,
+                format_error(
+                    &format!("Syntax error {error_position}: {error}"),
+                    &source,
+                    // Place one (1_u8) caret `^` below error position
+                    // in source string as a visual aid
+                    &[(error_index, 1_u8)],
+                    // Hints
+                    "\nTo match a literal ) use \\)\n\
+                    To match a metacharacter, precede it with a slash in your pattern \\\n\
+                    To match a *, for instance, use \\* in your pattern\n\n\
+                    But remember, \\\\ inside your (rust non-raw string) pattern is one slash for the regular expressions engine\n\
+                    Hence to match a single literal slash, you write pattern \\\\\\\\\n\
+                    The first pair (one slash, operator) escape the second pair (one slash, operand)\n\
+                    Or, you can use a raw string r\"\\\\\"",
+                ),
             ));
         }
-        if self.check(TokenType::LeftParen) {
+        if matches!(
+            self.current,
+            Some(Token {
+                type_name: TokenType::LeftParen | TokenType::NamedLeftParen { .. },
+                ..
+            })
+        ) {
             // The parser has found a possibly opening (
             // Note the word `possibly`, if pattern ends with a matching )
             // then the parser will report a syntax error
-            self.grouping_marks.push(GroupingMark::Group {
-                position: self.current.unwrap().position,
-            });
+            let position = self.current.unwrap().position;
+            if self.grouping_marks.len() == self.nest_limit {
+                // Parsing this group would recurse once more than allowed
+                let source = self.scanner.get_source_string();
+                return Err(SyntaxError::new(
+                    SyntaxErrorKind::NestTooDeep,
+                    position,
+                    format_error(
+                        &format!("Syntax error in position {position}: Groups nested too deeply"),
+                        &source,
+                        &[(position, 1_u8)],
+                        &format!(
+                            "At most {} groups can be open at once\n\
+                            Raise the limit with `nest_limit`",
+                            self.nest_limit
+                        ),
+                    ),
+                ));
+            }
+            self.grouping_marks.push(GroupingMark::Group { position });
             return Ok(());
         }
 
@@ -466,7 +724,12 @@ impl Parser {
     // Check if current token (if any) has a given type
     // if true then advance
     // if false report `error`
-    fn consume(&mut self, expected: TokenType, error: &str) -> Result<(), String> {
+    fn consume(
+        &mut self,
+        expected: TokenType,
+        kind: SyntaxErrorKind,
+        error: &str,
+    ) -> Result<(), SyntaxError> {
         if !self.check(expected) {
             // current token name (type) is not what was expected
             // in other words, grammar requires a specific item to appear here
@@ -479,20 +742,24 @@ impl Parser {
                     None => (source.len(), String::from("at end of pattern")), // in case parser reached end of input
                 }
             };
-            return Err(format_error(
-                &format!("Syntax error {error_position}: {error}"),
-                &self.scanner.get_source_string(),
-                // Place one (1_u8) caret `^` below error position
-                // in source string as a visual aid
-                &[(error_index, 1_u8)],
-                "", // Hints
+            return Err(SyntaxError::new(
+                kind,
+                error_index,
+                format_error(
+                    &format!("Syntax error {error_position}: {error}"),
+                    &self.scanner.get_source_string(),
+                    // Place one (1_u8) caret `^` below error position
+                    // in source string as a visual aid
+                    &[(error_index, 1_u8)],
+                    "", // Hints
+                ),
             ));
         }
         self.advance()?;
         Ok(())
     }
 
-    fn consume_quantifier(&mut self) -> Result<Quantifier, String> {
+    fn consume_quantifier(&mut self) -> Result<Quantifier, SyntaxError> {
         // Check current token, if its name (field `name`) is either one of:
         // Mark, Star, Plus
         // Consume each and construct a Quantifier variant
@@ -0,0 +1,353 @@
+// Simplify a syntax tree without changing what it matches
+// Branches are still tried in the same order, so the leftmost match found first
+// is the same for the simplified tree:
+// - Groups without a quantifier are removed if groups aren't kept, like `((a|b))c`
+//   into `(a|b)c` whose ( ) only tell the printer where the alternation ends
+// - Quantifiers of a group around one character are merged if groups aren't kept,
+//   like `(a+)*` into `a*`
+// - Concatenations and alternations inside one of their kind are flattened
+// - A branch of an alternation written before is removed, like `a|b|a` into `a|b`
+// - Characters starting a run of branches are factored, like `abc|abd|e` into `ab(c|d)|e`
+// - Branches next to each other which are single characters are merged into
+//   a character set matched in one step, like `a|b|c` in `x+|a|b|c` or in `(a|b|c)*`
+//   or into a dot if one of them is a dot, like `a|.` into `.`
+//
+// Every group captures, so groups are kept by default with their numbers and names
+// `Optimizer::keep_groups(false)` removes them for a simpler tree when captures don't matter
+
+use super::syntax_tree::*;
+use super::visit::{fold, Folder};
+use crate::report_fatal_error;
+
+#[derive(Debug, Clone)]
+pub struct Optimizer {
+    keep_groups: bool,
+}
+
+impl Default for Optimizer {
+    fn default() -> Self {
+        Optimizer::new()
+    }
+}
+
+impl Optimizer {
+    pub fn new() -> Optimizer {
+        Optimizer { keep_groups: true }
+    }
+
+    // Keep all groups so captures of the simplified tree are the same
+    // On by default, off removes groups matching doesn't need and changes captures
+    pub fn keep_groups(&mut self, yes: bool) -> &mut Self {
+        self.keep_groups = yes;
+        self
+    }
+
+    // Simplified copy of `tree`
+    pub fn optimize(&self, tree: &SyntaxTree) -> SyntaxTree {
+        let mut simplifier = Simplifier {
+            keep_groups: self.keep_groups,
+        };
+        // Each step keeps a valid tree, failing to build one is a bug
+        match fold(tree, &mut simplifier) {
+            Ok(simplified) => simplified,
+            Err(error) => report_fatal_error(&format!(
+                "The optimizer built an invalid tree from pattern {}\n{error}",
+                tree.source()
+            )),
+        }
+    }
+}
+
+impl SyntaxTree {
+    // Simplified copy of this tree with the same groups, so captures don't change
+    // For instance, the tree of pattern `((a+)*|ab|ac)` becomes the tree of `((a+)*|a(b|c))`
+    // see `Optimizer::keep_groups` to remove groups too
+    pub fn optimize(&self) -> SyntaxTree {
+        Optimizer::new().optimize(self)
+    }
+}
+
+struct Simplifier {
+    keep_groups: bool,
+}
+
+impl Folder for Simplifier {
+    fn fold(
+        &mut self,
+        tree: &SyntaxTree,
+        id: NodeId,
+        children: Vec<NodeId>,
+        builder: &mut TreeBuilder,
+    ) -> NodeId {
+        match tree[id].expression_type {
+            ExpressionType::Group { quantifier, .. } if !self.keep_groups => {
+                self.group(quantifier, children[0], tree, id, builder)
+            }
+            ExpressionType::Concatenation => concatenation(children, builder),
+            ExpressionType::Alternation => self.alternation(children, builder),
+            _ => builder.copy(tree, id, children),
+        }
+    }
+}
+
+impl Simplifier {
+    // Replacement of group `id` of `tree` whose expression is now `child`
+    fn group(
+        &self,
+        quantifier: Quantifier,
+        child: NodeId,
+        tree: &SyntaxTree,
+        id: NodeId,
+        builder: &mut TreeBuilder,
+    ) -> NodeId {
+        match builder.node(child).expression_type {
+            // (...) matches what's inside, ()* only the empty string
+            _ if quantifier == Quantifier::None => child,
+            ExpressionType::EmptyExpression => child,
+            // The matcher backtracks a group one repetition at a time, but not
+            // a character with `?` nor a dot with `*` or `+`, so these stay groups
+            ExpressionType::CharacterExpression {
+                value,
+                quantifier: inner_quantifier,
+            } if value.is_none()
+                || !matches!(
+                    merge(quantifier, inner_quantifier),
+                    Quantifier::ZeroOrMore | Quantifier::OneOrMore
+                ) =>
+            {
+                builder.copy(tree, id, vec![child])
+            }
+            ExpressionType::CharacterExpression {
+                value,
+                quantifier: inner_quantifier,
+            } => builder.add(Node::new(ExpressionType::CharacterExpression {
+                value,
+                quantifier: merge(quantifier, inner_quantifier),
+            })),
+            // Same as a character
+            ExpressionType::CharacterSet {
+                quantifier: inner_quantifier,
+            } if matches!(
+                merge(quantifier, inner_quantifier),
+                Quantifier::ZeroOrMore | Quantifier::OneOrMore
+            ) =>
+            {
+                let mut node = Node::new(ExpressionType::CharacterSet {
+                    quantifier: merge(quantifier, inner_quantifier),
+                });
+                node.children = builder.node(child).children.clone();
+                builder.add(node)
+            }
+            _ => builder.copy(tree, id, vec![child]),
+        }
+    }
+
+    fn alternation(&self, children: Vec<NodeId>, builder: &mut TreeBuilder) -> NodeId {
+        let mut branches: Vec<NodeId> = vec![];
+        for child in children {
+            let node = builder.node(child);
+            let items = match node.expression_type {
+                ExpressionType::Alternation
+                | ExpressionType::CharacterSet {
+                    quantifier: Quantifier::None,
+                } => node.children.clone(),
+                _ => vec![child],
+            };
+            for item in items {
+                // A branch like one before is only tried when that one failed, so it fails too
+                // unless it has groups to keep, their numbers would change
+                let repeated = !(self.keep_groups && has_group(item, builder))
+                    && branches
+                        .iter()
+                        .any(|branch| same_expression(*branch, item, builder));
+                if !repeated {
+                    branches.push(item);
+                }
+            }
+        }
+        factor(branches, builder)
+    }
+}
+
+// Quantifier of `(a?)*` and the like: repeating a repeated character
+// matches it any number of times, unless both quantifiers are the same
+fn merge(outer: Quantifier, inner: Quantifier) -> Quantifier {
+    match inner {
+        Quantifier::None => outer,
+        _ if inner == outer => outer,
+        _ => Quantifier::ZeroOrMore,
+    }
+}
+
+fn has_group(id: NodeId, builder: &TreeBuilder) -> bool {
+    let node = builder.node(id);
+    matches!(node.expression_type, ExpressionType::Group { .. })
+        || node.children.iter().any(|child| has_group(*child, builder))
+}
+
+// Concatenation of `items`, flattened and without empty expressions
+fn concatenation(items: Vec<NodeId>, builder: &mut TreeBuilder) -> NodeId {
+    let mut flat = vec![];
+    for item in items {
+        let node = builder.node(item);
+        match node.expression_type {
+            ExpressionType::Concatenation => flat.extend(node.children.iter().copied()),
+            ExpressionType::EmptyExpression => {}
+            _ => flat.push(item),
+        }
+    }
+    match flat.len() {
+        0 => builder.add(Node::new(ExpressionType::EmptyExpression)),
+        1 => flat[0],
+        _ => {
+            let mut node = Node::new(ExpressionType::Concatenation);
+            node.children = flat;
+            builder.add(node)
+        }
+    }
+}
+
+// Alternation of `branches`, branches next to each other starting with
+// the same characters written as those characters followed by an alternation
+// Only characters without a quantifier are factored: they match one way only,
+// so the branches are still tried in the same order at the same positions
+fn factor(branches: Vec<NodeId>, builder: &mut TreeBuilder) -> NodeId {
+    let sequences: Vec<Vec<NodeId>> = branches
+        .iter()
+        .map(|branch| {
+            let node = builder.node(*branch);
+            match node.expression_type {
+                ExpressionType::Concatenation => node.children.clone(),
+                _ => vec![*branch],
+            }
+        })
+        .collect();
+
+    let mut factored = vec![];
+    let mut start = 0;
+    while start < sequences.len() {
+        let first = sequences[start].first().copied();
+        let mut end = start + 1;
+        while end < sequences.len()
+            && first.is_some_and(|first| {
+                is_plain_character(first, builder)
+                    && sequences[end]
+                        .first()
+                        .is_some_and(|other| same_expression(first, *other, builder))
+            })
+        {
+            end += 1;
+        }
+        if end - start == 1 {
+            factored.push(branches[start]);
+            start = end;
+            continue;
+        }
+
+        let run = &sequences[start..end];
+        let mut prefix_length = 1;
+        while run.iter().all(|sequence| {
+            sequence.len() > prefix_length
+                && is_plain_character(run[0][prefix_length], builder)
+                && same_expression(run[0][prefix_length], sequence[prefix_length], builder)
+        }) {
+            prefix_length += 1;
+        }
+        let mut rests = vec![];
+        for sequence in run {
+            let rest = concatenation(sequence[prefix_length..].to_vec(), builder);
+            let node = builder.node(rest);
+            match node.expression_type {
+                ExpressionType::Alternation
+                | ExpressionType::CharacterSet {
+                    quantifier: Quantifier::None,
+                } => rests.extend(node.children.iter().copied()),
+                _ => rests.push(rest),
+            }
+        }
+        let rest = factor(rests, builder);
+        let mut items = run[0][..prefix_length].to_vec();
+        items.push(rest);
+        factored.push(concatenation(items, builder));
+        start = end;
+    }
+
+    let factored = character_sets(factored, builder);
+    match factored.len() {
+        1 => factored[0],
+        _ => {
+            let mut node = Node::new(ExpressionType::Alternation);
+            node.children = factored;
+            builder.add(node)
+        }
+    }
+}
+
+// `branches` with each run of two or more single characters merged into a character set
+// Each of them matches one character, so the first one matching does the same as the set
+fn character_sets(branches: Vec<NodeId>, builder: &mut TreeBuilder) -> Vec<NodeId> {
+    let mut merged = vec![];
+    let mut start = 0;
+    while start < branches.len() {
+        let mut end = start;
+        while end < branches.len() && is_plain_character(branches[end], builder) {
+            end += 1;
+        }
+        if end - start < 2 {
+            merged.push(branches[start]);
+            start = end.max(start + 1);
+            continue;
+        }
+
+        let run = &branches[start..end];
+        let dot = run.iter().find(|branch| {
+            matches!(
+                builder.node(**branch).expression_type,
+                ExpressionType::CharacterExpression { value: None, .. }
+            )
+        });
+        match dot {
+            // Any character, like the dot
+            Some(dot) => merged.push(*dot),
+            None => {
+                let mut node = Node::new(ExpressionType::CharacterSet {
+                    quantifier: Quantifier::None,
+                });
+                node.children = run.to_vec();
+                merged.push(builder.add(node));
+            }
+        }
+        start = end;
+    }
+    merged
+}
+
+fn is_plain_character(id: NodeId, builder: &TreeBuilder) -> bool {
+    matches!(
+        builder.node(id).expression_type,
+        ExpressionType::CharacterExpression {
+            quantifier: Quantifier::None,
+            ..
+        }
+    )
+}
+
+// Do expressions `a` and `b` match the same way? Group numbers don't matter
+fn same_expression(a: NodeId, b: NodeId, builder: &TreeBuilder) -> bool {
+    let (a, b) = (builder.node(a), builder.node(b));
+    let same_type = match (a.expression_type, b.expression_type) {
+        (
+            ExpressionType::Group { quantifier, .. },
+            ExpressionType::Group {
+                quantifier: other, ..
+            },
+        ) => quantifier == other && a.group_name == b.group_name,
+        (expression_type, other) => expression_type == other,
+    };
+    same_type
+        && a.children.len() == b.children.len()
+        && a.children
+            .iter()
+            .zip(&b.children)
+            .all(|(a, b)| same_expression(*a, *b, builder))
+}
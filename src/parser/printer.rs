@@ -0,0 +1,145 @@
+// Print a syntax tree back as a pattern string
+// The printed pattern is canonical: metacharacters are escaped, control characters
+// are written as escape sequences and no parentheses are added except where needed
+// so parsing a printed tree gives back the same tree (except spans and source)
+//
+// Trees the parser can not give, like a concatenation inside a concatenation,
+// are printed as a pattern matching the same strings, like a flat concatenation
+// An alternation inside a concatenation is enclosed in a new group
+// A character set is printed as an alternation of its characters, enclosed
+// in a new group with its quantifier if it has one, like `(a|b)*`
+
+use std::ops::Range;
+
+use super::syntax_tree::*;
+use super::visit::{walk, Visitor, Walk};
+use crate::scanner::{is_metacharacter, CONTROL_ESCAPES};
+
+impl SyntaxTree {
+    // Canonical pattern of this tree
+    // For instance, the tree of pattern `(\x41|b)\.` is printed as `(A|b)\.`
+    pub fn to_pattern(&self) -> String {
+        self.print().0
+    }
+
+    // Canonical pattern of this tree along with span of each expression in it
+    // indexed by node id
+    pub(crate) fn print(&self) -> (String, Vec<Range<usize>>) {
+        let mut printer = Printer {
+            pattern: String::new(),
+            length: 0,
+            spans: vec![0..0; self.len()],
+        };
+        walk(self, &mut printer);
+        (printer.pattern, printer.spans)
+    }
+}
+
+struct Printer {
+    pattern: String,
+    // How many characters field `pattern` has
+    length: usize,
+    spans: Vec<Range<usize>>,
+}
+
+impl Printer {
+    fn push(&mut self, ch: char) {
+        self.pattern.push(ch);
+        self.length += 1;
+    }
+
+    fn push_str(&mut self, string: &str) {
+        self.pattern.push_str(string);
+        self.length += string.chars().count();
+    }
+
+    // Write `ch` so that the scanner reads it as a character to match
+    fn push_character(&mut self, ch: char) {
+        if is_metacharacter(ch) {
+            self.push('\\');
+            self.push(ch);
+        } else if let Some((escape, _)) = CONTROL_ESCAPES.iter().find(|(_, value)| *value == ch) {
+            self.push('\\');
+            self.push(*escape);
+        } else if ch.is_control() {
+            self.push_str(&format!("\\u{{{:X}}}", ch as u32));
+        } else {
+            self.push(ch);
+        }
+    }
+
+    // Is `id` an alternation which needs parentheses to be read as one expression?
+    fn needs_group(tree: &SyntaxTree, id: NodeId) -> bool {
+        let node = &tree[id];
+        let parent_concatenation = node
+            .parent
+            .is_some_and(|parent| tree[parent].expression_type == ExpressionType::Concatenation);
+        let alternation = matches!(
+            node.expression_type,
+            ExpressionType::Alternation
+                | ExpressionType::CharacterSet {
+                    quantifier: Quantifier::None
+                }
+        );
+        alternation && parent_concatenation
+    }
+}
+
+impl Visitor for Printer {
+    fn pre(&mut self, tree: &SyntaxTree, id: NodeId) -> Walk {
+        let node = &tree[id];
+        // Branches after the first one are separated by |
+        if let Some(parent) = node.parent {
+            let parent = &tree[parent];
+            let branches = matches!(
+                parent.expression_type,
+                ExpressionType::Alternation | ExpressionType::CharacterSet { .. }
+            );
+            if branches && parent.children[0] != id {
+                self.push('|');
+            }
+        }
+        if Self::needs_group(tree, id) {
+            self.push('(');
+        }
+
+        let start = self.length;
+        match node.expression_type {
+            ExpressionType::CharacterExpression {
+                value: Some(value), ..
+            } => self.push_character(value),
+            ExpressionType::CharacterExpression { value: None, .. } => self.push('.'),
+            ExpressionType::Group { .. } => match &node.group_name {
+                Some(name) => self.push_str(&format!("(?<{name}>")),
+                None => self.push('('),
+            },
+            ExpressionType::CharacterSet { quantifier } if quantifier != Quantifier::None => {
+                self.push('(')
+            }
+            _ => {}
+        }
+        self.spans[id.index()].start = start;
+        Walk::Continue
+    }
+
+    fn post(&mut self, tree: &SyntaxTree, id: NodeId) {
+        match tree[id].expression_type {
+            ExpressionType::CharacterExpression { quantifier, .. } => {
+                self.push_str(&quantifier.to_string())
+            }
+            ExpressionType::Group { quantifier, .. } => {
+                self.push(')');
+                self.push_str(&quantifier.to_string());
+            }
+            ExpressionType::CharacterSet { quantifier } if quantifier != Quantifier::None => {
+                self.push(')');
+                self.push_str(&quantifier.to_string());
+            }
+            _ => {}
+        }
+        self.spans[id.index()].end = self.length;
+        if Self::needs_group(tree, id) {
+            self.push(')');
+        }
+    }
+}
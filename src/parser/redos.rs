@@ -0,0 +1,785 @@
+// Find patterns which may take the backtracking matcher exponential or polynomial time
+// on some texts (regular expression denial of service, ReDoS), without running them
+//
+// The pattern is seen as an automaton whose states are its characters: a transition goes
+// from a character to each character which may be matched right after it, and is made
+// by the concatenation or the repeated expression (with * or +) allowing it
+// - When the same text can go around a loop of this automaton in two different ways,
+//   the matcher may try exponentially many ways before failing, like `(a+)+` or `(a|a)*`
+// - When the same text can go around a loop, then to another loop and around it,
+//   the matcher may try polynomially many ways, like `a*a*`
+// Only single characters are tried for the second case, so `(ab)*(ab)*` isn't reported
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::ops::Range;
+
+use super::syntax_tree::*;
+use crate::format_error;
+
+// How many steps looking for exponential or for polynomial backtracking may take
+// before giving up with a `TooComplex` warning
+const STEPS_LIMIT: usize = 1 << 22;
+
+// Which backtracking shape was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BacktrackingWarningKind {
+    // A repeated expression directly inside another one, like `(a+)+` or `(a*b*)*`
+    NestedQuantifiers,
+    // A repeated expression whose repetitions can match the same text in different ways,
+    // like `(a|a)*`, `(a|aa)*` or `(a+a+)+`
+    AmbiguousRepetition,
+    // Repeated expressions too many to look for the other shapes in reasonable time,
+    // like a long concatenation of optional characters repeated
+    TooComplex,
+    // Two repeated expressions one after the other matching the same text, like `a*a*`
+    OverlappingQuantifiers,
+}
+
+impl BacktrackingWarningKind {
+    // Can matching take exponential time? Otherwise polynomial time
+    // A pattern too complex to check may
+    pub fn is_exponential(&self) -> bool {
+        !matches!(self, Self::OverlappingQuantifiers)
+    }
+}
+
+// A part of a pattern which may backtrack too much
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BacktrackingWarning {
+    // Which shape was found
+    pub kind: BacktrackingWarningKind,
+    // Characters of the pattern string making that shape
+    pub span: Range<usize>,
+    // Human readable warning produced by `format_error`
+    // with carets `^` below the repeated expressions
+    pub message: String,
+}
+
+impl Display for BacktrackingWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl SyntaxTree {
+    // Parts of this pattern which may backtrack exponentially or polynomially,
+    // in the order they appear in the pattern
+    // For instance, `x(a+)+y` gives a `NestedQuantifiers` warning spanning `(a+)+`
+    pub fn backtracking_warnings(&self) -> Vec<BacktrackingWarning> {
+        let automaton = Automaton::new(self);
+        let mut warnings = vec![];
+        for (span, kind) in automaton.exponential(self) {
+            warnings.push(warning(self, kind, span.clone(), &[span]));
+        }
+        let pairs = match automaton.polynomial(self) {
+            Some(pairs) => pairs,
+            None => {
+                let span = automaton.widest_loop(self, 0..automaton.blocks.len());
+                let reported = warnings.iter().any(|warning| {
+                    warning.kind == BacktrackingWarningKind::TooComplex
+                        && Some(&warning.span) == span.as_ref()
+                });
+                if let Some(span) = span.filter(|_| !reported) {
+                    warnings.push(warning(
+                        self,
+                        BacktrackingWarningKind::TooComplex,
+                        span.clone(),
+                        &[span],
+                    ));
+                }
+                vec![]
+            }
+        };
+        for (first, second) in pairs {
+            // Already reported when both loops are inside an exponential one
+            let span = first.start.min(second.start)..first.end.max(second.end);
+            let reported = warnings
+                .iter()
+                .any(|warning| warning.span.start <= span.start && span.end <= warning.span.end);
+            if !reported {
+                warnings.push(warning(
+                    self,
+                    BacktrackingWarningKind::OverlappingQuantifiers,
+                    span,
+                    &[first, second],
+                ));
+            }
+        }
+        warnings.sort_by_key(|warning| (warning.span.start, warning.span.end, warning.kind));
+        warnings
+    }
+}
+
+fn warning(
+    tree: &SyntaxTree,
+    kind: BacktrackingWarningKind,
+    span: Range<usize>,
+    marked: &[Range<usize>],
+) -> BacktrackingWarning {
+    let (what, hint) = match kind {
+        BacktrackingWarningKind::NestedQuantifiers => (
+            "Nested quantifiers may backtrack exponentially",
+            "Repeat the inner expression only once, like `(a+)+` written `a+`",
+        ),
+        BacktrackingWarningKind::AmbiguousRepetition => (
+            "Repeated expression matching the same text in different ways may backtrack exponentially",
+            "Make its branches or parts match different text, like `(a|aa)*` written `a*`",
+        ),
+        BacktrackingWarningKind::TooComplex => (
+            "Repeated expression too complex to check for backtracking",
+            "Write it with fewer optional parts or branches",
+        ),
+        BacktrackingWarningKind::OverlappingQuantifiers => (
+            "Quantifiers matching the same text one after the other may backtrack polynomially",
+            "Make one of them unable to match what the other matches, like `a*a*` written `a*`",
+        ),
+    };
+    let mut marked = marked.to_vec();
+    marked.sort_by_key(|marked| marked.start);
+    let markers = marked
+        .iter()
+        .map(|marked| {
+            let length = std::cmp::min(marked.len(), u8::MAX as usize) as u8;
+            (marked.start, length)
+        })
+        .collect::<Vec<_>>();
+    BacktrackingWarning {
+        kind,
+        span: span.clone(),
+        message: format_error(
+            &format!("Warning in position {}: {what}", span.start),
+            tree.source(),
+            &markers,
+            hint,
+        ),
+    }
+}
+
+// Transitions from each state of `from` to the states starting the positions of a junction
+// entered from `start`, made by the same concatenation or repeated expression
+#[derive(Debug, Clone)]
+struct Block {
+    from: Vec<usize>,
+    junction: usize,
+    start: usize,
+    maker: NodeId,
+}
+
+// States blocks enter, by position: the children of a concatenation, whose blocks
+// all share its junction so a run of nullable children doesn't make a block
+// for each pair of them, or the repeated expression of a loop, in one position
+#[derive(Debug, Clone)]
+struct Junction {
+    // States starting each position
+    starts: Vec<Vec<usize>>,
+    // Last position entered along with each one, the first one not nullable from it
+    reach: Vec<usize>,
+    // (position, state) of the states matching each character, None for the dot,
+    // in the order of positions
+    index: HashMap<Option<char>, Vec<(usize, usize)>>,
+}
+
+impl Junction {
+    fn new(starts: Vec<Vec<usize>>, reach: Vec<usize>, values: &[Option<char>]) -> Junction {
+        let mut index: HashMap<Option<char>, Vec<(usize, usize)>> = HashMap::new();
+        for (position, states) in starts.iter().enumerate() {
+            for state in states {
+                index
+                    .entry(values[*state])
+                    .or_default()
+                    .push((position, *state));
+            }
+        }
+        Junction {
+            starts,
+            reach,
+            index,
+        }
+    }
+
+    // Positions entered from `start`
+    fn entered(&self, start: usize) -> Range<usize> {
+        start..self.reach[start] + 1
+    }
+}
+
+// Characters of a pattern (its states) and transitions between them
+// The pattern is matched by going through transitions from a character
+// to the next one, matching each character reached
+struct Automaton {
+    // Character matched by each state, None for the dot
+    values: Vec<Option<char>>,
+    blocks: Vec<Block>,
+    junctions: Vec<Junction>,
+    // Blocks of transitions leaving each state
+    leaving: Vec<Vec<usize>>,
+}
+
+// A vertex of the graph of pairs of states reached by the same text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Vertex {
+    Pair(usize, usize),
+    // Between two pairs, going through a transition of each block
+    Blocks(usize, usize),
+}
+
+impl Automaton {
+    fn new(tree: &SyntaxTree) -> Automaton {
+        let mut values = vec![];
+        let mut state_of = HashMap::new();
+        for (index, node) in tree.nodes().iter().enumerate() {
+            if let ExpressionType::CharacterExpression { value, .. } = node.expression_type {
+                state_of.insert(index, values.len());
+                values.push(value);
+            }
+        }
+
+        // States an expression may start and end with, and can it match the empty string?
+        // Children come after their parent in the nodes of a tree
+        let count = tree.len();
+        let mut first: Vec<Vec<usize>> = vec![vec![]; count];
+        let mut last: Vec<Vec<usize>> = vec![vec![]; count];
+        let mut nullable = vec![false; count];
+        let mut blocks = vec![];
+        let mut junctions = vec![];
+        for index in (0..count).rev() {
+            let id = NodeId::new(index);
+            let node = &tree[id];
+            match node.expression_type {
+                ExpressionType::EmptyExpression => nullable[index] = true,
+                ExpressionType::CharacterExpression { quantifier, .. } => {
+                    let state = state_of[&index];
+                    first[index] = vec![state];
+                    last[index] = vec![state];
+                    nullable[index] =
+                        matches!(quantifier, Quantifier::ZeroOrOne | Quantifier::ZeroOrMore);
+                    if matches!(quantifier, Quantifier::ZeroOrMore | Quantifier::OneOrMore) {
+                        blocks.push(Block {
+                            from: vec![state],
+                            junction: junctions.len(),
+                            start: 0,
+                            maker: id,
+                        });
+                        junctions.push(Junction::new(vec![vec![state]], vec![0], &values));
+                    }
+                }
+                ExpressionType::Group { quantifier, .. } => {
+                    let child = node.children[0].index();
+                    first[index] = first[child].clone();
+                    last[index] = last[child].clone();
+                    nullable[index] = nullable[child]
+                        || matches!(quantifier, Quantifier::ZeroOrOne | Quantifier::ZeroOrMore);
+                    if matches!(quantifier, Quantifier::ZeroOrMore | Quantifier::OneOrMore)
+                        && !first[child].is_empty()
+                    {
+                        blocks.push(Block {
+                            from: last[child].clone(),
+                            junction: junctions.len(),
+                            start: 0,
+                            maker: id,
+                        });
+                        junctions.push(Junction::new(vec![first[child].clone()], vec![0], &values));
+                    }
+                }
+                // Like a group of an alternation of its characters
+                ExpressionType::CharacterSet { quantifier } => {
+                    let states = node
+                        .children
+                        .iter()
+                        .map(|child| state_of[&child.index()])
+                        .collect::<Vec<_>>();
+                    first[index] = states.clone();
+                    last[index] = states.clone();
+                    nullable[index] =
+                        matches!(quantifier, Quantifier::ZeroOrOne | Quantifier::ZeroOrMore);
+                    if matches!(quantifier, Quantifier::ZeroOrMore | Quantifier::OneOrMore) {
+                        blocks.push(Block {
+                            from: states.clone(),
+                            junction: junctions.len(),
+                            start: 0,
+                            maker: id,
+                        });
+                        junctions.push(Junction::new(vec![states], vec![0], &values));
+                    }
+                }
+                ExpressionType::Alternation => {
+                    let children = node.children.iter().map(|child| child.index());
+                    first[index] = children
+                        .clone()
+                        .flat_map(|child| first[child].clone())
+                        .collect();
+                    last[index] = children
+                        .clone()
+                        .flat_map(|child| last[child].clone())
+                        .collect();
+                    nullable[index] = children.clone().any(|child| nullable[child]);
+                }
+                ExpressionType::Concatenation => {
+                    let children = node.children.iter().map(|child| child.index());
+                    let children = children.collect::<Vec<_>>();
+                    // Each child goes to the next ones up to the first one not nullable
+                    let mut reach = vec![0; children.len()];
+                    for position in (0..children.len()).rev() {
+                        reach[position] =
+                            match nullable[children[position]] && position + 1 < children.len() {
+                                true => reach[position + 1],
+                                false => position,
+                            };
+                    }
+                    let starts = children
+                        .iter()
+                        .map(|child| first[*child].clone())
+                        .collect::<Vec<_>>();
+                    // How many states the positions before each one start with
+                    let mut before = vec![0];
+                    for states in &starts {
+                        before.push(before[before.len() - 1] + states.len());
+                    }
+                    for position in 1..children.len() {
+                        let from = &last[children[position - 1]];
+                        if !from.is_empty() && before[reach[position] + 1] > before[position] {
+                            blocks.push(Block {
+                                from: from.clone(),
+                                junction: junctions.len(),
+                                start: position,
+                                maker: id,
+                            });
+                        }
+                    }
+                    junctions.push(Junction::new(starts, reach, &values));
+
+                    nullable[index] = children.iter().all(|child| nullable[*child]);
+                    // Children up to the first one not nullable, from either end
+                    let starting = match children.iter().position(|child| !nullable[*child]) {
+                        Some(position) => &children[..=position],
+                        None => &children[..],
+                    };
+                    let ending = match children.iter().rposition(|child| !nullable[*child]) {
+                        Some(position) => &children[position..],
+                        None => &children[..],
+                    };
+                    first[index] = starting
+                        .iter()
+                        .flat_map(|child| first[*child].clone())
+                        .collect();
+                    last[index] = ending
+                        .iter()
+                        .flat_map(|child| last[*child].clone())
+                        .collect();
+                }
+            }
+        }
+
+        let mut leaving = vec![vec![]; values.len()];
+        for (index, block) in blocks.iter().enumerate() {
+            for state in &block.from {
+                leaving[*state].push(index);
+            }
+        }
+        Automaton {
+            values,
+            blocks,
+            junctions,
+            leaving,
+        }
+    }
+
+    // States a block enters
+    fn entered(&self, block: usize) -> impl Iterator<Item = usize> + '_ {
+        let block = &self.blocks[block];
+        let junction = &self.junctions[block.junction];
+        junction.starts[junction.entered(block.start)]
+            .iter()
+            .flatten()
+            .copied()
+    }
+
+    // States a block enters which can match `value`: the ones matching it and dots,
+    // only dots for None
+    fn entered_matching(&self, block: usize, value: Option<char>) -> Vec<usize> {
+        let block = &self.blocks[block];
+        let junction = &self.junctions[block.junction];
+        let positions = junction.entered(block.start);
+        let values = match value {
+            Some(_) => vec![value, None],
+            None => vec![None],
+        };
+        let mut states = vec![];
+        for value in values {
+            if let Some(entries) = junction.index.get(&value) {
+                let first = entries.partition_point(|(position, _)| *position < positions.start);
+                states.extend(
+                    entries[first..]
+                        .iter()
+                        .take_while(|(position, _)| *position < positions.end)
+                        .map(|(_, state)| *state),
+                );
+            }
+        }
+        states
+    }
+
+    // Graph of states, then blocks, then positions of junctions: a state goes to blocks
+    // leaving it, a block to the position it enters first, and a position to the states
+    // starting it and to the next position if entered along with it
+    fn graph(&self) -> Vec<Vec<usize>> {
+        let states = self.values.len();
+        // First vertex of each junction
+        let mut offsets = vec![];
+        let mut count = states + self.blocks.len();
+        for junction in &self.junctions {
+            offsets.push(count);
+            count += junction.starts.len();
+        }
+        let mut adjacent = vec![vec![]; count];
+        for (state, leaving) in self.leaving.iter().enumerate() {
+            adjacent[state] = leaving.iter().map(|block| states + block).collect();
+        }
+        for (index, block) in self.blocks.iter().enumerate() {
+            adjacent[states + index] = vec![offsets[block.junction] + block.start];
+        }
+        for (junction, offset) in self.junctions.iter().zip(offsets) {
+            for (position, starting) in junction.starts.iter().enumerate() {
+                let vertex = offset + position;
+                adjacent[vertex] = starting.clone();
+                if junction.reach[position] > position {
+                    adjacent[vertex].push(vertex + 1);
+                }
+            }
+        }
+        adjacent
+    }
+
+    // The widest repeated expression among makers of `blocks`
+    fn widest_loop(
+        &self,
+        tree: &SyntaxTree,
+        blocks: impl Iterator<Item = usize>,
+    ) -> Option<Range<usize>> {
+        blocks
+            .map(|block| self.blocks[block].maker)
+            .filter(|maker| tree[*maker].expression_type != ExpressionType::Concatenation)
+            .map(|maker| tree[maker].span.clone())
+            .max_by_key(|span| (span.len(), std::cmp::Reverse(span.start)))
+    }
+
+    // Loops which the same text can go around in two different ways
+    // Pairs of states reached by the same text from the same state are tracked:
+    // a loop of pairs back to a state with itself, which took two different
+    // transitions on the way, is two different ways around a loop
+    fn exponential(&self, tree: &SyntaxTree) -> Vec<(Range<usize>, BacktrackingWarningKind)> {
+        // A loop stays inside a strongly connected part of the automaton
+        let states = self.values.len();
+        let parts = strongly_connected(&self.graph());
+        let inside = |state: usize, block: usize| parts[state] == parts[states + block];
+
+        let mut vertices = vec![];
+        let mut index_of = HashMap::new();
+        for state in 0..states {
+            index_of.insert(Vertex::Pair(state, state), vertices.len());
+            vertices.push(Vertex::Pair(state, state));
+        }
+        // (from, to, does it take two different transitions?)
+        let mut edges = vec![];
+        let mut steps = 0;
+        let mut next = 0;
+        while next < vertices.len() {
+            if steps > STEPS_LIMIT {
+                let span = self.widest_loop(tree, 0..self.blocks.len());
+                return span
+                    .map(|span| (span, BacktrackingWarningKind::TooComplex))
+                    .into_iter()
+                    .collect();
+            }
+            let mut targets = vec![];
+            match vertices[next] {
+                Vertex::Pair(a, b) => {
+                    for first in self.leaving[a].iter().filter(|block| inside(a, **block)) {
+                        for second in self.leaving[b].iter().filter(|block| inside(b, **block)) {
+                            let different = first != second || a != b;
+                            targets.push((Vertex::Blocks(*first, *second), different));
+                        }
+                    }
+                }
+                Vertex::Blocks(first, second) => {
+                    for a in self.entered(first).filter(|a| inside(*a, first)) {
+                        steps += 1;
+                        if steps > STEPS_LIMIT {
+                            break;
+                        }
+                        // A dot overlaps any state, a character the ones matching it
+                        let entered = match self.values[a] {
+                            Some(_) => self.entered_matching(second, self.values[a]),
+                            None => self.entered(second).collect(),
+                        };
+                        steps += entered.len();
+                        for b in entered.into_iter().filter(|b| inside(*b, second)) {
+                            // Different blocks already took different transitions
+                            targets.push((Vertex::Pair(a, b), first == second && a != b));
+                        }
+                    }
+                }
+            }
+            steps += targets.len();
+            for (vertex, different) in targets {
+                let to = *index_of.entry(vertex).or_insert_with(|| {
+                    vertices.push(vertex);
+                    vertices.len() - 1
+                });
+                edges.push((next, to, different));
+            }
+            next += 1;
+        }
+
+        let mut adjacent = vec![vec![]; vertices.len()];
+        for (from, to, _) in &edges {
+            adjacent[*from].push(*to);
+        }
+        let components = strongly_connected(&adjacent);
+        let mut has_same_state = vec![false; vertices.len()];
+        let mut diverging = vec![false; vertices.len()];
+        for (index, vertex) in vertices.iter().enumerate() {
+            if let Vertex::Pair(a, b) = vertex {
+                has_same_state[components[index]] |= a == b;
+            }
+        }
+        // Does each pair of blocks go from a state with itself, and to one?
+        let mut from_same_state = vec![false; vertices.len()];
+        let mut to_same_state = vec![false; vertices.len()];
+        for (from, to, different) in &edges {
+            if components[*from] != components[*to] {
+                continue;
+            }
+            diverging[components[*from]] |= *different;
+            match (vertices[*from], vertices[*to]) {
+                (Vertex::Pair(a, b), _) => from_same_state[*to] |= a == b,
+                (_, Vertex::Pair(a, b)) => to_same_state[*from] |= a == b,
+                _ => {}
+            }
+        }
+
+        // Blocks of each component going two different ways around a loop
+        // and are two of them repeating the same?
+        let mut loops: HashMap<usize, (Vec<usize>, bool)> = HashMap::new();
+        for (index, vertex) in vertices.iter().enumerate() {
+            let component = components[index];
+            if let Vertex::Blocks(first, second) = vertex {
+                if has_same_state[component] && diverging[component] {
+                    let (blocks, nested) = loops.entry(component).or_default();
+                    blocks.extend([*first, *second]);
+                    // Two repeated expressions going from a state to the same state
+                    // are two quantifiers repeating the same
+                    let (first, second) = (self.blocks[*first].maker, self.blocks[*second].maker);
+                    *nested |= first != second
+                        && tree[first].expression_type != ExpressionType::Concatenation
+                        && tree[second].expression_type != ExpressionType::Concatenation
+                        && from_same_state[index]
+                        && to_same_state[index];
+                }
+            }
+        }
+
+        let mut spans: HashMap<Range<usize>, BacktrackingWarningKind> = HashMap::new();
+        for (blocks, nested) in loops.into_values() {
+            let kind = match nested {
+                true => BacktrackingWarningKind::NestedQuantifiers,
+                false => BacktrackingWarningKind::AmbiguousRepetition,
+            };
+            if let Some(span) = self.widest_loop(tree, blocks.into_iter()) {
+                let entry = spans.entry(span).or_insert(kind);
+                *entry = (*entry).min(kind);
+            }
+        }
+        spans.into_iter().collect()
+    }
+
+    // Pairs of loops the same text can go around, one after the other,
+    // or None when looking for them takes more than `STEPS_LIMIT` steps
+    // Each character of a loop is tried alone, going only through states matching it
+    fn polynomial(&self, tree: &SyntaxTree) -> Option<Vec<(Range<usize>, Range<usize>)>> {
+        // States in loops, whose strongly connected part has more than themselves
+        let parts = strongly_connected(&self.graph());
+        let mut sizes = vec![0; parts.len()];
+        for part in &parts {
+            sizes[*part] += 1;
+        }
+        let states = self.values.len();
+        // None stands for a character the pattern doesn't have, which only dots match
+        let mut looping: HashMap<Option<char>, Vec<usize>> = HashMap::new();
+        for state in (0..states).filter(|state| sizes[parts[*state]] > 1) {
+            looping.entry(self.values[state]).or_default().push(state);
+        }
+        // Dots going around loops go on through states matching any character
+        let mut characters = match looping.contains_key(&None) {
+            true => self.values.clone(),
+            false => looping.keys().copied().collect(),
+        };
+        characters.sort();
+        characters.dedup();
+
+        let mut steps = 0;
+        let mut pairs = vec![];
+        // (pass, vertex) of each state and block last seen by a pass, states then blocks
+        let mut seen_by = vec![(usize::MAX, 0); states + self.blocks.len()];
+        for (pass, character) in characters.into_iter().enumerate() {
+            // Graph of states matching the character and blocks leaving them,
+            // reached from the ones in loops
+            let mut roots = looping.get(&character).cloned().unwrap_or_default();
+            if character.is_some() {
+                roots.extend(looping.get(&None).into_iter().flatten());
+            }
+            let mut vertices = vec![];
+            for root in roots {
+                seen_by[root] = (pass, vertices.len());
+                vertices.push(root);
+            }
+            let mut adjacent = vec![];
+            while adjacent.len() < vertices.len() {
+                let vertex = vertices[adjacent.len()];
+                let targets = match vertex < states {
+                    true => self.leaving[vertex]
+                        .iter()
+                        .map(|block| states + block)
+                        .collect(),
+                    false => self.entered_matching(vertex - states, character),
+                };
+                steps += targets.len() + 1;
+                if steps > STEPS_LIMIT {
+                    return None;
+                }
+                let mut local = vec![];
+                for target in targets {
+                    if seen_by[target].0 != pass {
+                        seen_by[target] = (pass, vertices.len());
+                        vertices.push(target);
+                    }
+                    local.push(seen_by[target].1);
+                }
+                adjacent.push(local);
+            }
+
+            let components = strongly_connected(&adjacent);
+            // Blocks going around each loop
+            let mut loops: HashMap<usize, Vec<usize>> = HashMap::new();
+            for (vertex, targets) in adjacent.iter().enumerate() {
+                let component = components[vertex];
+                if vertices[vertex] >= states
+                    && targets.iter().any(|to| components[*to] == component)
+                {
+                    loops
+                        .entry(component)
+                        .or_default()
+                        .push(vertices[vertex] - states);
+                }
+            }
+            let loops = loops
+                .into_iter()
+                .filter_map(|(component, blocks)| {
+                    Some((component, self.widest_loop(tree, blocks.into_iter())?))
+                })
+                .collect::<HashMap<_, _>>();
+
+            // The loop each loop reaches first, not going around other loops on the way
+            let mut members: HashMap<usize, Vec<usize>> = HashMap::new();
+            for (vertex, component) in components.iter().enumerate() {
+                if loops.contains_key(component) {
+                    members.entry(*component).or_default().push(vertex);
+                }
+            }
+            // Search each vertex was last seen by
+            let mut seen = vec![usize::MAX; adjacent.len()];
+            for (search, (component, span)) in loops.iter().enumerate() {
+                let mut stack = members[component].clone();
+                let mut reached = vec![];
+                while let Some(vertex) = stack.pop() {
+                    steps += adjacent[vertex].len() + 1;
+                    if steps > STEPS_LIMIT {
+                        return None;
+                    }
+                    for next in &adjacent[vertex] {
+                        if seen[*next] == search {
+                            continue;
+                        }
+                        seen[*next] = search;
+                        match loops.get(&components[*next]) {
+                            Some(other) if components[*next] != *component => reached.push(other),
+                            _ => stack.push(*next),
+                        }
+                    }
+                }
+                // Preferably one written after this loop
+                let nearest = reached
+                    .into_iter()
+                    .min_by_key(|other| (other.start < span.end, other.start, other.end));
+                if let Some(other) = nearest {
+                    pairs.push((span.clone(), other.clone()));
+                }
+            }
+        }
+        pairs.sort_by_key(|(first, second)| (first.start, first.end, second.start, second.end));
+        pairs.dedup();
+        Some(pairs)
+    }
+}
+
+// Strongly connected component of each vertex of a graph given by vertices adjacent
+// to each vertex (Tarjan's algorithm, without recursion)
+fn strongly_connected(adjacent: &[Vec<usize>]) -> Vec<usize> {
+    const UNVISITED: usize = usize::MAX;
+    let count = adjacent.len();
+    let mut order = vec![UNVISITED; count];
+    let mut lowest = vec![0; count];
+    let mut on_stack = vec![false; count];
+    let mut stack = vec![];
+    let mut components = vec![UNVISITED; count];
+    let mut visited = 0;
+    let mut found = 0;
+    for root in 0..count {
+        if order[root] != UNVISITED {
+            continue;
+        }
+        // (vertex, how many of its adjacent vertices were gone through)
+        let mut path = vec![(root, 0)];
+        order[root] = visited;
+        lowest[root] = visited;
+        visited += 1;
+        stack.push(root);
+        on_stack[root] = true;
+        while let Some((vertex, next)) = path.last_mut() {
+            let vertex = *vertex;
+            match adjacent[vertex].get(*next) {
+                Some(&adjacent_vertex) => {
+                    *next += 1;
+                    if order[adjacent_vertex] == UNVISITED {
+                        order[adjacent_vertex] = visited;
+                        lowest[adjacent_vertex] = visited;
+                        visited += 1;
+                        stack.push(adjacent_vertex);
+                        on_stack[adjacent_vertex] = true;
+                        path.push((adjacent_vertex, 0));
+                    } else if on_stack[adjacent_vertex] {
+                        lowest[vertex] = lowest[vertex].min(order[adjacent_vertex]);
+                    }
+                }
+                None => {
+                    path.pop();
+                    if let Some((parent, _)) = path.last() {
+                        lowest[*parent] = lowest[*parent].min(lowest[vertex]);
+                    }
+                    if lowest[vertex] == order[vertex] {
+                        while let Some(member) = stack.pop() {
+                            on_stack[member] = false;
+                            components[member] = found;
+                            if member == vertex {
+                                break;
+                            }
+                        }
+                        found += 1;
+                    }
+                }
+            }
+        }
+    }
+    components
+}
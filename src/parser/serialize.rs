@@ -0,0 +1,559 @@
+// Syntax trees saved as bytes and loaded back without parsing their patterns again
+//
+// Format version 2, all integers are little endian:
+// - Magic bytes `rgxp`, format version (u16) and flags (u16)
+//   flag 1 marks a case insensitive regex (see `Regex::to_bytes`)
+// - Pattern string, its length in bytes (u32) followed by its UTF-8 bytes
+// - How many expressions (u32) followed by all expressions in pre-order
+//   each one is a tag (u8), its span start and end (u32 character positions)
+//   and then depending on its tag:
+//   0 empty expression : nothing
+//   1 dot              : quantifier (u8)
+//   2 character        : code point (u32), quantifier (u8)
+//   3 group            : quantifier (u8), name length in bytes (u32, 0 if unnamed), name
+//   4 concatenation    : how many children (u32)
+//   5 alternation      : how many children (u32)
+//   6 character set    : quantifier (u8), how many children (u32)
+//   Quantifiers are 0 for none, 1 for ?, 2 for * and 3 for +
+// Ids of parents and children and group numbers follow from the order of expressions
+//
+// Version 1 is the same without tag 6, character sets only optimized trees have
+// Bytes of version 1 still load, versions of this crate reading only version 1
+// reject bytes of version 2 as `DecodeErrorKind::UnsupportedVersion`
+//
+// Loading checks everything a tree is expected to be, so corrupted bytes are
+// reported as a `DecodeError` instead of giving a tree matching could panic on
+
+use super::syntax_tree::*;
+use super::DEFAULT_NEST_LIMIT;
+use crate::scanner::is_group_name;
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::sync::Arc;
+
+// Version written by `SyntaxTree::to_bytes`, `SyntaxTree::from_bytes` reads it and older ones
+pub const FORMAT_VERSION: u16 = 2;
+
+// Oldest version `SyntaxTree::from_bytes` reads
+const OLDEST_VERSION: u16 = 1;
+
+// First version with character sets (tag 6)
+const CHARACTER_SET_VERSION: u16 = 2;
+
+// First bytes of every saved tree
+const MAGIC: &[u8; 4] = b"rgxp";
+
+// Flag of a regex matching regardless of case
+pub(crate) const CASE_INSENSITIVE: u16 = 1;
+
+// Deepest tree loaded, a parsed tree within the default nest limit is
+// at most three expressions deeper per group: the group, an alternation and a concatenation
+const DEPTH_LIMIT: usize = 4 * DEFAULT_NEST_LIMIT;
+
+// Smallest number of bytes an expression takes, its tag and span
+const EXPRESSION_SIZE: usize = 9;
+
+// What is wrong with loaded bytes
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DecodeErrorKind {
+    // Bytes not starting with the magic bytes `rgxp`
+    UnknownFormat,
+    // A version of the format this version doesn't read, or flags it doesn't know
+    UnsupportedVersion,
+    // Bytes ending in the middle of the tree
+    UnexpectedEnd,
+    // Bytes left after the tree
+    TrailingBytes,
+    // A pattern string or a group name which is not valid UTF-8
+    InvalidUtf8,
+    // An unknown tag or quantifier, or a code point which is not a character
+    InvalidExpression,
+    // Expressions which don't make a tree, like a group with two children
+    // a span out of the pattern string, a duplicate group name or a tree too deep
+    InvalidTree,
+}
+
+// An error while loading a saved tree
+#[derive(Debug, Clone)]
+pub struct DecodeError {
+    // Which error this is
+    pub kind: DecodeErrorKind,
+    // Index of the offending byte
+    pub position: usize,
+    // Human readable error
+    pub message: String,
+}
+
+impl DecodeError {
+    fn new(kind: DecodeErrorKind, position: usize, error: &str) -> DecodeError {
+        DecodeError {
+            kind,
+            position,
+            message: format!("Invalid compiled pattern in byte {position}: {error}"),
+        }
+    }
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl SyntaxTree {
+    // This tree saved as bytes, see the module for the format
+    // Load it with `SyntaxTree::from_bytes`, in this version of this crate or a later one
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encode(self, 0)
+    }
+
+    // Load a tree saved with `SyntaxTree::to_bytes` (or `Regex::to_bytes`)
+    pub fn from_bytes(bytes: &[u8]) -> Result<SyntaxTree, DecodeError> {
+        decode(bytes).map(|(tree, _)| tree)
+    }
+}
+
+// `tree` saved as bytes with `flags`
+pub(crate) fn encode(tree: &SyntaxTree, flags: u16) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(16 + tree.source().len() + EXPRESSION_SIZE * tree.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&flags.to_le_bytes());
+    write_string(&mut bytes, tree.source());
+    write_length(&mut bytes, tree.len());
+
+    for node in tree.nodes() {
+        let tag = match node.expression_type {
+            ExpressionType::EmptyExpression => 0,
+            ExpressionType::CharacterExpression { value: None, .. } => 1,
+            ExpressionType::CharacterExpression { value: Some(_), .. } => 2,
+            ExpressionType::Group { .. } => 3,
+            ExpressionType::Concatenation => 4,
+            ExpressionType::Alternation => 5,
+            ExpressionType::CharacterSet { .. } => 6,
+        };
+        bytes.push(tag);
+        write_length(&mut bytes, node.span.start);
+        write_length(&mut bytes, node.span.end);
+        match node.expression_type {
+            ExpressionType::EmptyExpression => {}
+            ExpressionType::CharacterExpression { value, quantifier } => {
+                if let Some(value) = value {
+                    bytes.extend_from_slice(&u32::from(value).to_le_bytes());
+                }
+                bytes.push(quantifier_tag(quantifier));
+            }
+            ExpressionType::Group { quantifier, .. } => {
+                bytes.push(quantifier_tag(quantifier));
+                write_string(&mut bytes, node.group_name.as_deref().unwrap_or(""));
+            }
+            ExpressionType::Concatenation | ExpressionType::Alternation => {
+                write_length(&mut bytes, node.children.len())
+            }
+            ExpressionType::CharacterSet { quantifier } => {
+                bytes.push(quantifier_tag(quantifier));
+                write_length(&mut bytes, node.children.len())
+            }
+        }
+    }
+    bytes
+}
+
+// Tree saved in `bytes` and its flags
+pub(crate) fn decode(bytes: &[u8]) -> Result<(SyntaxTree, u16), DecodeError> {
+    let mut reader = Reader { bytes, position: 0 };
+    if reader.take(MAGIC.len()).ok() != Some(MAGIC.as_slice()) {
+        return Err(DecodeError::new(
+            DecodeErrorKind::UnknownFormat,
+            0,
+            "Not a saved syntax tree",
+        ));
+    }
+    let version = reader.u16()?;
+    if !(OLDEST_VERSION..=FORMAT_VERSION).contains(&version) {
+        return Err(DecodeError::new(
+            DecodeErrorKind::UnsupportedVersion,
+            MAGIC.len(),
+            &format!(
+                "Format version {version} is not supported, only versions {OLDEST_VERSION} to {FORMAT_VERSION} are"
+            ),
+        ));
+    }
+    let flags = reader.u16()?;
+    if flags & !CASE_INSENSITIVE != 0 {
+        return Err(DecodeError::new(
+            DecodeErrorKind::UnsupportedVersion,
+            MAGIC.len() + 2,
+            &format!("Unknown flags {flags:#x}"),
+        ));
+    }
+    let source = reader.string()?;
+    let count = reader.length()?;
+
+    // Don't trust `count` to allocate, each expression takes some bytes
+    let mut nodes: Vec<Node> = Vec::with_capacity(std::cmp::min(
+        count,
+        (bytes.len() - reader.position) / EXPRESSION_SIZE,
+    ));
+    // Where each expression starts in `bytes`, to report errors found once the tree is complete
+    let mut offsets = vec![];
+    // Expressions whose children are not all read yet, with how many are left
+    let mut open: Vec<(usize, usize)> = vec![];
+    let mut groups_count = 0;
+    for _ in 0..count {
+        let offset = reader.position;
+        let id = nodes.len();
+        let tag = reader.u8()?;
+        let span = reader.length()?..reader.length()?;
+        let mut children_count = 0;
+        let mut group_name = None;
+        let expression_type = match tag {
+            0 => ExpressionType::EmptyExpression,
+            1 => ExpressionType::CharacterExpression {
+                value: None,
+                quantifier: reader.quantifier()?,
+            },
+            2 => {
+                let position = reader.position;
+                let value = char::from_u32(reader.u32()?).ok_or_else(|| {
+                    DecodeError::new(
+                        DecodeErrorKind::InvalidExpression,
+                        position,
+                        "Code point is not a character",
+                    )
+                })?;
+                ExpressionType::CharacterExpression {
+                    value: Some(value),
+                    quantifier: reader.quantifier()?,
+                }
+            }
+            3 => {
+                let quantifier = reader.quantifier()?;
+                let name = reader.string()?;
+                if !name.is_empty() {
+                    group_name = Some(Arc::from(name));
+                }
+                children_count = 1;
+                groups_count += 1;
+                ExpressionType::Group {
+                    quantifier,
+                    index: groups_count,
+                }
+            }
+            4 | 5 => {
+                children_count = reader.length()?;
+                if tag == 4 {
+                    ExpressionType::Concatenation
+                } else {
+                    ExpressionType::Alternation
+                }
+            }
+            6 if version >= CHARACTER_SET_VERSION => {
+                let quantifier = reader.quantifier()?;
+                children_count = reader.length()?;
+                ExpressionType::CharacterSet { quantifier }
+            }
+            _ => {
+                return Err(DecodeError::new(
+                    DecodeErrorKind::InvalidExpression,
+                    offset,
+                    &format!("Unknown expression tag {tag}"),
+                ))
+            }
+        };
+
+        let mut node = Node::new(expression_type);
+        node.span = span;
+        node.group_name = group_name;
+        // Each expression after the root is the next child of the nearest open expression
+        match open.last_mut() {
+            Some((parent, left)) => {
+                *left -= 1;
+                node.parent = Some(NodeId::new(*parent));
+                nodes[*parent].children.push(NodeId::new(id));
+            }
+            None if id > 0 => {
+                return Err(DecodeError::new(
+                    DecodeErrorKind::InvalidTree,
+                    offset,
+                    "Expression after the end of the tree",
+                ))
+            }
+            None => {}
+        }
+        nodes.push(node);
+        offsets.push(offset);
+        if children_count > 0 {
+            open.push((id, children_count));
+        }
+        while let Some((_, 0)) = open.last() {
+            open.pop();
+        }
+    }
+
+    if !open.is_empty() || nodes.is_empty() {
+        return Err(DecodeError::new(
+            DecodeErrorKind::UnexpectedEnd,
+            reader.position,
+            "Missing expressions",
+        ));
+    }
+    if reader.position < bytes.len() {
+        return Err(DecodeError::new(
+            DecodeErrorKind::TrailingBytes,
+            reader.position,
+            "Bytes after the end of the tree",
+        ));
+    }
+    match check(&source, &nodes) {
+        Ok(()) => Ok((
+            SyntaxTree::from_nodes(Arc::from(source), nodes, NodeId::new(0)),
+            flags,
+        )),
+        Err((id, error)) => Err(DecodeError::new(
+            DecodeErrorKind::InvalidTree,
+            offsets[id],
+            &error,
+        )),
+    }
+}
+
+// Is `nodes` a tree of pattern `source` like the parser makes?
+// Otherwise the index of the first wrong expression and what's wrong with it
+fn check(source: &str, nodes: &[Node]) -> Result<(), (usize, String)> {
+    if nodes.is_empty() {
+        return Err((0, String::from("A tree has at least one expression")));
+    }
+    let length = source.chars().count();
+    let mut group_names = HashSet::new();
+    let mut groups_count = 0;
+    // Next expression in pre-order
+    let mut next = 0;
+    // (id, parent, depth)
+    let mut stack = vec![(0, None, 1)];
+    while let Some((id, parent, depth)) = stack.pop() {
+        if id != next {
+            return Err((
+                id,
+                format!("Expression {id} is not in pre-order, expected expression {next}"),
+            ));
+        }
+        next += 1;
+        let node = &nodes[id];
+        if node.parent != parent {
+            return Err((id, String::from("Wrong parent")));
+        }
+        if depth > DEPTH_LIMIT {
+            return Err((id, format!("Tree deeper than {DEPTH_LIMIT} expressions")));
+        }
+        if node.span.start > node.span.end || node.span.end > length {
+            return Err((
+                id,
+                format!(
+                    "Span {:?} out of a pattern of {length} characters",
+                    node.span
+                ),
+            ));
+        }
+
+        let expected = match node.expression_type {
+            ExpressionType::EmptyExpression | ExpressionType::CharacterExpression { .. } => 0..=0,
+            ExpressionType::Group { .. } => 1..=1,
+            ExpressionType::Concatenation
+            | ExpressionType::Alternation
+            | ExpressionType::CharacterSet { .. } => 1..=usize::MAX,
+        };
+        if !expected.contains(&node.children.len()) {
+            return Err((
+                id,
+                format!(
+                    "{:?} can not have {} children",
+                    node.expression_type,
+                    node.children.len()
+                ),
+            ));
+        }
+        if let Some(ExpressionType::CharacterSet { .. }) =
+            parent.map(|parent| nodes[parent.index()].expression_type)
+        {
+            if !matches!(
+                node.expression_type,
+                ExpressionType::CharacterExpression {
+                    value: Some(_),
+                    quantifier: Quantifier::None
+                }
+            ) {
+                return Err((
+                    id,
+                    String::from("A character set can only have characters without quantifier"),
+                ));
+            }
+        }
+        if let ExpressionType::Group { index, .. } = node.expression_type {
+            // Groups are numbered in the order their ( appear
+            groups_count += 1;
+            if index != groups_count {
+                return Err((id, format!("Group {index} should be group {groups_count}")));
+            }
+        }
+        if let Some(name) = &node.group_name {
+            if !matches!(node.expression_type, ExpressionType::Group { .. }) {
+                return Err((id, String::from("Only a group can have a name")));
+            }
+            if !is_group_name(name) {
+                return Err((id, format!("Invalid group name `{name}`")));
+            }
+            if !group_names.insert(name) {
+                return Err((id, format!("Duplicate group name `{name}`")));
+            }
+        }
+
+        // Pushed in reverse to be popped in order
+        for child in node.children.iter().rev() {
+            if child.index() >= nodes.len() {
+                return Err((id, format!("No expression {}", child.index())));
+            }
+            stack.push((child.index(), Some(NodeId::new(id)), depth + 1));
+        }
+    }
+    if next < nodes.len() {
+        return Err((next, format!("Expression {next} is not in the tree")));
+    }
+    Ok(())
+}
+
+fn quantifier_tag(quantifier: Quantifier) -> u8 {
+    match quantifier {
+        Quantifier::None => 0,
+        Quantifier::ZeroOrOne => 1,
+        Quantifier::ZeroOrMore => 2,
+        Quantifier::OneOrMore => 3,
+    }
+}
+
+fn write_length(bytes: &mut Vec<u8>, length: usize) {
+    let length = u32::try_from(length).expect("Syntax tree too large to be saved");
+    bytes.extend_from_slice(&length.to_le_bytes());
+}
+
+fn write_string(bytes: &mut Vec<u8>, string: &str) {
+    write_length(bytes, string.len());
+    bytes.extend_from_slice(string.as_bytes());
+}
+
+// Read saved bytes from start to end
+struct Reader<'a> {
+    bytes: &'a [u8],
+    // Index of the next byte to read
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    // Next `count` bytes
+    fn take(&mut self, count: usize) -> Result<&'a [u8], DecodeError> {
+        match self
+            .bytes
+            .get(self.position..self.position.saturating_add(count))
+        {
+            Some(taken) => {
+                self.position += count;
+                Ok(taken)
+            }
+            None => Err(DecodeError::new(
+                DecodeErrorKind::UnexpectedEnd,
+                self.bytes.len(),
+                &format!("Expected {count} more bytes"),
+            )),
+        }
+    }
+
+    fn u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, DecodeError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, DecodeError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn length(&mut self) -> Result<usize, DecodeError> {
+        Ok(self.u32()? as usize)
+    }
+
+    fn string(&mut self) -> Result<String, DecodeError> {
+        let length = self.length()?;
+        let start = self.position;
+        match std::str::from_utf8(self.take(length)?) {
+            Ok(string) => Ok(String::from(string)),
+            Err(error) => Err(DecodeError::new(
+                DecodeErrorKind::InvalidUtf8,
+                start + error.valid_up_to(),
+                "Invalid UTF-8 string",
+            )),
+        }
+    }
+
+    fn quantifier(&mut self) -> Result<Quantifier, DecodeError> {
+        let position = self.position;
+        match self.u8()? {
+            0 => Ok(Quantifier::None),
+            1 => Ok(Quantifier::ZeroOrOne),
+            2 => Ok(Quantifier::ZeroOrMore),
+            3 => Ok(Quantifier::OneOrMore),
+            tag => Err(DecodeError::new(
+                DecodeErrorKind::InvalidExpression,
+                position,
+                &format!("Unknown quantifier {tag}"),
+            )),
+        }
+    }
+}
+
+// With feature `serde`, a tree is serialized as its pattern string and its expressions
+// checked like saved bytes when deserialized
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use super::*;
+    use serde::ser::SerializeStruct;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    impl Serialize for SyntaxTree {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let mut tree = serializer.serialize_struct("SyntaxTree", 2)?;
+            tree.serialize_field("source", self.source())?;
+            tree.serialize_field("nodes", self.nodes())?;
+            tree.end()
+        }
+    }
+
+    // Fields of a tree not checked yet
+    #[derive(Deserialize)]
+    #[serde(rename = "SyntaxTree")]
+    struct UncheckedTree {
+        source: String,
+        nodes: Vec<Node>,
+    }
+
+    impl<'de> Deserialize<'de> for SyntaxTree {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let UncheckedTree { source, nodes } = UncheckedTree::deserialize(deserializer)?;
+            match check(&source, &nodes) {
+                Ok(()) => Ok(SyntaxTree::from_nodes(
+                    Arc::from(source),
+                    nodes,
+                    NodeId::new(0),
+                )),
+                Err((id, error)) => Err(serde::de::Error::custom(format!(
+                    "Invalid syntax tree in expression {id}: {error}"
+                ))),
+            }
+        }
+    }
+}
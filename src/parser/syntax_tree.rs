@@ -1,10 +1,14 @@
 // Syntax tree structs (Tokens structures)
 
-use std::collections::LinkedList;
+use std::collections::{HashSet, LinkedList};
 use std::fmt::Display;
+use std::ops::Range;
 use std::sync::{Arc, RwLock, Weak};
 
-#[derive(Debug, Clone, Copy)]
+use crate::scanner::is_group_name;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Quantifier {
     None,       // No quantifier
     ZeroOrOne,  // Quantifier ?
@@ -25,7 +29,8 @@ impl Display for Quantifier {
 }
 
 // Expression types
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ExpressionType {
     // Empty string expression
     // the expression between ( and ) in string `()`
@@ -50,21 +55,469 @@ pub enum ExpressionType {
     // something like (a|bc|x.y.z)
     Alternation,
 
-    // A grouped expression (...)
+    // A grouped expression (...) or a named one (?<name>...)
     // where `...` is another regular expression
     Group {
         quantifier: Quantifier,
+        // Groups are numbered from 1 in the order their ( appear in pattern
+        // number 0 stands for the whole match
+        index: usize,
+    },
+
+    // One character among its children, characters without quantifier
+    // and other than the dot, matched in one step
+    // Patterns have no syntax for it, the optimizer merges alternations
+    // of single characters like `a|b|c` into one
+    CharacterSet {
+        quantifier: Quantifier,
     },
 }
 
+// Index of an expression (node) in a syntax tree, see `SyntaxTree`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodeId(usize);
+
+impl NodeId {
+    pub(crate) fn new(index: usize) -> NodeId {
+        NodeId(index)
+    }
+
+    // Position of this node in `SyntaxTree::nodes`
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+// An expression of a syntax tree
+// It refers to its parent and children by their ids in the same tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Node {
+    pub expression_type: ExpressionType,
+
+    // Positions (character indices) in the pattern where
+    // this (sub)expression starts and ends (exclusive)
+    // like token positions and error positions
+    pub span: Range<usize>,
+
+    // None for the root expression
+    pub parent: Option<NodeId>,
+
+    // Children expressions, in the order they appear in the pattern
+    pub children: Vec<NodeId>,
+
+    // Name of a named group (?<name>...)
+    // None for all other expressions
+    pub group_name: Option<Arc<str>>,
+}
+
+impl Node {
+    pub fn new(expression_type: ExpressionType) -> Node {
+        Node {
+            expression_type,
+            span: 0..0,
+            parent: None,
+            children: vec![],
+            group_name: None,
+        }
+    }
+}
+
+// Expressions of a parsed pattern stored in one vector (an arena)
+// Nodes are in pre-order: the root expression comes first, then
+// each expression is followed by all expressions inside it
+// so a tree is never modified once built and can be shared freely
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxTree {
+    // Pattern string all expressions were parsed from
+    source: Arc<str>,
+    nodes: Vec<Node>,
+}
+
+impl SyntaxTree {
+    // Tree of `nodes` (in any order) whose root is `root`
+    // Nodes are reordered in pre-order and their `parent` fields are set
+    // nodes which are not inside `root` are dropped
+    pub(crate) fn from_nodes(source: Arc<str>, mut nodes: Vec<Node>, root: NodeId) -> SyntaxTree {
+        let mut ordered: Vec<Node> = Vec::with_capacity(nodes.len());
+        // (old id, new id of its parent)
+        let mut stack = vec![(root, None)];
+        while let Some((old_id, parent)) = stack.pop() {
+            let id = NodeId(ordered.len());
+            let mut node = std::mem::replace(
+                &mut nodes[old_id.0],
+                Node::new(ExpressionType::EmptyExpression),
+            );
+            node.parent = parent;
+            // Children get their new ids when they are popped
+            // pushed in reverse to be popped in order
+            for child in node.children.drain(..).rev() {
+                stack.push((child, Some(id)));
+            }
+            if let Some(NodeId(parent)) = parent {
+                ordered[parent].children.push(id);
+            }
+            ordered.push(node);
+        }
+        SyntaxTree {
+            source,
+            nodes: ordered,
+        }
+    }
+
+    // Pattern string this tree was parsed from
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    // The whole pattern
+    pub fn root(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    pub fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id.0]
+    }
+
+    // All nodes, in pre-order
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    // How many expressions this tree has
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    // Always false, even the empty pattern has an (empty) expression
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    // Approximate number of bytes this tree allocated
+    pub(crate) fn heap_size(&self) -> usize {
+        self.source.len()
+            + self
+                .nodes
+                .iter()
+                .map(|node| {
+                    std::mem::size_of::<Node>()
+                        + node.children.len() * std::mem::size_of::<NodeId>()
+                        + node.group_name.as_ref().map_or(0, |name| name.len())
+                })
+                .sum::<usize>()
+    }
+
+    // Pattern of expression `id`, its span of the source string
+    pub fn pattern(&self, id: NodeId) -> &str {
+        slice_chars(&self.source, &self.node(id).span)
+    }
+
+    // Do both trees have the same expressions, ignoring where they are in their patterns?
+    // Like trees of `a\x41` and `aA`, or a parsed tree and its printed pattern parsed again
+    pub fn same_structure(&self, other: &SyntaxTree) -> bool {
+        self.len() == other.len()
+            && self.nodes.iter().zip(&other.nodes).all(|(node, other)| {
+                node.expression_type == other.expression_type
+                    && node.parent == other.parent
+                    && node.children == other.children
+                    && node.group_name == other.group_name
+            })
+    }
+
+    // Compatibility accessor, build the syntax tree as linked `ParsedRegexp` objects
+    // The returned objects share nothing with this tree
+    pub fn to_regexp(&self) -> Arc<RwLock<ParsedRegexp>> {
+        self.regexp_of(self.root(), None)
+    }
+
+    // Linked copy of expression `id`, whose parent is `parent`
+    fn regexp_of(
+        &self,
+        id: NodeId,
+        parent: Option<Weak<RwLock<ParsedRegexp>>>,
+    ) -> Arc<RwLock<ParsedRegexp>> {
+        let node = self.node(id);
+        let mut regexp = ParsedRegexp::new(node.expression_type);
+        regexp.source = Arc::clone(&self.source);
+        regexp.span = node.span.clone();
+        regexp.parent = parent;
+        regexp.group_name = node.group_name.clone();
+        let regexp = Arc::new(RwLock::new(regexp));
+        let children = node
+            .children
+            .iter()
+            .map(|child| self.regexp_of(*child, Some(Arc::downgrade(&regexp))))
+            .collect();
+        *regexp.read().unwrap().children.write().unwrap() = children;
+        regexp
+    }
+
+    // Tree of `regexp` and all expressions inside it
+    // like one built by hand or returned by `SyntaxTree::to_regexp`
+    pub fn from_regexp(regexp: &ParsedRegexp) -> SyntaxTree {
+        let mut nodes = vec![];
+        Self::collect_nodes(regexp, &mut nodes);
+        SyntaxTree::from_nodes(Arc::clone(&regexp.source), nodes, NodeId(0))
+    }
+
+    // Add `regexp` and all expressions inside it to `nodes`, return its id
+    fn collect_nodes(regexp: &ParsedRegexp, nodes: &mut Vec<Node>) -> NodeId {
+        let id = NodeId(nodes.len());
+        nodes.push(Node {
+            expression_type: regexp.expression_type,
+            span: regexp.span.clone(),
+            parent: None,
+            children: vec![],
+            group_name: regexp.group_name.clone(),
+        });
+        let children = regexp
+            .children
+            .read()
+            .unwrap()
+            .iter()
+            .map(|child| Self::collect_nodes(&child.read().unwrap(), nodes))
+            .collect();
+        nodes[id.0].children = children;
+        id
+    }
+}
+
+// Build a syntax tree one expression at a time, children before their parent
+// like a tree rewritten by `visit::fold`
+// The source of a built tree is its canonical pattern (see `SyntaxTree::to_pattern`)
+#[derive(Debug, Clone, Default)]
+pub struct TreeBuilder {
+    nodes: Vec<Node>,
+}
+
+impl TreeBuilder {
+    pub fn new() -> TreeBuilder {
+        TreeBuilder::default()
+    }
+
+    // Add an expression, return its id
+    // Its children MUST already be added to this builder
+    // its fields `span` and `parent` are ignored
+    pub fn add(&mut self, node: Node) -> NodeId {
+        self.nodes.push(node);
+        NodeId(self.nodes.len() - 1)
+    }
+
+    // Add a copy of expression `id` of `tree` with children `children`
+    pub fn copy(&mut self, tree: &SyntaxTree, id: NodeId, children: Vec<NodeId>) -> NodeId {
+        let mut node = tree.node(id).clone();
+        node.children = children;
+        self.add(node)
+    }
+
+    // Expression `id` added to this builder
+    pub fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id.0]
+    }
+
+    // Tree made of `root` and all expressions inside it
+    // other added expressions are dropped, so a rewrite may leave replaced ones behind
+    // Groups are numbered again from 1 in the order their ( would appear in a pattern
+    // Fail if an expression has children it can not have (like a character)
+    // or lacks children it needs (a group has exactly one, an alternation and
+    // a concatenation at least one), if a character set has other children than
+    // characters without quantifier, if an expression is not added before its parent
+    // or is a child twice in the tree, or if a group name is invalid or used twice
+    pub fn build(self, root: NodeId) -> Result<SyntaxTree, BuildError> {
+        if root.0 >= self.nodes.len() {
+            return Err(BuildError::new(
+                BuildErrorKind::NotATree,
+                root,
+                "was not added to the builder",
+            ));
+        }
+        let mut is_child = vec![false; self.nodes.len()];
+        let mut group_names = HashSet::new();
+        let mut stack = vec![root];
+        while let Some(id) = stack.pop() {
+            let node = &self.nodes[id.0];
+            let expected = match node.expression_type {
+                ExpressionType::EmptyExpression | ExpressionType::CharacterExpression { .. } => {
+                    0..=0
+                }
+                ExpressionType::Group { .. } => 1..=1,
+                ExpressionType::Concatenation
+                | ExpressionType::Alternation
+                | ExpressionType::CharacterSet { .. } => 1..=usize::MAX,
+            };
+            if !expected.contains(&node.children.len()) {
+                return Err(BuildError::new(
+                    BuildErrorKind::InvalidChildren,
+                    id,
+                    &format!(
+                        "{:?} can not have {} children",
+                        node.expression_type,
+                        node.children.len()
+                    ),
+                ));
+            }
+            for child in &node.children {
+                // Children added first means no expression is inside itself
+                if child.0 >= id.0 {
+                    return Err(BuildError::new(
+                        BuildErrorKind::NotATree,
+                        id,
+                        &format!("child {child:?} was not added before its parent"),
+                    ));
+                }
+                if is_child[child.0] {
+                    return Err(BuildError::new(
+                        BuildErrorKind::NotATree,
+                        *child,
+                        "is a child twice",
+                    ));
+                }
+                is_child[child.0] = true;
+                stack.push(*child);
+            }
+            if let ExpressionType::CharacterSet { .. } = node.expression_type {
+                for child in &node.children {
+                    let child_type = self.nodes[child.0].expression_type;
+                    if !matches!(
+                        child_type,
+                        ExpressionType::CharacterExpression {
+                            value: Some(_),
+                            quantifier: Quantifier::None
+                        }
+                    ) {
+                        return Err(BuildError::new(
+                            BuildErrorKind::InvalidChildren,
+                            id,
+                            &format!("a character set can not have {child_type:?} as child"),
+                        ));
+                    }
+                }
+            }
+            if let Some(name) = &node.group_name {
+                if !is_group_name(name) {
+                    return Err(BuildError::new(
+                        BuildErrorKind::InvalidGroupName,
+                        id,
+                        &format!("invalid group name `{name}`"),
+                    ));
+                }
+                if !group_names.insert(name) {
+                    return Err(BuildError::new(
+                        BuildErrorKind::DuplicateGroupName,
+                        id,
+                        &format!("duplicate group name `{name}`"),
+                    ));
+                }
+            }
+        }
+
+        let mut tree = SyntaxTree::from_nodes(Arc::from(""), self.nodes, root);
+        let mut groups_count = 0;
+        for node in tree.nodes.iter_mut() {
+            if let ExpressionType::Group { index, .. } = &mut node.expression_type {
+                groups_count += 1;
+                *index = groups_count;
+            }
+        }
+        let (pattern, spans) = tree.print();
+        tree.source = Arc::from(pattern);
+        for (node, span) in tree.nodes.iter_mut().zip(spans) {
+            node.span = span;
+        }
+        Ok(tree)
+    }
+}
+
+// What is wrong with expressions given to `TreeBuilder::build`
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BuildErrorKind {
+    // An expression with children it can not have, like a character with one
+    // or without children it needs, like a group without one
+    InvalidChildren,
+    // Expressions which don't make a tree, like a child added after its parent
+    // or a child of two expressions
+    NotATree,
+    // A malformed group name, like `1st`
+    InvalidGroupName,
+    // Two groups with the same name
+    DuplicateGroupName,
+}
+
+// An error while building a syntax tree
+#[derive(Debug, Clone)]
+pub struct BuildError {
+    // Which error this is
+    pub kind: BuildErrorKind,
+    // The offending expression, as added to the builder
+    pub id: NodeId,
+    // Human readable error
+    pub message: String,
+}
+
+impl BuildError {
+    fn new(kind: BuildErrorKind, id: NodeId, error: &str) -> BuildError {
+        BuildError {
+            kind,
+            id,
+            message: format!("Invalid syntax tree, expression {}: {error}", id.0),
+        }
+    }
+}
+
+impl Display for BuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+impl std::ops::Index<NodeId> for SyntaxTree {
+    type Output = Node;
+
+    fn index(&self, id: NodeId) -> &Node {
+        self.node(id)
+    }
+}
+
+impl Display for SyntaxTree {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+// Characters `span` (character positions) of `source`
+fn slice_chars<'a>(source: &'a str, span: &Range<usize>) -> &'a str {
+    let mut offsets = source
+        .char_indices()
+        .map(|(offset, _)| offset)
+        .chain(std::iter::once(source.len()));
+    let start = offsets.nth(span.start).unwrap_or(source.len());
+    let end = match span.len() {
+        0 => start,
+        length => offsets.nth(length - 1).unwrap_or(source.len()),
+    };
+    &source[start..end]
+}
+
 // (Wrapper) Expression objects after parsing
+// Linked form of a syntax tree, kept for compatibility
+// see `SyntaxTree::to_regexp` and `SyntaxTree::from_regexp`
 #[derive(Debug)]
 pub struct ParsedRegexp {
     // -- Which expression this wrapper contains
     pub expression_type: ExpressionType,
 
-    // Pattern of this (sub)expression
-    pub pattern: Arc<str>,
+    // Pattern string this expression was parsed from
+    // one string shared by all expressions of a syntax tree
+    pub source: Arc<str>,
+
+    // Positions (character indices) in field `source` where
+    // this (sub)expression starts and ends (exclusive)
+    // like token positions and error positions
+    pub span: Range<usize>,
 
     // -- Parent expression of this object
     // * We use a Weak reference to avoid reference cycles
@@ -85,6 +538,10 @@ pub struct ParsedRegexp {
     // * We use RwLock<ParsedRegexp> to allow interior mutability
     // in case an `ParsedRegexp` needs to be modified
     pub children: RwLock<Vec<Arc<RwLock<ParsedRegexp>>>>,
+
+    // Name of a named group (?<name>...)
+    // None for all other expressions
+    pub group_name: Option<Arc<str>>,
 }
 
 // Replace `Default` trait with a constructor which at least initializes
@@ -94,22 +551,29 @@ impl ParsedRegexp {
     pub fn new(expr_type: ExpressionType) -> Self {
         ParsedRegexp {
             expression_type: expr_type,
-            pattern: Arc::from(""),
+            source: Arc::from(""),
+            span: 0..0,
             parent: None,
             children: RwLock::new(vec![]),
+            group_name: None,
         }
     }
 
+    // Pattern of this (sub)expression, its span of field `source`
+    pub fn pattern(&self) -> &str {
+        slice_chars(&self.source, &self.span)
+    }
+
     pub fn debug_as_strings(&self) -> String {
         let mut debug = String::new();
         debug.push_str("ParsedRegexp {\n");
         let indent = "  "; // 2 spaces
-        debug.push_str(&format!("{indent}pattern: {},\n", self.pattern));
+        debug.push_str(&format!("{indent}pattern: {},\n", self.pattern()));
 
         if let Some(parent) = &self.parent {
             let parent = parent.upgrade().unwrap();
-            let parent = &parent.read().unwrap().pattern;
-            debug.push_str(&format!("{indent}parent : {},\n", parent));
+            let parent = parent.read().unwrap();
+            debug.push_str(&format!("{indent}parent : {},\n", parent.pattern()));
         }
 
         let children = self.children.read().unwrap();
@@ -117,8 +581,8 @@ impl ParsedRegexp {
         if !children.is_empty() {
             debug.push('\n');
             for child in children.iter() {
-                let child = &child.read().unwrap().pattern;
-                debug.push_str(&format!("{indent}{indent}{child},\n"));
+                let child = child.read().unwrap();
+                debug.push_str(&format!("{indent}{indent}{},\n", child.pattern()));
             }
         }
         debug.push_str(&format!("{indent}}},\n"));
@@ -139,9 +603,11 @@ impl ParsedRegexp {
         let mut source_children = LinkedList::from([Arc::new(RwLock::new(Self::clone(self)))]);
         let deep_copy = Arc::new(RwLock::new(ParsedRegexp {
             expression_type: self.expression_type,
-            pattern: Arc::from(self.pattern.as_ref()),
+            source: Arc::clone(&self.source),
+            span: self.span.clone(),
             parent: None,
             children: RwLock::new(vec![]),
+            group_name: self.group_name.clone(),
         }));
         let mut dest_children = LinkedList::from([Arc::clone(&deep_copy)]);
 
@@ -165,8 +631,10 @@ impl ParsedRegexp {
                     let new_dest_child = Arc::new(RwLock::new(ParsedRegexp {
                         expression_type: src_kid.expression_type,
                         parent: Some(Arc::downgrade(&dest_child)),
-                        pattern: Arc::from(src_kid.pattern.as_ref()),
+                        source: Arc::clone(&src_kid.source),
+                        span: src_kid.span.clone(),
                         children: RwLock::new(vec![]),
+                        group_name: src_kid.group_name.clone(),
                     }));
 
                     dest_children.push_back(Arc::clone(&new_dest_child));
@@ -183,7 +651,8 @@ impl Clone for ParsedRegexp {
     fn clone(&self) -> Self {
         ParsedRegexp {
             expression_type: self.expression_type,
-            pattern: Arc::from(self.pattern.as_ref()),
+            source: Arc::clone(&self.source),
+            span: self.span.clone(),
             parent: self.parent.as_ref().map(Weak::clone),
             children: RwLock::new(
                 self.children
@@ -193,12 +662,13 @@ impl Clone for ParsedRegexp {
                     .map(Arc::clone)
                     .collect(),
             ),
+            group_name: self.group_name.clone(),
         }
     }
 }
 
 impl Display for ParsedRegexp {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.pattern)
+        write!(f, "{}", self.pattern())
     }
 }
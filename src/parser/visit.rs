@@ -0,0 +1,95 @@
+// Walk and rewrite syntax trees
+// A `Visitor` is called on each expression of a tree, before and after its children
+// A `Folder` builds a new tree replacing each expression, children first
+// Both go through trees without recursion, so deeply nested patterns are fine
+
+use super::syntax_tree::*;
+
+// What a walk does after `Visitor::pre`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Walk {
+    // Visit children of this expression
+    Continue,
+    // Do not visit children of this expression, go on with its next sibling
+    SkipChildren,
+    // Visit nothing more, not even `Visitor::post` of this expression
+    Stop,
+}
+
+pub trait Visitor {
+    // Called on expression `id` before its children
+    fn pre(&mut self, _tree: &SyntaxTree, _id: NodeId) -> Walk {
+        Walk::Continue
+    }
+
+    // Called on expression `id` after its children (even if they were skipped)
+    fn post(&mut self, _tree: &SyntaxTree, _id: NodeId) {}
+}
+
+// Visit all expressions of `tree`, in the order they appear in its pattern
+pub fn walk<V: Visitor + ?Sized>(tree: &SyntaxTree, visitor: &mut V) {
+    walk_subtree(tree, tree.root(), visitor);
+}
+
+// Visit expression `id` of `tree` and all expressions inside it
+pub fn walk_subtree<V: Visitor + ?Sized>(tree: &SyntaxTree, id: NodeId, visitor: &mut V) {
+    // (expression, were its children visited already?)
+    let mut stack = vec![(id, false)];
+    while let Some((id, visited)) = stack.pop() {
+        if visited {
+            visitor.post(tree, id);
+            continue;
+        }
+        match visitor.pre(tree, id) {
+            Walk::Continue => {
+                stack.push((id, true));
+                // Pushed in reverse to be visited in order
+                stack.extend(tree[id].children.iter().rev().map(|child| (*child, false)));
+            }
+            Walk::SkipChildren => stack.push((id, true)),
+            Walk::Stop => return,
+        }
+    }
+}
+
+pub trait Folder {
+    // Expression replacing expression `id` of `tree` in the new tree
+    // `children` replace its children and were already added to `builder`
+    // By default the expression is kept as it is with its new children
+    fn fold(
+        &mut self,
+        tree: &SyntaxTree,
+        id: NodeId,
+        children: Vec<NodeId>,
+        builder: &mut TreeBuilder,
+    ) -> NodeId {
+        builder.copy(tree, id, children)
+    }
+}
+
+// New tree made by replacing each expression of `tree` using `folder`
+// Expressions are folded in the order their ends appear in the pattern, children first
+// See `TreeBuilder::build` for what the new tree looks like and when it fails
+pub fn fold<F: Folder + ?Sized>(
+    tree: &SyntaxTree,
+    folder: &mut F,
+) -> Result<SyntaxTree, BuildError> {
+    let mut builder = TreeBuilder::new();
+    // (expression, replacements of its children folded so far)
+    let mut stack = vec![(tree.root(), vec![])];
+    loop {
+        let (id, children) = stack.last_mut().unwrap();
+        let next_child = tree[*id].children.get(children.len()).copied();
+        match next_child {
+            Some(child) => stack.push((child, vec![])),
+            None => {
+                let (id, children) = stack.pop().unwrap();
+                let replacement = folder.fold(tree, id, children, &mut builder);
+                match stack.last_mut() {
+                    Some((_, siblings)) => siblings.push(replacement),
+                    None => return builder.build(replacement),
+                }
+            }
+        }
+    }
+}
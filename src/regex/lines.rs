@@ -0,0 +1,235 @@
+// Line oriented search
+// Report which lines of a reader match a regex, like `grep` does
+// Lines are read one at a time so the reader may be arbitrarily large
+
+use std::collections::VecDeque;
+use std::io::{self, BufRead};
+
+use super::Regex;
+
+// Why a line was reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineKind {
+    // The line was selected: it matches the regex
+    // or it does not match when the search is inverted
+    Match,
+    // The line was not selected but it's near a selected line
+    // (see `LineSearchBuilder::before_context` and `LineSearchBuilder::after_context`)
+    Context,
+}
+
+// A line reported by a line search
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    // Line number, counted from 1
+    number: usize,
+    // Byte offset where the line starts, counted from the first byte read
+    offset: usize,
+    // Line content without its line terminator (\n or \r\n)
+    // invalid UTF-8 read from the reader shows up as U+FFFD
+    content: String,
+    kind: LineKind,
+}
+
+impl Line {
+    // Line number, counted from 1
+    pub fn number(&self) -> usize {
+        self.number
+    }
+
+    // Byte offset where this line starts in the reader
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    // Line content without its line terminator
+    pub fn as_str(&self) -> &str {
+        &self.content
+    }
+
+    pub fn kind(&self) -> LineKind {
+        self.kind
+    }
+
+    // Was this line selected (rather than given as context)?
+    pub fn is_match(&self) -> bool {
+        self.kind == LineKind::Match
+    }
+}
+
+// Options of a line search
+#[derive(Debug, Clone, Default)]
+pub struct LineSearchBuilder {
+    // Select lines which do NOT match
+    invert: bool,
+    // Stop after selecting this many lines
+    max_count: Option<usize>,
+    // How many lines to report before each selected line
+    before_context: usize,
+    // How many lines to report after each selected line
+    after_context: usize,
+}
+
+impl LineSearchBuilder {
+    pub fn new() -> LineSearchBuilder {
+        LineSearchBuilder::default()
+    }
+
+    // Select lines which do NOT match, like `grep -v`
+    pub fn invert(&mut self, yes: bool) -> &mut LineSearchBuilder {
+        self.invert = yes;
+        self
+    }
+
+    // Stop after selecting `count` lines, like `grep -m`
+    // Context lines after the last selected line are still reported
+    pub fn max_count(&mut self, count: Option<usize>) -> &mut LineSearchBuilder {
+        self.max_count = count;
+        self
+    }
+
+    // Report `count` lines before each selected line, like `grep -B`
+    pub fn before_context(&mut self, count: usize) -> &mut LineSearchBuilder {
+        self.before_context = count;
+        self
+    }
+
+    // Report `count` lines after each selected line, like `grep -A`
+    pub fn after_context(&mut self, count: usize) -> &mut LineSearchBuilder {
+        self.after_context = count;
+        self
+    }
+
+    // Report `count` lines before and after each selected line, like `grep -C`
+    pub fn context(&mut self, count: usize) -> &mut LineSearchBuilder {
+        self.before_context(count).after_context(count)
+    }
+
+    // Search lines of `reader` for `regex` using these options
+    pub fn search<R: BufRead>(&self, regex: &Regex, reader: R) -> Lines<R> {
+        Lines {
+            reader,
+            regex: regex.clone(),
+            options: self.clone(),
+            line_bytes: vec![],
+            line_number: 0,
+            offset: 0,
+            before: VecDeque::new(),
+            after_remaining: 0,
+            selected: 0,
+            pending: VecDeque::new(),
+            done: self.max_count == Some(0),
+        }
+    }
+}
+
+// Iterator over reported lines of a reader, in order
+// each line is reported once even if it's near many selected lines
+// Yield an error (and stop) if reading fails
+pub struct Lines<R> {
+    reader: R,
+    regex: Regex,
+    options: LineSearchBuilder,
+
+    // Bytes of the line being read
+    line_bytes: Vec<u8>,
+    // Number of last line read
+    line_number: usize,
+    // Byte offset of next line
+    offset: usize,
+
+    // Lines not reported yet which may be context of the next selected line
+    before: VecDeque<Line>,
+    // How many of the next lines are context of the last selected line
+    after_remaining: usize,
+    // How many lines were selected so far
+    selected: usize,
+
+    // Lines found but not yielded yet
+    pending: VecDeque<Line>,
+    // True once no more lines will be reported
+    done: bool,
+}
+
+impl<R: BufRead> Lines<R> {
+    // Next line of reader, None at end of reader
+    fn read_line(&mut self) -> io::Result<Option<Line>> {
+        self.line_bytes.clear();
+        let length = self.reader.read_until(b'\n', &mut self.line_bytes)?;
+        if length == 0 {
+            return Ok(None);
+        }
+
+        let mut content = &self.line_bytes[..];
+        if let Some(stripped) = content.strip_suffix(b"\n") {
+            content = stripped.strip_suffix(b"\r").unwrap_or(stripped);
+        }
+        self.line_number += 1;
+        let line = Line {
+            number: self.line_number,
+            offset: self.offset,
+            content: String::from_utf8_lossy(content).into_owned(),
+            kind: LineKind::Context,
+        };
+        self.offset += length;
+        Ok(Some(line))
+    }
+
+    // Read lines until some are ready to be reported or reader ends
+    fn advance(&mut self) -> io::Result<()> {
+        while self.pending.is_empty() && !self.done {
+            let mut line = match self.read_line()? {
+                Some(line) => line,
+                None => {
+                    self.done = true;
+                    break;
+                }
+            };
+
+            if Some(self.selected) == self.options.max_count {
+                // Only context of the last selected line is left
+                if self.after_remaining > 0 {
+                    self.after_remaining -= 1;
+                    self.pending.push_back(line);
+                }
+                if self.after_remaining == 0 {
+                    self.done = true;
+                }
+                continue;
+            }
+
+            if self.regex.find(&line.content).is_some() != self.options.invert {
+                line.kind = LineKind::Match;
+                self.pending.extend(self.before.drain(..));
+                self.pending.push_back(line);
+                self.selected += 1;
+                self.after_remaining = self.options.after_context;
+                if Some(self.selected) == self.options.max_count && self.after_remaining == 0 {
+                    self.done = true;
+                }
+            } else if self.after_remaining > 0 {
+                self.after_remaining -= 1;
+                self.pending.push_back(line);
+            } else if self.options.before_context > 0 {
+                if self.before.len() == self.options.before_context {
+                    self.before.pop_front();
+                }
+                self.before.push_back(line);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Iterator for Lines<R> {
+    type Item = io::Result<Line>;
+
+    fn next(&mut self) -> Option<io::Result<Line>> {
+        if let Err(error) = self.advance() {
+            // Do not try reading again after a failure
+            self.done = true;
+            return Some(Err(error));
+        }
+        self.pending.pop_front().map(Ok)
+    }
+}
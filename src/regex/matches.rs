@@ -0,0 +1,107 @@
+// Matches borrowing the searched string
+// Unlike ranges given by `Matcher`, which index characters,
+// these use byte offsets into the original string so the matched text is a slice of it
+
+use std::ops::Range;
+use std::sync::Arc;
+
+use super::Regex;
+use crate::matcher::Matcher;
+
+// A single match of a regex in a haystack (the searched string)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match<'h> {
+    haystack: &'h str,
+    // Byte offset where the match starts
+    start: usize,
+    // Byte offset right after the match
+    end: usize,
+}
+
+impl<'h> Match<'h> {
+    // Byte offset where this match starts in the haystack
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    // Byte offset right after this match in the haystack
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    // Byte range of this match in the haystack
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    // Length of this match in bytes
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    // Matched text, borrowed from the haystack
+    pub fn as_str(&self) -> &'h str {
+        &self.haystack[self.start..self.end]
+    }
+}
+
+// Iterator over successive non-overlapping matches of a regex in a haystack
+pub struct Matches<'h> {
+    haystack: &'h str,
+    // Searches characters of haystack from the byte offset a search started at
+    // characters before it are never decoded, no match can look at them
+    matcher: Matcher,
+    // Character index in matcher target and byte offset in haystack of the same position
+    // moved forward as matches are found, which never go backwards
+    index: usize,
+    offset: usize,
+}
+
+impl<'h> Matches<'h> {
+    // Search `haystack` for `regex` starting at byte offset `start`
+    // Panic if `start` is not a character boundary within haystack, like slicing it would
+    pub(crate) fn new(regex: &Regex, haystack: &'h str, start: usize) -> Matches<'h> {
+        if start > haystack.len() {
+            panic!("byte index {start} is out of bounds of haystack")
+        }
+        if !haystack.is_char_boundary(start) {
+            panic!("byte index {start} is not a char boundary of haystack")
+        }
+
+        Matches {
+            haystack,
+            matcher: regex.matcher_for_chars(Arc::new(haystack[start..].chars().collect())),
+            index: 0,
+            offset: start,
+        }
+    }
+
+    // Byte offset in haystack of character `index` of matcher target
+    // Only characters since the previous call are walked over
+    fn offset(&mut self, index: usize) -> usize {
+        self.offset += self.haystack[self.offset..]
+            .chars()
+            .take(index - self.index)
+            .map(char::len_utf8)
+            .sum::<usize>();
+        self.index = index;
+        self.offset
+    }
+}
+
+impl<'h> Iterator for Matches<'h> {
+    type Item = Match<'h>;
+
+    fn next(&mut self) -> Option<Match<'h>> {
+        let range = self.matcher.next()?;
+        Some(Match {
+            haystack: self.haystack,
+            start: self.offset(range.start),
+            end: self.offset(range.end),
+        })
+    }
+}
@@ -0,0 +1,338 @@
+// Compiled patterns
+// Parse a pattern once and match it against any number of targets
+
+// Match many patterns in a single pass
+pub mod set;
+
+// Matches borrowing the searched string
+pub mod matches;
+
+// Search readers in chunks
+pub mod stream;
+
+// Report which lines of a reader match
+pub mod lines;
+
+use std::fmt::Display;
+use std::io::BufRead;
+use std::ops::Range;
+use std::sync::Arc;
+
+use crate::format_error;
+use crate::matcher::{
+    captures::GroupInfo,
+    escape,
+    prefilter::{Literals, Prefilter},
+    Matcher, PatternAnalysis,
+};
+use crate::parser::{
+    error::{SyntaxError, SyntaxErrorKind},
+    serialize::{self, DecodeError},
+    syntax_tree::*,
+    ParserBuilder,
+};
+use lines::{LineSearchBuilder, Lines};
+use matches::{Match, Matches};
+use stream::{StreamBuilder, StreamMatches};
+
+#[derive(Debug, Clone)]
+pub struct Regex {
+    // Syntax tree of the pattern this regex was created from
+    regexp: Arc<SyntaxTree>,
+
+    // Analysis of field `regexp`, shared by all matchers of this regex
+    analysis: PatternAnalysis,
+}
+
+impl Regex {
+    // Parse `pattern` into a new regex
+    // See `RegexBuilder` for more options
+    pub fn new(pattern: &str) -> Result<Regex, SyntaxError> {
+        RegexBuilder::new().build(pattern)
+    }
+
+    // Create a regex from an already parsed pattern
+    // like one parsed with `ParserBuilder`
+    pub fn from_regexp(regexp: &Arc<SyntaxTree>) -> Regex {
+        Regex {
+            regexp: Arc::clone(regexp),
+            analysis: PatternAnalysis::new(regexp),
+        }
+    }
+
+    // This regex saved as bytes, its syntax tree and whether it's case insensitive
+    // Load it with `Regex::from_bytes` to skip parsing, see `parser::serialize` for the format
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let flags = if self.analysis.case_insensitive {
+            serialize::CASE_INSENSITIVE
+        } else {
+            0
+        };
+        serialize::encode(&self.regexp, flags)
+    }
+
+    // Load a regex saved with `Regex::to_bytes`
+    // Its pattern is not parsed again, only analyzed
+    pub fn from_bytes(bytes: &[u8]) -> Result<Regex, DecodeError> {
+        let (regexp, flags) = serialize::decode(bytes)?;
+        let analysis = if flags & serialize::CASE_INSENSITIVE != 0 {
+            PatternAnalysis::case_insensitive(&regexp)
+        } else {
+            PatternAnalysis::new(&regexp)
+        };
+        Ok(Regex {
+            regexp: Arc::new(regexp),
+            analysis,
+        })
+    }
+
+    // Create a regex matching `text` verbatim
+    // Unlike `Regex::new(&escape(text))`, `text` is never parsed
+    // nor analyzed, matches are found with a substring search
+    pub fn literal(text: &str) -> Regex {
+        let characters = text.chars().collect::<Vec<_>>();
+        let regexp = Self::literal_regexp(&characters, Arc::from(escape(text)));
+        Regex {
+            analysis: PatternAnalysis {
+                prefilter: Prefilter::from_literals(Literals::exact(characters)).map(Arc::new),
+                // No expression is quantified
+                backtracking: Arc::new(vec![false; regexp.len()]),
+                ..PatternAnalysis::default()
+            },
+            regexp: Arc::new(regexp),
+        }
+    }
+
+    // Build the syntax tree the parser would give for `pattern`, the escaped `characters`
+    fn literal_regexp(characters: &[char], pattern: Arc<str>) -> SyntaxTree {
+        let expression = |expression_type: ExpressionType, span: Range<usize>| {
+            let mut expr = Node::new(expression_type);
+            expr.span = span;
+            expr
+        };
+        let character_expression = |value: char, start: usize| {
+            // A metacharacter takes two characters once escaped
+            let length = escape(&value.to_string()).chars().count();
+            expression(
+                ExpressionType::CharacterExpression {
+                    value: Some(value),
+                    quantifier: Quantifier::None,
+                },
+                start..start + length,
+            )
+        };
+
+        let nodes = match characters {
+            // Empty string pattern for the empty expression
+            [] => vec![expression(ExpressionType::EmptyExpression, 0..0)],
+            [value] => vec![character_expression(*value, 0)],
+            _ => {
+                let mut concatenation =
+                    expression(ExpressionType::Concatenation, 0..pattern.chars().count());
+                // Concatenation first then its children, in pre-order
+                concatenation.children = (1..=characters.len()).map(NodeId::new).collect();
+                let mut nodes = vec![concatenation];
+                let mut start = 0;
+                for value in characters {
+                    let child = character_expression(*value, start);
+                    start = child.span.end;
+                    nodes.push(child);
+                }
+                nodes
+            }
+        };
+        SyntaxTree::from_nodes(pattern, nodes, NodeId::new(0))
+    }
+
+    // Pattern string of this regex
+    pub fn as_str(&self) -> &str {
+        self.regexp.source()
+    }
+
+    // Syntax tree of this regex
+    pub fn regexp(&self) -> &Arc<SyntaxTree> {
+        &self.regexp
+    }
+
+    // Numbers and names of groups of this regex
+    pub fn group_info(&self) -> &GroupInfo {
+        &self.analysis.groups
+    }
+
+    // Create a matcher searching `target` for this regex
+    pub fn matcher(&self, target: &str) -> Matcher {
+        self.matcher_for_chars(Arc::new(target.chars().collect()))
+    }
+
+    // First match in `haystack`, if any
+    pub fn find<'h>(&self, haystack: &'h str) -> Option<Match<'h>> {
+        self.find_iter(haystack).next()
+    }
+
+    // First match in `haystack` starting at or after byte offset `start`, if any
+    // Panic if `start` is not a character boundary within `haystack`
+    pub fn find_at<'h>(&self, haystack: &'h str, start: usize) -> Option<Match<'h>> {
+        Matches::new(self, haystack, start).next()
+    }
+
+    // All non-overlapping matches in `haystack`, from left to right
+    pub fn find_iter<'h>(&self, haystack: &'h str) -> Matches<'h> {
+        Matches::new(self, haystack, 0)
+    }
+
+    // All non-overlapping matches in `reader`, read in chunks, from left to right
+    // See `StreamBuilder` to tune how the reader is consumed
+    pub fn stream_matches<R: BufRead>(&self, reader: R) -> StreamMatches<R> {
+        StreamBuilder::new().search(self, reader)
+    }
+
+    // Lines of `reader` matching this regex, with their numbers and byte offsets
+    // See `LineSearchBuilder` for inverted search, context lines and more
+    pub fn search_lines<R: BufRead>(&self, reader: R) -> Lines<R> {
+        LineSearchBuilder::new().search(self, reader)
+    }
+
+    // Create a matcher searching an already split target
+    pub(crate) fn matcher_for_chars(&self, target: Arc<Vec<char>>) -> Matcher {
+        Matcher::with_analysis(&self.regexp, target, self.analysis.clone())
+    }
+}
+
+// Default of `RegexBuilder::size_limit`
+const DEFAULT_SIZE_LIMIT: usize = 64 << 20;
+
+// Options for parsing a pattern into a regex
+#[derive(Debug, Clone)]
+pub struct RegexBuilder {
+    // Match characters regardless of their case
+    case_insensitive: bool,
+    // Limits of the parser, see `ParserBuilder`
+    parser: ParserBuilder,
+    // How many bytes the syntax tree and its analysis can take
+    size_limit: usize,
+}
+
+impl Default for RegexBuilder {
+    fn default() -> Self {
+        let mut parser = ParserBuilder::new();
+        // Unknown escapes are read as a literal slash, like `Parser::parse` does
+        parser.strict_escapes(false);
+        RegexBuilder {
+            case_insensitive: false,
+            parser,
+            size_limit: DEFAULT_SIZE_LIMIT,
+        }
+    }
+}
+
+impl RegexBuilder {
+    pub fn new() -> RegexBuilder {
+        RegexBuilder::default()
+    }
+
+    // Match characters regardless of their case, so `error` also matches `Error` and `ERROR`
+    // Slower because matches are no longer found with literal searches
+    pub fn case_insensitive(&mut self, yes: bool) -> &mut RegexBuilder {
+        self.case_insensitive = yes;
+        self
+    }
+
+    // At most `limit` groups open at once, see `ParserBuilder::nest_limit`
+    pub fn nest_limit(&mut self, limit: usize) -> &mut RegexBuilder {
+        self.parser.nest_limit(limit);
+        self
+    }
+
+    // At most `limit` expressions, see `ParserBuilder::node_limit`
+    pub fn node_limit(&mut self, limit: usize) -> &mut RegexBuilder {
+        self.parser.node_limit(limit);
+        self
+    }
+
+    // At most `limit` bytes taken by the compiled pattern, its syntax tree and
+    // what's computed from it to match faster, like automata of alternations
+    // 64 MiB by default, larger patterns are reported as `SyntaxErrorKind::ProgramTooLarge`
+    pub fn size_limit(&mut self, limit: usize) -> &mut RegexBuilder {
+        self.size_limit = limit;
+        self
+    }
+
+    // Parse `pattern` into a new regex using these options
+    pub fn build(&self, pattern: &str) -> Result<Regex, SyntaxError> {
+        let regexp = self.parser.parse(pattern)?;
+        // Analysis takes at least as much as the tree, don't compute it for nothing
+        self.check_size(&regexp, regexp.heap_size())?;
+        let analysis = if self.case_insensitive {
+            PatternAnalysis::case_insensitive(&regexp)
+        } else {
+            PatternAnalysis::new(&regexp)
+        };
+        self.check_size(&regexp, regexp.heap_size() + analysis.heap_size())?;
+        Ok(Regex { regexp, analysis })
+    }
+
+    // Report a pattern whose compiled form takes `size` bytes if that's above the limit
+    fn check_size(&self, regexp: &SyntaxTree, size: usize) -> Result<(), SyntaxError> {
+        if size <= self.size_limit {
+            return Ok(());
+        }
+        let source = regexp.source();
+        let length = source.chars().count();
+        Err(SyntaxError::new(
+            SyntaxErrorKind::ProgramTooLarge,
+            0,
+            format_error(
+                &format!(
+                    "Pattern too large: it takes more than {} bytes once compiled",
+                    self.size_limit
+                ),
+                source,
+                // Place carets `^` below the whole pattern
+                &[(0, std::cmp::min(length, u8::MAX as usize) as u8)],
+                "Raise the limit with `size_limit` or split the pattern",
+            ),
+        ))
+    }
+}
+
+impl Display for Regex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+// With feature `serde`, a regex is serialized as its pattern string
+// and deserialized by parsing it, so an invalid pattern in a configuration file
+// fails to load with its syntax error. Options of `RegexBuilder` are not kept
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use super::Regex;
+    use serde::de::{Error, Visitor};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    impl Serialize for Regex {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str(self.as_str())
+        }
+    }
+
+    struct RegexVisitor;
+
+    impl Visitor<'_> for RegexVisitor {
+        type Value = Regex;
+
+        fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "a regular expression pattern string")
+        }
+
+        fn visit_str<E: Error>(self, pattern: &str) -> Result<Regex, E> {
+            Regex::new(pattern).map_err(|error| E::custom(error.message))
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Regex {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_str(RegexVisitor)
+        }
+    }
+}
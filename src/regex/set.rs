@@ -0,0 +1,184 @@
+// Match many patterns against the same target in a single pass
+// Strings matches of each pattern start with are all searched for by one automaton
+// which wakes a pattern only where one of its strings occurs
+// patterns without such strings are tried along the way at each position they may match
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::sync::Arc;
+
+use super::Regex;
+use crate::matcher::aho_corasick::AhoCorasick;
+use crate::matcher::Matcher;
+use crate::parser::error::SyntaxError;
+
+#[derive(Debug, Clone)]
+pub struct RegexSet {
+    // Patterns of this set, in the order they were given
+    regexes: Vec<Regex>,
+    // Strings matches start with, of all patterns having some
+    starts: AhoCorasick,
+    // Patterns (their indices) whose matches may start with each string of `starts`
+    owners: Vec<Vec<usize>>,
+    // Patterns without strings their matches start with
+    unfiltered: Vec<usize>,
+}
+
+impl RegexSet {
+    // Parse all `patterns` into a new set
+    // Fail on the first pattern which is not a valid regular expression
+    pub fn new<I, S>(patterns: I) -> Result<RegexSet, SyntaxError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let regexes = patterns
+            .into_iter()
+            .map(|pattern| Regex::new(pattern.as_ref()))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(RegexSet::from_regexes(regexes))
+    }
+
+    // Create a set from already compiled regexes
+    pub fn from_regexes(regexes: Vec<Regex>) -> RegexSet {
+        let mut strings: Vec<Vec<char>> = vec![];
+        let mut owners: Vec<Vec<usize>> = vec![];
+        // Index of each string in `strings`, patterns may share strings
+        let mut string_index = HashMap::new();
+        let mut unfiltered = vec![];
+        for (index, regex) in regexes.iter().enumerate() {
+            let starts = regex
+                .analysis
+                .prefilter
+                .as_ref()
+                .and_then(|prefilter| prefilter.starts());
+            match starts {
+                Some(starts) => {
+                    for start in starts {
+                        let string = *string_index.entry(start.clone()).or_insert_with(|| {
+                            strings.push(start);
+                            owners.push(vec![]);
+                            strings.len() - 1
+                        });
+                        if owners[string].last() != Some(&index) {
+                            owners[string].push(index);
+                        }
+                    }
+                }
+                None => unfiltered.push(index),
+            }
+        }
+        RegexSet {
+            regexes,
+            starts: AhoCorasick::new(&strings),
+            owners,
+            unfiltered,
+        }
+    }
+
+    // How many patterns this set has
+    pub fn len(&self) -> usize {
+        self.regexes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.regexes.is_empty()
+    }
+
+    // Patterns of this set, in the order they were given
+    pub fn regexes(&self) -> &[Regex] {
+        &self.regexes
+    }
+
+    // Does any pattern match some range within `target`?
+    pub fn is_match(&self, target: &str) -> bool {
+        !self.search(target, true).is_empty()
+    }
+
+    // Indices (in increasing order) of all patterns matching some range within `target`
+    pub fn matches(&self, target: &str) -> Vec<usize> {
+        self.search(target, false)
+    }
+
+    // Walk `target` once from left to right
+    // Try a pattern only where one of its start strings occurs, or for patterns
+    // without start strings where their prefilters allow a match
+    // and stop trying a pattern as soon as it matched
+    fn search(&self, target: &str, stop_at_first_match: bool) -> Vec<usize> {
+        // Split target once, all matchers share it
+        let target = Arc::new(target.chars().collect());
+        let mut matchers = self
+            .regexes
+            .iter()
+            .map(|regex| regex.matcher_for_chars(Arc::clone(&target)))
+            .collect::<Vec<Matcher>>();
+        let mut matched = vec![false; self.regexes.len()];
+        let mut matched_count = 0;
+
+        // Patterns without start strings waiting to be tried, ordered by the next position
+        // they may match at then by their index to keep a deterministic order
+        let mut pending = BinaryHeap::new();
+        for index in &self.unfiltered {
+            if let Some(candidate) = matchers[*index].next_candidate(0) {
+                pending.push(Reverse((candidate, *index)));
+            }
+        }
+        // Start strings found, in the order they end
+        let mut occurrences = self.starts.occurrences(&target, 0).peekable();
+        // Patterns already tried at a position, start strings of a pattern
+        // may occur at the same position
+        let mut tried = HashSet::new();
+
+        loop {
+            // Whichever comes first in target, the next unfiltered candidate
+            // or the end of the next start string occurrence
+            let occurrence_end = occurrences.peek().map(|(_, _, end)| *end);
+            let pending_first = match (pending.peek(), occurrence_end) {
+                (Some(Reverse((candidate, _))), Some(end)) => *candidate <= end,
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (None, None) => break,
+            };
+            let unfiltered;
+            let (position, woken) = match pending_first {
+                true => {
+                    let Reverse((candidate, index)) = pending.pop().unwrap();
+                    unfiltered = [index];
+                    (candidate, &unfiltered[..])
+                }
+                false => {
+                    let (string, start, _) = occurrences.next().unwrap();
+                    (start, &self.owners[string][..])
+                }
+            };
+
+            for index in woken.iter().copied() {
+                if matched[index] || !tried.insert((index, position)) {
+                    continue;
+                }
+                let matcher = &mut matchers[index];
+                let complete = self.regexes[index]
+                    .analysis
+                    .prefilter
+                    .as_ref()
+                    .is_some_and(|prefilter| prefilter.is_complete());
+                // A start string of a complete prefilter is a match
+                if complete || matcher.match_at(position).is_some() {
+                    // This pattern is done, no need to look for more of its matches
+                    matched[index] = true;
+                    matched_count += 1;
+                } else if pending_first {
+                    if let Some(candidate) = matcher.next_candidate(position + 1) {
+                        // Retry this pattern later
+                        pending.push(Reverse((candidate, index)));
+                    }
+                }
+            }
+            if (stop_at_first_match && matched_count > 0) || matched_count == matched.len() {
+                break;
+            }
+        }
+
+        (0..matched.len()).filter(|index| matched[*index]).collect()
+    }
+}
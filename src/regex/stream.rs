@@ -0,0 +1,274 @@
+// Streaming search
+// Search a reader for matches without holding all of its contents in memory
+// The reader is consumed in chunks and matches are reported with byte offsets
+// counted from the first byte read
+//
+// Data which may still take part in a match is kept between chunks, so matches
+// spanning chunk boundaries are found as long as they are at most `max_look_behind`
+// bytes long, a longer match may be cut short or missed
+
+use std::collections::VecDeque;
+use std::io::{self, BufRead};
+use std::ops::Range;
+use std::sync::Arc;
+
+use super::Regex;
+
+// A match found in a reader
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamMatch {
+    // Byte offset where the match starts, counted from the first byte read
+    start: usize,
+    // Byte offset right after the match
+    end: usize,
+    // Matched text, the reader is not kept around to borrow it from
+    // invalid UTF-8 read from the reader shows up as U+FFFD
+    text: String,
+}
+
+impl StreamMatch {
+    // Byte offset where this match starts in the reader
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    // Byte offset right after this match in the reader
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    // Byte range of this match in the reader
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    // Length of this match in bytes
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    // Matched text
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+}
+
+// Options of a streaming search
+#[derive(Debug, Clone)]
+pub struct StreamBuilder {
+    // How many bytes at the end of data read so far are searched again
+    // once more data arrives, the longest match found across chunks
+    max_look_behind: usize,
+    // How many bytes to read at once (at least)
+    chunk_size: usize,
+}
+
+impl Default for StreamBuilder {
+    fn default() -> Self {
+        StreamBuilder::new()
+    }
+}
+
+impl StreamBuilder {
+    pub fn new() -> StreamBuilder {
+        StreamBuilder {
+            max_look_behind: 8 * 1024,
+            chunk_size: 64 * 1024,
+        }
+    }
+
+    // Longest match, in bytes, guaranteed to be found when it spans chunks
+    // Larger values keep more data in memory and search it more than once
+    pub fn max_look_behind(&mut self, bytes: usize) -> &mut StreamBuilder {
+        self.max_look_behind = bytes;
+        self
+    }
+
+    // How many bytes to read from the reader at once
+    pub fn chunk_size(&mut self, bytes: usize) -> &mut StreamBuilder {
+        // Reading nothing would never reach the end of reader
+        self.chunk_size = std::cmp::max(bytes, 1);
+        self
+    }
+
+    // Search `reader` for `regex` using these options
+    pub fn search<R: BufRead>(&self, regex: &Regex, reader: R) -> StreamMatches<R> {
+        StreamMatches {
+            reader,
+            regex: regex.clone(),
+            options: self.clone(),
+            buffer: Arc::new(vec![]),
+            offsets: vec![0],
+            undecoded: vec![],
+            pending: VecDeque::new(),
+            resume: 0,
+            eof: false,
+            done: false,
+        }
+    }
+}
+
+// Iterator over successive non-overlapping matches of a regex in a reader
+// Yield an error (and stop) if reading fails
+pub struct StreamMatches<R> {
+    reader: R,
+    regex: Regex,
+    options: StreamBuilder,
+
+    // Characters read and kept for searching
+    // shared with the matcher during a search only, so it's changed in place afterwards
+    buffer: Arc<Vec<char>>,
+    // Byte offset in reader of each character in `buffer`
+    // followed by the offset of the end of data decoded so far
+    offsets: Vec<usize>,
+    // Bytes read but not decoded yet, an incomplete UTF-8 sequence
+    // at the end of a chunk waits here for the rest of it
+    undecoded: Vec<u8>,
+
+    // Matches found but not yielded yet
+    pending: VecDeque<StreamMatch>,
+    // Index in `buffer` where next search starts
+    resume: usize,
+    // True once reader has no more data
+    eof: bool,
+    // True once all of reader was searched
+    done: bool,
+}
+
+impl<R: BufRead> StreamMatches<R> {
+    // Drop characters no longer needed and read the next chunk
+    fn fill(&mut self) -> io::Result<()> {
+        Arc::make_mut(&mut self.buffer).drain(..self.resume);
+        self.offsets.drain(..self.resume);
+        self.resume = 0;
+
+        let mut read = 0;
+        while read < self.options.chunk_size {
+            let available = match self.reader.fill_buf() {
+                Ok(available) => available,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            };
+            if available.is_empty() {
+                self.eof = true;
+                break;
+            }
+            let length = available.len();
+            self.undecoded.extend_from_slice(available);
+            self.reader.consume(length);
+            read += length;
+        }
+
+        self.decode();
+        Ok(())
+    }
+
+    // Move decodable bytes of `undecoded` into `buffer`
+    fn decode(&mut self) {
+        let buffer = Arc::make_mut(&mut self.buffer);
+        let mut current = 0;
+        let mut offset = *self.offsets.last().unwrap();
+        // Remove offset of end of data, pushed back below
+        self.offsets.pop();
+
+        while current < self.undecoded.len() {
+            let (valid, invalid) = match std::str::from_utf8(&self.undecoded[current..]) {
+                Ok(valid) => (valid, None),
+                Err(error) => {
+                    let valid = &self.undecoded[current..current + error.valid_up_to()];
+                    // Bytes up to `valid_up_to` are valid UTF-8
+                    (std::str::from_utf8(valid).unwrap(), Some(error))
+                }
+            };
+            for (index, ch) in valid.char_indices() {
+                buffer.push(ch);
+                self.offsets.push(offset + index);
+            }
+            offset += valid.len();
+            current += valid.len();
+
+            let error = match invalid {
+                Some(error) => error,
+                None => break,
+            };
+            let invalid_length = match error.error_len() {
+                Some(length) => length,
+                // Sequence is cut at end of data read so far
+                // wait for its remaining bytes unless there are no more
+                None if !self.eof => break,
+                None => self.undecoded.len() - current,
+            };
+            buffer.push(char::REPLACEMENT_CHARACTER);
+            self.offsets.push(offset);
+            offset += invalid_length;
+            current += invalid_length;
+        }
+
+        self.undecoded.drain(..current);
+        self.offsets.push(offset);
+    }
+
+    // Search characters read so far for matches which more data can not change
+    fn search(&mut self) {
+        let end_offset = *self.offsets.last().unwrap();
+        // Matches starting at `limit` or later are left for the next search
+        // because they may go on past the end of data read so far
+        let limit = if self.eof {
+            self.buffer.len() + 1
+        } else {
+            self.offsets
+                .iter()
+                .position(|offset| end_offset - offset <= self.options.max_look_behind)
+                .unwrap()
+        };
+
+        // The matcher is dropped at the end, leaving `buffer` unshared for the next fill
+        let mut matcher = self.regex.matcher_for_chars(Arc::clone(&self.buffer));
+        matcher.seek(self.resume);
+        // No match starts between the last settled match and `limit`
+        // positions from `limit` on are tried again with more data
+        let mut next_resume = std::cmp::max(self.resume, limit);
+        while let Some(found) = matcher.next() {
+            if found.start >= limit {
+                break;
+            }
+            self.pending.push_back(StreamMatch {
+                start: self.offsets[found.start],
+                end: self.offsets[found.end],
+                text: self.buffer[found.clone()].iter().collect(),
+            });
+            next_resume = std::cmp::max(matcher.position(), limit);
+        }
+
+        // An empty match may leave matcher just past the end of buffer
+        self.resume = std::cmp::min(next_resume, self.buffer.len());
+        if self.eof {
+            self.done = true;
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for StreamMatches<R> {
+    type Item = io::Result<StreamMatch>;
+
+    fn next(&mut self) -> Option<io::Result<StreamMatch>> {
+        loop {
+            if let Some(found) = self.pending.pop_front() {
+                return Some(Ok(found));
+            }
+            if self.done {
+                return None;
+            }
+            if let Err(error) = self.fill() {
+                // Do not try reading again after a failure
+                self.done = true;
+                return Some(Err(error));
+            }
+            self.search();
+        }
+    }
+}
@@ -4,9 +4,7 @@
 #[allow(dead_code)]
 pub mod tokens;
 
-use tokens::{Token, TokenType::*};
-
-use crate::report_fatal_error;
+use tokens::{EscapeError, Token, TokenType, TokenType::*};
 
 pub const ANCHORS: [char; 4] = ['A', 'Z', 'b', 'B'];
 
@@ -14,6 +12,37 @@ pub fn is_anchor_char(ch: char) -> bool {
     ANCHORS.contains(&ch)
 }
 
+// Characters with a special meaning in patterns
+// precede any of them with a slash to match it literally
+// Update rule `Metacharacter` in file `grammar`
+pub const METACHARACTERS: [char; 8] = ['(', ')', '\\', '|', '*', '.', '?', '+'];
+
+pub fn is_metacharacter(ch: char) -> bool {
+    METACHARACTERS.contains(&ch)
+}
+
+// Can `name` name a group, like `year` in (?<year>...)?
+// A name is an ASCII letter or _ followed by ASCII letters, digits or _
+pub fn is_group_name(name: &str) -> bool {
+    match name.chars().next() {
+        Some(first) if first.is_ascii_alphabetic() || first == '_' => name
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '_'),
+        _ => false,
+    }
+}
+
+// Escape sequences standing for a single control character
+// pairs of (character after the slash, decoded character)
+pub const CONTROL_ESCAPES: [(char, char); 6] = [
+    ('n', '\n'),
+    ('t', '\t'),
+    ('r', '\r'),
+    ('f', '\x0C'),
+    ('v', '\x0B'),
+    ('0', '\0'),
+];
+
 pub struct Scanner {
     // source string characters vector to allow fast access
     source: Vec<char>,
@@ -25,12 +54,28 @@ pub struct Scanner {
     // when it's true it means we already generated EmtpyString token or we could not do so
     // rather we should attempt to generate another token (if any remaining)
     found_empty_string: bool,
+    // index right after the most recently scanned escape sequence (like `\|`)
+    // so that an escaped ( or | is never mistaken for an actual metacharacter
+    // when deciding whether to generate an Empty token
+    escape_end: Option<usize>,
+    // when true, a slash before an ordinary character (like `\c`) or at end of
+    // source string generates an InvalidEscape token instead of a literal slash
+    strict_escapes: bool,
+    // index right after the most recently scanned opening of a group
+    // either a ( or a (?<name> so that an Empty token is generated in `(?<name>)`
+    group_open_end: Option<usize>,
 }
 
 // an Iterator transforming source string into a tokens stream
 // each toekn is generated on request
 impl Scanner {
     pub fn new(source: &str) -> Scanner {
+        Scanner::with_strict_escapes(source, false)
+    }
+
+    // Create a scanner which (if `strict_escapes` is true) reports
+    // unknown escapes like `\c` instead of reading them as a literal slash
+    pub fn with_strict_escapes(source: &str, strict_escapes: bool) -> Scanner {
         // source characters as a vector for fast access
         let source = source.chars().collect::<Vec<_>>();
         // current (`processed` or `to be processed`) character
@@ -39,10 +84,17 @@ impl Scanner {
         // because the empty string can occur anywhere with an abitrary string
         // even within the empty string (which is itself)
         let found_empty_string = false;
+        // no escape sequences scanned yet
+        let escape_end = None;
+        // no groups scanned yet
+        let group_open_end = None;
         Scanner {
             source,
             current,
             found_empty_string,
+            escape_end,
+            strict_escapes,
+            group_open_end,
         }
     }
 
@@ -106,6 +158,155 @@ impl Scanner {
     fn get_next_char(&self) -> char {
         self.get_char_at(self.current, 1)
     }
+
+    // read up to `max_digits` hexadecimal digits starting at index `start`
+    // return the digits value and how many digits were read
+    fn read_hex_digits(&self, start: usize, max_digits: usize) -> (u32, usize) {
+        let mut value = 0_u32;
+        let mut digits = 0;
+        while digits < max_digits {
+            match self
+                .source
+                .get(start + digits)
+                .and_then(|ch| ch.to_digit(16))
+            {
+                Some(digit) => {
+                    value = value * 16 + digit;
+                    digits += 1;
+                }
+                None => break,
+            }
+        }
+        (value, digits)
+    }
+
+    // Read the opening of a named group `(?<name>` starting at current character (a ()
+    // and move past it
+    // A name is an ASCII letter or _ followed by ASCII letters, digits or _
+    // Return the type of token this group opening produces
+    fn scan_group_name(&mut self) -> TokenType {
+        let start = self.current;
+        // skip (?<
+        let name_start = start + 3;
+        let name_length = self.source[name_start.min(self.source.len())..]
+            .iter()
+            .take_while(|ch| ch.is_ascii_alphanumeric() || **ch == '_')
+            .count();
+        self.current = name_start + name_length;
+
+        let first_char = self.get_char_at(name_start, 0);
+        if name_length > 0 && !first_char.is_ascii_digit() && self.get_peek_char() == '>' {
+            // move past closing >
+            self.advance();
+            return NamedLeftParen { name_length };
+        }
+
+        if self.has_next() {
+            // include the offending character
+            self.advance();
+        }
+        InvalidGroupName {
+            length: self.current - start,
+        }
+    }
+
+    // Decode the escape sequence starting at current character (a slash)
+    // and move past it
+    // The slash is known NOT to precede an anchor character
+    // Return the type of token this escape sequence produces
+    fn scan_escape(&mut self) -> TokenType {
+        let start = self.current;
+        let next_char = self.get_next_char();
+
+        if start + 1 >= self.source.len() {
+            // A trailing slash, nothing to escape
+            // it matches itself
+            self.advance();
+            if self.strict_escapes {
+                return InvalidEscape {
+                    error: EscapeError::TrailingSlash,
+                    length: 1,
+                };
+            }
+            return Character { value: '\\' };
+        }
+
+        if is_metacharacter(next_char) {
+            // EscapedMetacharacter => "\" Metacharacter
+            // strip the metacharacter from its special meaning
+            self.current += 2;
+            return Character { value: next_char };
+        }
+
+        if let Some((_, value)) = CONTROL_ESCAPES.iter().find(|(ch, _)| *ch == next_char) {
+            // \n \t \r \f \v \0
+            self.current += 2;
+            return Character { value: *value };
+        }
+
+        match next_char {
+            'x' => {
+                // \xHH, exactly two hexadecimal digits
+                let (value, digits) = self.read_hex_digits(start + 2, 2);
+                self.current = start + 2 + digits;
+                if digits < 2 {
+                    return InvalidEscape {
+                        error: EscapeError::MalformedHexEscape,
+                        length: self.current - start,
+                    };
+                }
+                // two hexadecimal digits never exceed 0xFF, always a valid character
+                Character {
+                    value: char::from_u32(value).unwrap(),
+                }
+            }
+            'u' => {
+                // \u{H...}, one to six hexadecimal digits
+                if self.get_char_at(start, 2) != '{' {
+                    self.current = start + 2;
+                    return InvalidEscape {
+                        error: EscapeError::MalformedUnicodeEscape,
+                        length: 2,
+                    };
+                }
+                let (value, digits) = self.read_hex_digits(start + 3, 6);
+                self.current = start + 3 + digits;
+                if digits == 0 || self.get_peek_char() != '}' {
+                    if self.has_next() {
+                        // include the offending character
+                        self.advance();
+                    }
+                    return InvalidEscape {
+                        error: EscapeError::MalformedUnicodeEscape,
+                        length: self.current - start,
+                    };
+                }
+                // move past closing }
+                self.advance();
+                match char::from_u32(value) {
+                    Some(value) => Character { value },
+                    None => InvalidEscape {
+                        error: EscapeError::InvalidCodePoint,
+                        length: self.current - start,
+                    },
+                }
+            }
+            _ => {
+                if self.strict_escapes {
+                    // Likely a typo, like \d which is not supported
+                    self.current += 2;
+                    return InvalidEscape {
+                        error: EscapeError::UnknownEscape,
+                        length: 2,
+                    };
+                }
+                // A slash before an ordinary character does nothing
+                // the expression `\c` matches a slash followed by a lowercase C
+                self.advance();
+                Character { value: '\\' }
+            }
+        }
+    }
 }
 
 impl Iterator for Scanner {
@@ -122,10 +323,11 @@ impl Iterator for Scanner {
         let previous_car = self.get_previous_char();
         // if certain characters "( | )" are adjacent with the former not escaped
         // we can generate an Empty token
-        // self.source[self.current - 2] (if exists) is second to current character
-        // it's where to look to check whether previous character is preceeded by a slash
-        // which means it's escaped
-        let is_prev_escaped = self.get_char_at(self.current, -2) == '\\';
+        // if previous character ended an escape sequence (like `\(`)
+        // then it's an ordinary character and not a metacharacter
+        let is_prev_escaped = self.escape_end == Some(self.current);
+        // if previous characters opened a group, either ( or (?<name>
+        let is_prev_group_open = self.group_open_end == Some(self.current);
         if !is_prev_escaped && !self.found_empty_string {
             // Set flag (self.found_empty_string) to not attempt to generate Empty token
             // if previous iteration did
@@ -157,9 +359,9 @@ impl Iterator for Scanner {
                 (previous_car == '|' && peek_char == '|') ||
 
                 // CASE 5
-                // "...(|...)..."
+                // "...(|...)..." or "...(?<name>|...)..."
                 // emit `Empty` AFTER ( and BEFORE |
-                (previous_car == '(' && peek_char == '|') ||
+                (is_prev_group_open && peek_char == '|') ||
 
                 // CASE 6
                 // "...(...|)..."
@@ -167,9 +369,9 @@ impl Iterator for Scanner {
                 (previous_car == '|' && peek_char == ')') ||
 
                 // CASE 7
-                // "...()..."
+                // "...()..." or "...(?<name>)..."
                 // emit `Empty` AFTER ( and BEFORE )
-                (previous_car == '(' && peek_char == ')')
+                (is_prev_group_open && peek_char == ')')
             ) {
                 // Note that we do not call advance()
                 // because Empty contains no characters at all
@@ -224,8 +426,15 @@ impl Iterator for Scanner {
         let next_token = next.as_mut().unwrap(); //&mut Token
 
         match peek_char {
+            '(' if next_char == '?' && self.get_char_at(self.current, 2) == '<' => {
+                // Named group, like (?<year>...)
+                next_token.type_name = self.scan_group_name();
+                self.group_open_end = Some(self.current);
+                return next;
+            }
             '(' => {
                 next_token.type_name = LeftParen;
+                self.group_open_end = Some(self.current + 1);
             }
             ')' => {
                 next_token.type_name = RightParen;
@@ -258,6 +467,12 @@ impl Iterator for Scanner {
                 }
                 return next;
             }
+            '\\' => {
+                // Escape sequences, like \n or \x41 or \u{1F600} or \*
+                next_token.type_name = self.scan_escape();
+                self.escape_end = Some(self.current);
+                return next;
+            }
             _ => {
                 // Any other ordinary character.
                 // that's, not a metacharacter and an escaped metacharacter
@@ -24,13 +24,50 @@ pub enum TokenType {
     Character { value: char },
 
     // METACHARACTERS
-    LeftParen,  // (
+    LeftParen, // (
+    // (?<name>, opening ( of a named group
+    // `name_length` is how many characters of the name follow (?<
+    NamedLeftParen { name_length: usize },
     RightParen, // )
     Pipe,       // |, alternation operator (E1|E2|...|E_n)
     Mark,       // ?, match zero or one occurrence of previous expression
     Star,       // *, match zero or more occurrences of previous expression
     Plus,       // +, match zero or more occurrences of previous expression
     Dot,        // ., match any single character even newline `\n`
+
+    // ERRORS
+    // A malformed escape sequence like `\xZ1` or `\u{110000}`
+    // `length` is how many characters of the source string it spans
+    // the parser reports it as a syntax error
+    InvalidEscape { error: EscapeError, length: usize },
+    // A malformed group name like `(?<1st>` or `(?<name`
+    // `length` is how many characters of the source string it spans
+    // the parser reports it as a syntax error
+    InvalidGroupName { length: usize },
+}
+
+// Why an escape sequence could not be decoded
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum EscapeError {
+    MalformedHexEscape,     // \x not followed by exactly two hexadecimal digits
+    MalformedUnicodeEscape, // \u not followed by {H...} with one to six hexadecimal digits
+    InvalidCodePoint,       // \u{...} is a surrogate or above 10FFFF
+    // Strict escapes only
+    UnknownEscape, // a slash before a character which is neither a metacharacter nor begins an escape sequence, like \c
+    TrailingSlash, // a slash at the end of pattern with nothing to escape
+}
+
+impl std::fmt::Display for EscapeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let string_value = match self {
+            Self::MalformedHexEscape => "Malformed hexadecimal escape",
+            Self::MalformedUnicodeEscape => "Malformed unicode escape",
+            Self::InvalidCodePoint => "Invalid unicode code point",
+            Self::UnknownEscape => "Unknown escape sequence",
+            Self::TrailingSlash => "Trailing slash",
+        };
+        write!(f, "{string_value}")
+    }
 }
 
 // Scanner generates `Tokens` which are a atoms of regular expressions
@@ -0,0 +1,116 @@
+// A tree builder rejects expressions which don't make a valid tree instead of panicking
+
+use std::sync::Arc;
+
+use regexps::parser::syntax_tree::*;
+
+fn character(value: char) -> Node {
+    Node::new(ExpressionType::CharacterExpression {
+        value: Some(value),
+        quantifier: Quantifier::None,
+    })
+}
+
+fn node(expression_type: ExpressionType, children: Vec<NodeId>) -> Node {
+    let mut node = Node::new(expression_type);
+    node.children = children;
+    node
+}
+
+fn group(name: &str, children: Vec<NodeId>) -> Node {
+    let mut node = node(
+        ExpressionType::Group {
+            quantifier: Quantifier::None,
+            index: 0,
+        },
+        children,
+    );
+    node.group_name = Some(Arc::from(name));
+    node
+}
+
+// Kind of the error and offending expression building `root`
+fn error(builder: TreeBuilder, root: NodeId) -> (BuildErrorKind, NodeId) {
+    let error = builder.build(root).unwrap_err();
+    (error.kind, error.id)
+}
+
+#[test]
+fn invalid_children() {
+    let mut builder = TreeBuilder::new();
+    let a = builder.add(character('a'));
+    let star = builder.add(Node::new(ExpressionType::CharacterExpression {
+        value: Some('b'),
+        quantifier: Quantifier::ZeroOrMore,
+    }));
+    let set = builder.add(node(
+        ExpressionType::CharacterSet {
+            quantifier: Quantifier::None,
+        },
+        vec![a, star],
+    ));
+    assert_eq!(error(builder, set), (BuildErrorKind::InvalidChildren, set));
+
+    let mut builder = TreeBuilder::new();
+    let a = builder.add(character('a'));
+    let b = builder.add(node(
+        ExpressionType::CharacterExpression {
+            value: Some('b'),
+            quantifier: Quantifier::None,
+        },
+        vec![a],
+    ));
+    assert_eq!(error(builder, b), (BuildErrorKind::InvalidChildren, b));
+}
+
+#[test]
+fn not_a_tree() {
+    let mut builder = TreeBuilder::new();
+    let a = builder.add(character('a'));
+    let concatenation = builder.add(node(ExpressionType::Concatenation, vec![a, a]));
+    assert_eq!(error(builder, concatenation), (BuildErrorKind::NotATree, a));
+
+    // A child added after its parent, ids given by another builder
+    let mut other = TreeBuilder::new();
+    other.add(character('a'));
+    let second = other.add(character('b'));
+    let mut builder = TreeBuilder::new();
+    let alternation = builder.add(node(ExpressionType::Alternation, vec![second]));
+    builder.add(character('a'));
+    assert_eq!(
+        error(builder, alternation),
+        (BuildErrorKind::NotATree, alternation)
+    );
+
+    // A root never added
+    assert_eq!(
+        error(TreeBuilder::new(), second),
+        (BuildErrorKind::NotATree, second)
+    );
+}
+
+#[test]
+fn invalid_group_names() {
+    let mut builder = TreeBuilder::new();
+    let a = builder.add(character('a'));
+    let invalid = builder.add(group("1st", vec![a]));
+    assert_eq!(
+        error(builder, invalid),
+        (BuildErrorKind::InvalidGroupName, invalid)
+    );
+
+    let mut builder = TreeBuilder::new();
+    let a = builder.add(character('a'));
+    let first = builder.add(group("x", vec![a]));
+    let b = builder.add(character('b'));
+    let second = builder.add(group("x", vec![b]));
+    let root = builder.add(node(ExpressionType::Concatenation, vec![first, second]));
+    let (kind, _) = error(builder, root);
+    assert_eq!(kind, BuildErrorKind::DuplicateGroupName);
+
+    // Valid names build
+    let mut builder = TreeBuilder::new();
+    let a = builder.add(character('a'));
+    let root = builder.add(group("x", vec![a]));
+    assert_eq!(builder.build(root).unwrap().to_pattern(), "(?<x>a)");
+}
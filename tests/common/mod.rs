@@ -0,0 +1,235 @@
+// Random patterns, syntax trees and texts for the property tests
+// Each test file uses some of them
+#![allow(dead_code)]
+
+use regexps::parser::combinators::{self, Pattern};
+use regexps::parser::syntax_tree::*;
+use regexps::regex::Regex;
+
+// Xorshift generator, seeded so a failing case can be run again
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    // A number in `0..bound`
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+}
+
+// Characters of generated patterns, which texts are made of
+pub const ALPHABET: [char; 12] = [
+    'a', 'b', 'a', 'A', 'é', '\n', '\t', '\0', '\u{7}', '😀', '.', '|',
+];
+
+// Pieces of pattern strings matching one character, written in every way the syntax allows
+const ATOMS: [&str; 22] = [
+    "a",
+    "b",
+    "a",
+    ".",
+    "A",
+    "\\x41",
+    "é",
+    "\\u{E9}",
+    "\\n",
+    "\\t",
+    "\\0",
+    "\\u{7}",
+    "\\u{1F600}",
+    "😀",
+    "\\.",
+    "\\|",
+    "\\(",
+    "\\)",
+    "\\\\",
+    "\\?",
+    "\\*",
+    "\\+",
+];
+
+const QUANTIFIERS: [Quantifier; 6] = [
+    Quantifier::None,
+    Quantifier::None,
+    Quantifier::None,
+    Quantifier::ZeroOrOne,
+    Quantifier::ZeroOrMore,
+    Quantifier::OneOrMore,
+];
+
+// Groups nested in generated patterns at most
+const DEPTH: usize = 3;
+
+// Random pattern string with escapes, empty branches, nested and named groups
+pub fn pattern(rng: &mut Rng) -> String {
+    pattern_at(rng, 0, &mut 0)
+}
+
+fn pattern_at(rng: &mut Rng, depth: usize, names: &mut usize) -> String {
+    let branches = match rng.below(3) {
+        0 => 2 + rng.below(2),
+        _ => 1,
+    };
+    let mut pattern = vec![];
+    for _ in 0..branches {
+        let mut branch = String::new();
+        for _ in 0..rng.below(4) {
+            if depth < DEPTH && rng.below(5) == 0 {
+                if rng.below(3) == 0 {
+                    *names += 1;
+                    branch.push_str(&format!("(?<g{names}>"));
+                } else {
+                    branch.push('(');
+                }
+                branch.push_str(&pattern_at(rng, depth + 1, names));
+                branch.push(')');
+            } else {
+                let atom = *rng.pick(&ATOMS);
+                branch.push_str(atom);
+            }
+            branch.push_str(&rng.pick(&QUANTIFIERS).to_string());
+        }
+        pattern.push(branch);
+    }
+    pattern.join("|")
+}
+
+// Where an expression of a generated tree is, which decides what it can be
+// to make a tree the parser could give
+#[derive(Clone, Copy, PartialEq)]
+enum Place {
+    // Root or inside a group
+    Alone,
+    Branch,
+    Item,
+}
+
+// Random tree shaped like the ones `Parser::parse` gives, built with `TreeBuilder`
+pub fn tree(rng: &mut Rng) -> SyntaxTree {
+    let mut builder = TreeBuilder::new();
+    let root = expression(rng, &mut builder, Place::Alone, 0, &mut 0);
+    builder.build(root).unwrap()
+}
+
+fn expression(
+    rng: &mut Rng,
+    builder: &mut TreeBuilder,
+    place: Place,
+    depth: usize,
+    names: &mut usize,
+) -> NodeId {
+    // Empty, character, group, concatenation, alternation
+    let kinds: &[usize] = match (place, depth < DEPTH) {
+        (Place::Item, true) => &[1, 1, 2],
+        (Place::Item, false) => &[1],
+        (Place::Branch, true) => &[0, 1, 2, 3],
+        (Place::Branch, false) => &[0, 1, 3],
+        (Place::Alone, true) => &[0, 1, 2, 3, 4],
+        (Place::Alone, false) => &[0, 1, 3],
+    };
+    let node = match rng.pick(kinds) {
+        0 => Node::new(ExpressionType::EmptyExpression),
+        1 => Node::new(ExpressionType::CharacterExpression {
+            value: match rng.below(6) {
+                0 => None,
+                _ => Some(*rng.pick(&ALPHABET)),
+            },
+            quantifier: *rng.pick(&QUANTIFIERS),
+        }),
+        2 => {
+            let child = expression(rng, builder, Place::Alone, depth + 1, names);
+            let mut node = Node::new(ExpressionType::Group {
+                quantifier: *rng.pick(&QUANTIFIERS),
+                index: 0,
+            });
+            if rng.below(3) == 0 {
+                *names += 1;
+                node.group_name = Some(format!("g{names}").into());
+            }
+            node.children = vec![child];
+            node
+        }
+        3 => {
+            let mut node = Node::new(ExpressionType::Concatenation);
+            node.children = (0..2 + rng.below(3))
+                .map(|_| expression(rng, builder, Place::Item, depth, names))
+                .collect();
+            node
+        }
+        _ => {
+            let mut node = Node::new(ExpressionType::Alternation);
+            node.children = (0..2 + rng.below(2))
+                .map(|_| expression(rng, builder, Place::Branch, depth, names))
+                .collect();
+            node
+        }
+    };
+    builder.add(node)
+}
+
+// Random pattern made with combinators
+pub fn combinators(rng: &mut Rng) -> Pattern {
+    combinators_at(rng, 0, &mut 0)
+}
+
+fn combinators_at(rng: &mut Rng, depth: usize, names: &mut usize) -> Pattern {
+    let kind = match depth < DEPTH {
+        true => rng.below(9),
+        false => rng.below(4),
+    };
+    match kind {
+        0 => combinators::character(*rng.pick(&ALPHABET)),
+        1 => combinators::any(),
+        2 => combinators::empty(),
+        3 => combinators::literal(&text(rng, 3)),
+        4 | 5 => combinators_at(rng, depth + 1, names).then(combinators_at(rng, depth + 1, names)),
+        6 => combinators_at(rng, depth + 1, names).or(combinators_at(rng, depth + 1, names)),
+        7 => {
+            let pattern = combinators_at(rng, depth + 1, names);
+            match rng.below(3) {
+                0 => {
+                    *names += 1;
+                    combinators::named_group(&format!("g{names}"), pattern)
+                }
+                _ => combinators::group(pattern),
+            }
+        }
+        _ => {
+            let pattern = combinators_at(rng, depth + 1, names);
+            match rng.below(3) {
+                0 => pattern.optional(),
+                1 => pattern.zero_or_more(),
+                _ => pattern.one_or_more(),
+            }
+        }
+    }
+}
+
+// Random text of at most `length` characters of `ALPHABET`
+pub fn text(rng: &mut Rng, length: usize) -> String {
+    (0..rng.below(length + 1))
+        .map(|_| *rng.pick(&ALPHABET))
+        .collect()
+}
+
+// Ranges of all matches of `regex` in `text`
+pub fn matches(regex: &Regex, text: &str) -> Vec<(usize, usize)> {
+    regex
+        .find_iter(text)
+        .map(|found| (found.start(), found.end()))
+        .collect()
+}
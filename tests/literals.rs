@@ -0,0 +1,102 @@
+// Searching for literals first, with a prefilter or an automaton of alternatives,
+// finds the same matches as trying the pattern at every position
+
+mod common;
+
+use common::Rng;
+use regexps::matcher::Matcher;
+use regexps::regex::{Regex, RegexBuilder};
+
+const CASES: u64 = 3000;
+
+// Characters of texts, none of them has another case
+const ALPHABET: [char; 8] = ['1', '2', '-', '=', '0', '😀', ' ', '\n'];
+
+// Strings of `ALPHABET`, alone and in alternations
+const LITERALS: [&str; 8] = ["1", "12", "21", "-=", "1-1", "😀0", "==", "\\n2"];
+
+// Pieces around literals, which may also match them
+const OTHERS: [&str; 8] = [".", "1?", "(.)*", "-+", "(2|=)+", "0*", ".?", ""];
+
+// Random pattern made of literals and alternations of literals
+fn pattern(rng: &mut Rng, names: &mut usize) -> String {
+    let mut pattern = String::new();
+    for _ in 0..1 + rng.below(4) {
+        let piece = match rng.below(4) {
+            0 => rng.pick(&OTHERS).to_string(),
+            1 => rng.pick(&LITERALS).to_string(),
+            _ => {
+                let branches = (0..2 + rng.below(4))
+                    .map(|_| *rng.pick(&LITERALS))
+                    .collect::<Vec<_>>();
+                let name = if rng.below(3) == 0 {
+                    *names += 1;
+                    format!("?<g{names}>")
+                } else {
+                    String::new()
+                };
+                let quantifier = *rng.pick(&["", "", "?", "*", "+"]);
+                format!("({name}{}){quantifier}", branches.join("|"))
+            }
+        };
+        pattern.push_str(&piece);
+    }
+    if rng.below(4) == 0 {
+        pattern = format!("{pattern}|{}", self::pattern(rng, names));
+    }
+    pattern
+}
+
+// Random text of `ALPHABET` with some literals in it
+fn text(rng: &mut Rng) -> String {
+    let mut text = String::new();
+    for _ in 0..rng.below(8) {
+        match rng.below(3) {
+            0 => text.push_str(&rng.pick(&LITERALS).replace("\\n", "\n")),
+            _ => text.push(*rng.pick(&ALPHABET)),
+        }
+    }
+    text
+}
+
+// Byte ranges of matches given by `matcher`, which gives character ranges
+fn byte_ranges(matcher: Matcher, text: &str) -> Vec<(usize, usize)> {
+    let offsets = text
+        .char_indices()
+        .map(|(offset, _)| offset)
+        .chain([text.len()])
+        .collect::<Vec<_>>();
+    matcher
+        .map(|range| (offsets[range.start], offsets[range.end]))
+        .collect()
+}
+
+#[test]
+fn same_matches_without_literal_search() {
+    for seed in 0..CASES {
+        let mut rng = Rng::new(seed);
+        let pattern = pattern(&mut rng, &mut 0);
+        let regex = Regex::new(&pattern).unwrap();
+        // A case insensitive regex never searches for literals
+        // and over characters without case it matches like any other
+        let baseline = RegexBuilder::new()
+            .case_insensitive(true)
+            .build(&pattern)
+            .unwrap();
+        for _ in 0..4 {
+            let text = text(&mut rng);
+            let expected = byte_ranges(baseline.matcher(&text), &text);
+            assert_eq!(
+                common::matches(&regex, &text),
+                expected,
+                "{pattern:?} on {text:?}"
+            );
+            let matcher = Matcher::new(&pattern, &text).unwrap();
+            assert_eq!(
+                byte_ranges(matcher, &text),
+                expected,
+                "{pattern:?} on {text:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,92 @@
+// Backtracking always ends, giving back a shorter match each time
+
+use std::sync::Arc;
+
+use regexps::parser::optimize::Optimizer;
+use regexps::parser::Parser;
+use regexps::regex::Regex;
+
+fn find_all(pattern: &str, text: &str) -> Vec<(usize, usize)> {
+    let regex = Regex::new(pattern).unwrap();
+    regex
+        .find_iter(text)
+        .map(|found| (found.start(), found.end()))
+        .collect()
+}
+
+// `a?a` on "a" hung: a backtracking `a?` ignored its bound and took the same `a` again
+#[test]
+fn a_optional_then_a_gives_back_its_character() {
+    assert_eq!(find_all("a?a", "a"), vec![(0, 1)]);
+    assert_eq!(find_all("a?a", "ba"), vec![(1, 2)]);
+    assert_eq!(find_all("a?b", "aab"), vec![(1, 3)]);
+    assert_eq!(find_all("a?a?a", "aa"), vec![(0, 2)]);
+}
+
+// `(b|c?).` on "b" needs a group without quantifier to give back a shorter match when backtracked
+#[test]
+fn b_or_c_optional_then_dot_stops_backtracking() {
+    // Alternation branches are not tried again, so `c?` is never tried
+    assert_eq!(find_all("(b|c?).", "b"), vec![]);
+    assert_eq!(find_all("(b|bc).", "bc"), vec![(0, 2)]);
+}
+
+// `(.*|)*` on "ab" needs `.*` to stay at or after its start position when backtracked
+#[test]
+fn dot_star_never_moves_backwards() {
+    assert_eq!(find_all("(.*|)*", "ab"), vec![(0, 2), (2, 2)]);
+    assert_eq!(find_all(".*a", "aab"), vec![(0, 2)]);
+    assert_eq!(find_all("x.*y", "xayby"), vec![(0, 5)]);
+}
+
+// `x|((\.()*|.+)+0*a)` on "|." needs a concatenation to find entries of the backtrack table
+// after entries of inner expressions were inserted
+#[test]
+fn dot_plus_in_repeated_group_ends() {
+    assert_eq!(find_all(r"x|((\.()*|.+)+0*a)", "|."), vec![]);
+}
+
+// `(a*)?a` on "aba" needs `(a*)?` to match zero times when `a*` can't give back more
+#[test]
+fn a_star_optional_then_a_falls_back_to_zero_times() {
+    assert_eq!(find_all("(a*)?a", "aba"), vec![(0, 1), (2, 3)]);
+    assert_eq!(find_all("x(a*)?a", "xaa"), vec![(0, 3)]);
+}
+
+// `(.(.?))+` on "aab" needs each repetition to match `.(.?)` from scratch
+#[test]
+fn dot_dot_optional_repeated_starts_each_repetition_over() {
+    // `(.?)` backtracks inside one repetition only
+    assert_eq!(find_all("(.(.?))+", "aab"), vec![(0, 3)]);
+}
+
+// `(.*.)c` on "xyc" needs a concatenation to fail a child ending after its match bound
+#[test]
+fn dot_star_dot_then_c_gives_back_a_character() {
+    assert_eq!(find_all("(.*.)c", "xyc"), vec![(0, 3)]);
+    assert_eq!(find_all("(.*.)b?c", "xyc"), vec![(0, 3)]);
+}
+
+// Optimized `(a*|b)c` on "abc" hung: the optimizer drops the group, leaving an
+// alternation right inside the concatenation which gave back the same match
+#[test]
+fn optimized_a_star_or_b_then_c_ends() {
+    let tree = Optimizer::new()
+        .keep_groups(false)
+        .optimize(&Parser::parse("(a*|b)c").unwrap());
+    let regex = Regex::from_regexp(&Arc::new(tree));
+    let found = regex
+        .find_iter("abc")
+        .map(|found| (found.start(), found.end()))
+        .collect::<Vec<_>>();
+    assert_eq!(found, vec![(2, 3)]);
+}
+
+#[test]
+fn alternation_of_strings_gives_back_a_shorter_branch() {
+    // Branches are plain strings, matched with an automaton
+    assert_eq!(find_all("(12|1)+2", "==12-1"), vec![(2, 4)]);
+    assert_eq!(find_all("(abc|ab|a)+c", "abc"), vec![(0, 3)]);
+    assert_eq!(find_all("(abc|ab)+c", "abc"), vec![(0, 3)]);
+    assert_eq!(find_all("(abc|ab)+bc", "abc"), vec![]);
+}
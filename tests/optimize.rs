@@ -0,0 +1,113 @@
+// An optimized tree gives the same matches as the tree it was made from
+
+mod common;
+
+use std::sync::Arc;
+
+use common::Rng;
+use regexps::matcher::Matcher;
+use regexps::parser::optimize::Optimizer;
+use regexps::parser::syntax_tree::{ExpressionType, SyntaxTree};
+use regexps::parser::Parser;
+use regexps::regex::Regex;
+
+const CASES: u64 = 3000;
+
+// Match `tree` and its optimized copies on a few random texts
+fn check(tree: &SyntaxTree, rng: &mut Rng) {
+    let original = Regex::from_regexp(&Arc::new(tree.clone()));
+    let optimized = tree.optimize();
+    // Character sets are saved and loaded back like other expressions
+    assert_eq!(
+        SyntaxTree::from_bytes(&optimized.to_bytes()).unwrap(),
+        optimized
+    );
+    let optimized = Regex::from_regexp(&Arc::new(optimized));
+    let dropped = Regex::from_regexp(&Arc::new(
+        Optimizer::new().keep_groups(false).optimize(tree),
+    ));
+    for _ in 0..4 {
+        let text = common::text(rng, 8);
+        let expected = common::matches(&original, &text);
+        for regex in [&optimized, &dropped] {
+            assert_eq!(
+                common::matches(regex, &text),
+                expected,
+                "{:?} optimized into {:?} on {text:?}",
+                tree.source(),
+                regex.as_str()
+            );
+        }
+    }
+}
+
+#[test]
+fn parsed_patterns() {
+    for seed in 0..CASES {
+        let mut rng = Rng::new(seed);
+        let tree = Parser::parse(&common::pattern(&mut rng)).unwrap();
+        check(&tree, &mut rng);
+    }
+}
+
+#[test]
+fn built_trees() {
+    for seed in 0..CASES {
+        let mut rng = Rng::new(seed);
+        let tree = common::tree(&mut rng);
+        check(&tree, &mut rng);
+    }
+}
+
+#[test]
+fn alternations_of_characters() {
+    // Runs of single characters between other branches, repeated or not
+    for seed in 0..CASES {
+        let mut rng = Rng::new(seed);
+        let branches = (0..2 + rng.below(4))
+            .map(|_| match rng.below(4) {
+                0 => String::from(*rng.pick(&["", "ab", "a*", "(b|a)+", ".?", "a|b"])),
+                _ => regexps::matcher::escape(&rng.pick(&common::ALPHABET).to_string()),
+            })
+            .collect::<Vec<_>>();
+        let quantifier = *rng.pick(&["", "?", "*", "+"]);
+        let pattern = format!("x({}){quantifier}y?", branches.join("|"));
+        let tree = Parser::parse(&pattern).unwrap();
+        check(&tree, &mut rng);
+    }
+}
+
+#[test]
+fn single_characters_become_one_set() {
+    let tree = Parser::parse("x(a|b|c)*|d|e").unwrap().optimize();
+    let sets = tree
+        .nodes()
+        .iter()
+        .filter(|node| matches!(node.expression_type, ExpressionType::CharacterSet { .. }))
+        .count();
+    assert_eq!(sets, 2);
+    assert_eq!(tree.to_pattern(), "x(a|b|c)*|d|e");
+
+    let regex = Regex::from_regexp(&Arc::new(tree));
+    assert_eq!(common::matches(&regex, "xabcad"), vec![(0, 5), (5, 6)]);
+    // A dot among them matches any character
+    assert_eq!(Parser::parse("a|.|b").unwrap().optimize().to_pattern(), ".");
+}
+
+#[test]
+fn groups_are_kept_by_default() {
+    let tree = Parser::parse("((?<x>a))(b|c)|(?<y>a)d").unwrap();
+    let optimized = Arc::new(tree.optimize());
+    assert_eq!(optimized.to_pattern(), "((?<x>a))(b|c)|(?<y>a)d");
+    let mut matcher = Matcher::from_regexp(&optimized, "-ac");
+    let captures = matcher.captures().unwrap();
+    assert_eq!(captures.len(), 5);
+    assert_eq!(captures.text(1).as_deref(), Some("a"));
+    assert_eq!(captures.name("x"), captures.get(2));
+    assert_eq!(captures.text(3).as_deref(), Some("c"));
+    assert_eq!(captures.get(4), None);
+
+    // Removing them changes captures
+    let dropped = Optimizer::new().keep_groups(false).optimize(&tree);
+    assert_eq!(dropped.to_pattern(), "a(b|c|d)");
+}
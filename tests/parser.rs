@@ -0,0 +1,84 @@
+// Escaped strings parse to patterns matching them literally
+// and strict escapes reject a slash escaping nothing
+
+mod common;
+
+use common::Rng;
+use regexps::matcher::escape;
+use regexps::parser::error::SyntaxErrorKind;
+use regexps::parser::{Parser, ParserBuilder};
+use regexps::regex::Regex;
+use regexps::scanner::tokens::EscapeError;
+use regexps::scanner::METACHARACTERS;
+
+const CASES: u64 = 2000;
+
+// Random text of characters of `common::ALPHABET`, metacharacters and letters
+// which begin escape sequences once after a slash
+fn text(rng: &mut Rng) -> String {
+    (0..rng.below(9))
+        .map(|_| match rng.below(3) {
+            0 => *rng.pick(&common::ALPHABET),
+            1 => *rng.pick(&METACHARACTERS),
+            _ => *rng.pick(&['n', 't', 'x', 'u', '{', '}', '0', 'c', 'A', 'b']),
+        })
+        .collect()
+}
+
+#[test]
+fn escaped_text_matches_itself() {
+    for seed in 0..CASES {
+        let mut rng = Rng::new(seed);
+        let text = text(&mut rng);
+        let pattern = escape(&text);
+        for parser in [
+            *ParserBuilder::new().strict_escapes(false),
+            ParserBuilder::new(),
+        ] {
+            let regex = Regex::from_regexp(&parser.parse(&pattern).unwrap());
+            let found = regex.find(&text).map(|found| found.range());
+            assert_eq!(found, Some(0..text.len()), "{pattern:?} on {text:?}");
+        }
+        assert!(Parser::parse(&pattern)
+            .unwrap()
+            .same_structure(Regex::literal(&text).regexp()));
+
+        // Found after any other text too
+        let haystack = format!("{}{text}", self::text(&mut rng));
+        let regex = Regex::new(&pattern).unwrap();
+        let found = regex
+            .find_iter(&haystack)
+            .last()
+            .map(|found| found.as_str());
+        assert_eq!(found, Some(text.as_str()), "{pattern:?} in {haystack:?}");
+    }
+}
+
+// Kind and position of the error of `pattern` parsed with strict escapes
+fn strict_error(pattern: &str) -> (SyntaxErrorKind, usize) {
+    let error = ParserBuilder::new().parse(pattern).unwrap_err();
+    (error.kind, error.position)
+}
+
+#[test]
+fn strict_escapes_reject_unknown_escape() {
+    let unknown = SyntaxErrorKind::InvalidEscape(EscapeError::UnknownEscape);
+    assert_eq!(strict_error("\\c"), (unknown, 0));
+    assert_eq!(strict_error("a(b|\\c)"), (unknown, 4));
+    assert_eq!(strict_error("é\\d+"), (unknown, 1));
+    // Not strict, the slash matches itself
+    let regex = Regex::new("\\c").unwrap();
+    assert_eq!(regex.find("a\\c").unwrap().range(), 1..3);
+}
+
+#[test]
+fn strict_escapes_reject_trailing_slash() {
+    let trailing = SyntaxErrorKind::InvalidEscape(EscapeError::TrailingSlash);
+    assert_eq!(strict_error("\\"), (trailing, 0));
+    assert_eq!(strict_error("ab\\"), (trailing, 2));
+    // An escaped slash at the end is fine
+    assert!(ParserBuilder::new().parse("ab\\\\").is_ok());
+    // Not strict, the slash matches itself
+    let regex = Regex::new("ab\\").unwrap();
+    assert_eq!(regex.find("xab\\").unwrap().range(), 1..4);
+}
@@ -0,0 +1,71 @@
+// A printed tree parses back to the same tree, which gives the same matches
+
+mod common;
+
+use std::sync::Arc;
+
+use common::Rng;
+use regexps::parser::syntax_tree::SyntaxTree;
+use regexps::parser::{Parser, ParserBuilder};
+use regexps::regex::Regex;
+
+const CASES: u64 = 3000;
+
+// Parse the printed pattern of `tree` and compare with it, on a few random texts
+fn check(tree: &SyntaxTree, rng: &mut Rng) {
+    let printed = tree.to_pattern();
+    let parsed = match Parser::parse(&printed) {
+        Ok(parsed) => parsed,
+        Err(error) => panic!("{printed:?} of {:?} doesn't parse\n{error}", tree.source()),
+    };
+    assert!(
+        parsed.same_structure(tree),
+        "{printed:?} parses to another tree than {:?}",
+        tree.source()
+    );
+    // Printed again the same, only with escapes allowed by strict escapes
+    assert_eq!(parsed.to_pattern(), printed);
+    assert!(ParserBuilder::new().parse(&printed).is_ok(), "{printed:?}");
+
+    let original = Regex::from_regexp(&Arc::new(tree.clone()));
+    let printed = Regex::from_regexp(&parsed);
+    for _ in 0..4 {
+        let text = common::text(rng, 8);
+        assert_eq!(
+            common::matches(&original, &text),
+            common::matches(&printed, &text),
+            "{:?} and {:?} on {text:?}",
+            original.as_str(),
+            printed.as_str()
+        );
+    }
+}
+
+#[test]
+fn parsed_patterns() {
+    for seed in 0..CASES {
+        let mut rng = Rng::new(seed);
+        let tree = Parser::parse(&common::pattern(&mut rng)).unwrap();
+        check(&tree, &mut rng);
+    }
+}
+
+#[test]
+fn built_trees() {
+    for seed in 0..CASES {
+        let mut rng = Rng::new(seed);
+        let tree = common::tree(&mut rng);
+        check(&tree, &mut rng);
+    }
+}
+
+#[test]
+fn combinator_patterns() {
+    for seed in 0..CASES {
+        let mut rng = Rng::new(seed);
+        let pattern = common::combinators(&mut rng);
+        let tree = pattern.build().unwrap();
+        assert_eq!(tree.source(), pattern.to_pattern());
+        check(&tree, &mut rng);
+    }
+}
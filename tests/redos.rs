@@ -0,0 +1,63 @@
+// Looking for backtracking shapes stays fast on long patterns
+
+use std::time::{Duration, Instant};
+
+use regexps::parser::redos::BacktrackingWarningKind;
+use regexps::parser::Parser;
+
+// `count` different characters, each followed by `quantifier`
+fn distinct(count: u32, quantifier: &str) -> String {
+    (0..count)
+        .map(|index| format!("{}{quantifier}", char::from_u32(0x4e00 + index).unwrap()))
+        .collect()
+}
+
+fn warnings(pattern: &str) -> (Vec<BacktrackingWarningKind>, Duration) {
+    let tree = Parser::parse(pattern).unwrap();
+    let start = Instant::now();
+    let warnings = tree.backtracking_warnings();
+    let kinds = warnings.iter().map(|warning| warning.kind).collect();
+    (kinds, start.elapsed())
+}
+
+#[test]
+fn long_runs_of_optional_characters() {
+    for quantifier in ["?", "*"] {
+        let (kinds, elapsed) = warnings(&distinct(3000, quantifier));
+        assert_eq!(kinds, vec![], "x{quantifier} repeated");
+        assert!(
+            elapsed < Duration::from_secs(10),
+            "x{quantifier} took {elapsed:?}"
+        );
+    }
+}
+
+#[test]
+fn long_runs_of_optional_characters_repeated() {
+    for quantifier in ["?", "*"] {
+        let (kinds, elapsed) = warnings(&format!("({})*", distinct(3000, quantifier)));
+        assert_eq!(kinds.len(), 1, "(x{quantifier} repeated)*");
+        assert!(kinds[0].is_exponential());
+        assert!(
+            elapsed < Duration::from_secs(10),
+            "(x{quantifier} repeated)* took {elapsed:?}"
+        );
+    }
+}
+
+#[test]
+fn long_runs_of_overlapping_quantifiers() {
+    let (kinds, elapsed) = warnings(&"a*".repeat(3000));
+    assert!(!kinds.is_empty());
+    assert!(
+        elapsed < Duration::from_secs(10),
+        "a* repeated took {elapsed:?}"
+    );
+
+    let (kinds, elapsed) = warnings(&format!(".*{}.*", distinct(3000, "?")));
+    assert_eq!(kinds, vec![BacktrackingWarningKind::OverlappingQuantifiers]);
+    assert!(
+        elapsed < Duration::from_secs(10),
+        ".*x?….* took {elapsed:?}"
+    );
+}
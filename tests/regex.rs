@@ -0,0 +1,78 @@
+// Matches borrowing the haystack are found where a matcher over the whole haystack finds them
+
+mod common;
+
+use common::Rng;
+use regexps::regex::Regex;
+
+const CASES: u64 = 2000;
+
+// Byte range of the first match of `regex` in `text` starting at or after character `start`
+// found by a matcher given all of `text`
+fn expected(regex: &Regex, text: &str, start: usize) -> Option<(usize, usize)> {
+    let offsets = text
+        .char_indices()
+        .map(|(offset, _)| offset)
+        .chain([text.len()])
+        .collect::<Vec<_>>();
+    let mut matcher = regex.matcher(text);
+    matcher.seek(start);
+    let found = matcher.next()?;
+    Some((offsets[found.start], offsets[found.end]))
+}
+
+#[test]
+fn find_at_every_boundary() {
+    for seed in 0..CASES {
+        let mut rng = Rng::new(seed);
+        let regex = Regex::new(&common::pattern(&mut rng)).unwrap();
+        let text = common::text(&mut rng, 8);
+        let boundaries = text
+            .char_indices()
+            .map(|(offset, _)| offset)
+            .chain([text.len()]);
+        for (start, offset) in boundaries.enumerate() {
+            let found = regex.find_at(&text, offset);
+            assert_eq!(
+                found.map(|found| (found.start(), found.end())),
+                expected(&regex, &text, start),
+                "{:?} on {text:?} from {offset}",
+                regex.as_str()
+            );
+            if let Some(found) = found {
+                assert_eq!(found.as_str(), &text[found.range()]);
+            }
+        }
+    }
+}
+
+#[test]
+fn find_iter_byte_offsets() {
+    for seed in 0..CASES {
+        let mut rng = Rng::new(seed);
+        let regex = Regex::new(&common::pattern(&mut rng)).unwrap();
+        let text = common::text(&mut rng, 8);
+        let characters = text.chars().collect::<Vec<_>>();
+        let found = regex
+            .find_iter(&text)
+            .map(|found| found.as_str())
+            .collect::<Vec<_>>();
+        let expected = regex
+            .matcher(&text)
+            .map(|range| characters[range].iter().collect::<String>())
+            .collect::<Vec<_>>();
+        assert_eq!(found, expected, "{:?} on {text:?}", regex.as_str());
+    }
+}
+
+#[test]
+#[should_panic(expected = "not a char boundary")]
+fn find_at_inside_character() {
+    Regex::new("a").unwrap().find_at("éa", 1);
+}
+
+#[test]
+#[should_panic(expected = "out of bounds")]
+fn find_at_past_end() {
+    Regex::new("a").unwrap().find_at("a", 2);
+}
@@ -0,0 +1,344 @@
+// The rgx command, run as a separate process on files and standard input
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+// Exit statuses, like those of grep
+const SELECTED: i32 = 0;
+const NOT_SELECTED: i32 = 1;
+const TROUBLE: i32 = 2;
+
+// Run rgx with `args`, feeding it `stdin`, return its exit status, output and error output
+fn rgx(args: &[&str], stdin: &str) -> (i32, String, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rgx"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // rgx may exit before reading its input, like on a syntax error
+    let _ = child.stdin.take().unwrap().write_all(stdin.as_bytes());
+    let output = child.wait_with_output().unwrap();
+    (
+        output.status.code().unwrap(),
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+// Run rgx and return its exit status and output, expecting no error output
+fn run(args: &[&str], stdin: &str) -> (i32, String) {
+    let (status, output, errors) = rgx(args, stdin);
+    assert_eq!(errors, "", "rgx {args:?}");
+    (status, output)
+}
+
+// A directory of files written by a test, removed once the test is over
+struct TempDir(PathBuf);
+
+impl TempDir {
+    // `name` tells apart directories of tests running at the same time
+    fn new(name: &str) -> TempDir {
+        let path = std::env::temp_dir().join(format!("rgx-{}-{name}", std::process::id()));
+        // Left over from a previous run which failed
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    // Write `contents` to file `name`, creating its directories, return its path as a string
+    fn file(&self, name: &str, contents: &str) -> String {
+        let path = self.0.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+const LOG: &str = "Error one\nok two\nERROR three\n";
+
+#[test]
+fn search_standard_input() {
+    assert_eq!(run(&["o+"], LOG), (SELECTED, "Error one\nok two\n".into()));
+    // - also stands for standard input
+    assert_eq!(run(&["two", "-"], LOG), (SELECTED, "ok two\n".into()));
+    assert_eq!(run(&["four"], LOG), (NOT_SELECTED, "".into()));
+    assert_eq!(run(&["four"], ""), (NOT_SELECTED, "".into()));
+}
+
+#[test]
+fn search_options() {
+    let ignore_case = (SELECTED, "Error one\nERROR three\n".into());
+    assert_eq!(run(&["-i", "error"], LOG), ignore_case);
+    assert_eq!(run(&["--ignore-case", "error"], LOG), ignore_case);
+    assert_eq!(run(&["error"], LOG), (NOT_SELECTED, "".into()));
+
+    assert_eq!(
+        run(&["-v", "Error"], LOG),
+        (SELECTED, "ok two\nERROR three\n".into())
+    );
+    assert_eq!(run(&["-v", "."], LOG), (NOT_SELECTED, "".into()));
+
+    assert_eq!(run(&["-c", "-i", "error"], LOG), (SELECTED, "2\n".into()));
+    // Counting nothing is still printed
+    assert_eq!(run(&["-c", "four"], LOG), (NOT_SELECTED, "0\n".into()));
+    assert_eq!(run(&["-vc", "one"], LOG), (SELECTED, "2\n".into()));
+
+    assert_eq!(
+        run(&["-n", "-v", "two"], LOG),
+        (SELECTED, "1:Error one\n3:ERROR three\n".into())
+    );
+    // One line for each match, empty matches are left out
+    assert_eq!(
+        run(&["-o", "-n", "o+|e|x?"], LOG),
+        (SELECTED, "1:o\n1:o\n1:e\n2:o\n2:o\n3:e\n3:e\n".into())
+    );
+    assert_eq!(
+        run(&["-o", "-i", "r+"], LOG),
+        (SELECTED, "rr\nr\nRR\nR\nr\n".into())
+    );
+    // Selected lines have no match to print
+    assert_eq!(run(&["-o", "-v", "two"], LOG), (SELECTED, "".into()));
+}
+
+#[test]
+fn search_files() {
+    let dir = TempDir::new("search-files");
+    let first = dir.file("first.txt", "abc\nxyz\n");
+    let second = dir.file("second.txt", "xab");
+    assert_eq!(run(&["y", &first], ""), (SELECTED, "xyz\n".into()));
+    // Lines of many files are named, the last line needs no line break
+    assert_eq!(
+        run(&["-n", "ab", &first, &second], ""),
+        (SELECTED, format!("{first}:1:abc\n{second}:1:xab\n"))
+    );
+    assert_eq!(
+        run(&["-c", "y", &first, "-", &second], "y\ny\n"),
+        (
+            SELECTED,
+            format!("{first}:1\n(standard input):2\n{second}:0\n")
+        )
+    );
+}
+
+#[test]
+fn search_recursively() {
+    let dir = TempDir::new("search-recursively");
+    let top = dir.file("b.txt", "abc\n");
+    let inner = dir.file("a/c.txt", "none\nxab\n");
+    let other = dir.file("a/d.txt", "none\n");
+    let path = dir.path().to_str().unwrap();
+    // Files are searched in order of names and always named
+    assert_eq!(
+        run(&["-r", "-n", "ab", path], ""),
+        (SELECTED, format!("{inner}:2:xab\n{top}:1:abc\n"))
+    );
+    assert_eq!(
+        run(&["--recursive", "-c", "none", path], ""),
+        (SELECTED, format!("{inner}:1\n{other}:1\n{top}:0\n"))
+    );
+    // Files given around a directory are named like the files inside it
+    let other_dir = TempDir::new("search-recursively-outside");
+    let outside = other_dir.file("e.txt", "ab\n");
+    assert_eq!(
+        run(&["-r", "ab", &outside, &format!("{path}/a"), &top], ""),
+        (SELECTED, format!("{outside}:ab\n{inner}:xab\n{top}:abc\n"))
+    );
+    // Working directory by default
+    let output = Command::new(env!("CARGO_BIN_EXE_rgx"))
+        .args(["-r", "ab"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(SELECTED));
+    assert_eq!(output.stdout, b"./a/c.txt:xab\n./b.txt:abc\n");
+}
+
+#[test]
+fn search_troubles() {
+    let dir = TempDir::new("search-troubles");
+    let file = dir.file("file.txt", "abc\n");
+    let path = dir.path().to_str().unwrap();
+
+    let (status, output, errors) = rgx(&["(a"], "a\n");
+    assert_eq!((status, output.as_str()), (TROUBLE, ""));
+    assert!(errors.contains("Expected )"), "{errors}");
+
+    let (status, _, errors) = rgx(&["-z", "a"], "a\n");
+    assert_eq!(status, TROUBLE);
+    assert!(errors.contains("unknown option `-z`"), "{errors}");
+
+    let (status, _, errors) = rgx(&["a"], "");
+    assert_eq!(status, NOT_SELECTED);
+    assert_eq!(errors, "");
+    let (status, _, errors) = rgx(&[], "a\n");
+    assert_eq!(status, TROUBLE);
+    assert!(errors.starts_with("Usage: rgx"), "{errors}");
+
+    // Other files are still searched but the exit status tells about the missing one
+    let missing = dir.path().join("missing.txt");
+    let missing = missing.to_str().unwrap();
+    let (status, output, errors) = rgx(&["a", missing, &file], "");
+    assert_eq!((status, output), (TROUBLE, format!("{file}:abc\n")));
+    assert!(errors.starts_with(&format!("rgx: {missing}: ")), "{errors}");
+
+    let (status, output, errors) = rgx(&["a", path], "");
+    assert_eq!((status, output.as_str()), (TROUBLE, ""));
+    assert_eq!(errors, format!("rgx: {path}: Is a directory\n"));
+
+    assert_eq!(run(&["--help"], ""), (SELECTED, run(&["-h"], "").1));
+}
+
+const TEXT: &str = "ab\ncd\nab\n";
+
+#[test]
+fn substitute_standard_input() {
+    assert_eq!(
+        run(&["s", "a(b)", "[$1]"], TEXT),
+        (SELECTED, "[b]\ncd\n[b]\n".into())
+    );
+    assert_eq!(
+        run(&["sub", "-i", "A", "x", "-"], TEXT),
+        (SELECTED, "xb\ncd\nxb\n".into())
+    );
+    // Nothing replaced, text is printed as is
+    assert_eq!(run(&["s", "x", "y"], TEXT), (NOT_SELECTED, TEXT.into()));
+}
+
+#[test]
+fn substitute_whole_text() {
+    // Matches span lines, unlike sed which substitutes each line on its own
+    assert_eq!(
+        run(&["s", "b.c", "-"], TEXT),
+        (SELECTED, "a-d\nab\n".into())
+    );
+    assert_eq!(
+        run(&["s", "\\n", " "], TEXT),
+        (SELECTED, "ab cd ab ".into())
+    );
+    assert_eq!(run(&["s", "b(.)*", "!"], TEXT), (SELECTED, "a!".into()));
+}
+
+#[test]
+fn substitute_count() {
+    assert_eq!(
+        run(&["s", "-c", "1", "ab", "x"], TEXT),
+        (SELECTED, "x\ncd\nab\n".into())
+    );
+    assert_eq!(
+        run(&["s", "--count=5", "ab", "x"], TEXT),
+        (SELECTED, "x\ncd\nx\n".into())
+    );
+    // Each file has its own count
+    let dir = TempDir::new("substitute-count");
+    let first = dir.file("first.txt", TEXT);
+    let second = dir.file("second.txt", "ab ab");
+    assert_eq!(
+        run(&["s", "-c1", "ab", "x", &first, &second], ""),
+        (SELECTED, "x\ncd\nab\nx ab".into())
+    );
+    // Replacing none at most is nothing replaced
+    assert_eq!(
+        run(&["s", "-c", "0", "ab", "x"], TEXT),
+        (NOT_SELECTED, TEXT.into())
+    );
+
+    let (status, _, errors) = rgx(&["s", "-c", "many", "ab", "x"], TEXT);
+    assert_eq!(status, TROUBLE);
+    assert!(errors.contains("invalid count `many`"), "{errors}");
+    let (status, _, errors) = rgx(&["s", "ab", "x", "-c"], TEXT);
+    assert_eq!(status, TROUBLE);
+    assert!(errors.contains("option `-c` requires a value"), "{errors}");
+}
+
+#[test]
+fn substitute_in_place() {
+    let dir = TempDir::new("substitute-in-place");
+    let first = dir.file("first.txt", TEXT);
+    let second = dir.file("second.txt", "cd\n");
+    assert_eq!(
+        run(&["s", "-w", "a(b)", "[$1]", &first, &second], ""),
+        (SELECTED, "".into())
+    );
+    assert_eq!(fs::read_to_string(&first).unwrap(), "[b]\ncd\n[b]\n");
+    assert_eq!(fs::read_to_string(&second).unwrap(), "cd\n");
+    // No temporary file is left behind
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+
+    assert_eq!(
+        run(&["s", "--in-place", "x", "y", &first], ""),
+        (NOT_SELECTED, "".into())
+    );
+    assert_eq!(fs::read_to_string(&first).unwrap(), "[b]\ncd\n[b]\n");
+
+    let (status, _, errors) = rgx(&["s", "-w", "a", "b"], TEXT);
+    assert_eq!(status, TROUBLE);
+    assert_eq!(errors, "rgx s: can not edit standard input in place\n");
+}
+
+#[test]
+fn substitute_dry_run() {
+    let dir = TempDir::new("substitute-dry-run");
+    let file = dir.file("file.txt", TEXT);
+    let diff = format!(
+        "--- {file}\n+++ {file}\n\
+         @@ -1,1 +1,1 @@\n-ab\n+xb\n\
+         @@ -3,1 +3,1 @@\n-ab\n+xb\n"
+    );
+    assert_eq!(
+        run(&["s", "-d", "a", "x", &file], ""),
+        (SELECTED, diff.clone())
+    );
+    // A dry run never writes, even in place
+    assert_eq!(
+        run(&["s", "-d", "-w", "a", "x", &file], ""),
+        (SELECTED, diff)
+    );
+    assert_eq!(fs::read_to_string(&file).unwrap(), TEXT);
+
+    // A match spanning lines joins them
+    assert_eq!(
+        run(&["s", "--dry-run", "b.c", "", "-"], TEXT),
+        (
+            SELECTED,
+            "--- (standard input)\n+++ (standard input)\n\
+             @@ -1,2 +1,1 @@\n-ab\n-cd\n+ad\n"
+                .into()
+        )
+    );
+    assert_eq!(run(&["s", "-d", "x", "y"], TEXT), (NOT_SELECTED, "".into()));
+}
+
+#[test]
+fn substitute_troubles() {
+    let (status, output, errors) = rgx(&["s", "a", "$2"], TEXT);
+    assert_eq!((status, output.as_str()), (TROUBLE, ""));
+    assert!(errors.contains("No group 2"), "{errors}");
+
+    let (status, _, errors) = rgx(&["s", "a"], TEXT);
+    assert_eq!(status, TROUBLE);
+    assert!(errors.starts_with("Usage: rgx s"), "{errors}");
+
+    let dir = TempDir::new("substitute-troubles");
+    let missing = dir.path().join("missing.txt");
+    let missing = missing.to_str().unwrap();
+    let (status, output, errors) = rgx(&["s", "a", "x", missing, "-"], TEXT);
+    assert_eq!((status, output.as_str()), (TROUBLE, "xb\ncd\nxb\n"));
+    assert!(
+        errors.starts_with(&format!("rgx s: {missing}: ")),
+        "{errors}"
+    );
+}
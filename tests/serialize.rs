@@ -0,0 +1,68 @@
+// Saved trees load back, including bytes saved by older versions of the format
+
+use regexps::parser::serialize::{DecodeErrorKind, FORMAT_VERSION};
+use regexps::parser::syntax_tree::SyntaxTree;
+use regexps::parser::Parser;
+use regexps::regex::Regex;
+
+// `(?<x>a|é)+c` saved by a case insensitive regex in format version 1
+fn version_1() -> Vec<u8> {
+    let parts: [&[u8]; 9] = [
+        // Magic bytes, version 1 and flag 1 (case insensitive)
+        b"rgxp\x01\x00\x01\x00",
+        // Pattern string
+        b"\x0c\x00\x00\x00(?<x>a|\xc3\xa9)+c",
+        // How many expressions
+        b"\x06\x00\x00\x00",
+        // Concatenation of 2 children, characters 0..11
+        b"\x04\x00\x00\x00\x00\x0b\x00\x00\x00\x02\x00\x00\x00",
+        // Group `x` with +, characters 0..10
+        b"\x03\x00\x00\x00\x00\x0a\x00\x00\x00\x03\x01\x00\x00\x00x",
+        // Alternation of 2 children, characters 5..8
+        b"\x05\x05\x00\x00\x00\x08\x00\x00\x00\x02\x00\x00\x00",
+        // Characters `a`, `é` and `c`
+        b"\x02\x05\x00\x00\x00\x06\x00\x00\x00a\x00\x00\x00\x00",
+        b"\x02\x07\x00\x00\x00\x08\x00\x00\x00\xe9\x00\x00\x00\x00",
+        b"\x02\x0a\x00\x00\x00\x0b\x00\x00\x00c\x00\x00\x00\x00",
+    ];
+    parts.concat()
+}
+
+#[test]
+fn version_1_still_loads() {
+    let version_1 = version_1();
+    let regex = Regex::from_bytes(&version_1).unwrap();
+    assert_eq!(regex.as_str(), "(?<x>a|é)+c");
+    assert_eq!(regex.find("xAÉac").unwrap().range(), 1..6);
+    let tree = SyntaxTree::from_bytes(&version_1).unwrap();
+    assert_eq!(tree, *Parser::parse("(?<x>a|é)+c").unwrap());
+
+    // Saved again in the current version
+    let bytes = regex.to_bytes();
+    assert_eq!(bytes[4..6], FORMAT_VERSION.to_le_bytes());
+    assert_eq!(bytes[6..], version_1[6..]);
+}
+
+#[test]
+fn character_sets_need_version_2() {
+    let tree = Parser::parse("(a|b)+").unwrap().optimize();
+    let mut bytes = tree.to_bytes();
+    assert_eq!(SyntaxTree::from_bytes(&bytes).unwrap(), tree);
+
+    // Version 1 has no tag 6
+    bytes[4..6].copy_from_slice(&1u16.to_le_bytes());
+    let error = SyntaxTree::from_bytes(&bytes).unwrap_err();
+    assert_eq!(error.kind, DecodeErrorKind::InvalidExpression);
+}
+
+#[test]
+fn unknown_versions_fail() {
+    let bytes = Parser::parse("a").unwrap().to_bytes();
+    for version in [0, FORMAT_VERSION + 1] {
+        let mut bytes = bytes.clone();
+        bytes[4..6].copy_from_slice(&version.to_le_bytes());
+        let error = SyntaxTree::from_bytes(&bytes).unwrap_err();
+        assert_eq!(error.kind, DecodeErrorKind::UnsupportedVersion);
+        assert_eq!(error.position, 4);
+    }
+}
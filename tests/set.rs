@@ -0,0 +1,54 @@
+// A set finds the same patterns as matching each of them alone
+
+mod common;
+
+use common::Rng;
+use regexps::regex::set::RegexSet;
+use regexps::regex::Regex;
+
+const CASES: u64 = 1000;
+
+// Pieces of patterns with strings to search for, shared by some patterns
+const LITERALS: [&str; 8] = ["a", "ab", "ba", "aa", "é😀", "a|b", "(ab|b)", "\\.a"];
+
+fn check(patterns: &[String], rng: &mut Rng) {
+    let set = RegexSet::new(patterns).unwrap();
+    let regexes = patterns
+        .iter()
+        .map(|pattern| Regex::new(pattern).unwrap())
+        .collect::<Vec<_>>();
+    for _ in 0..4 {
+        let text = common::text(rng, 12);
+        let expected = (0..regexes.len())
+            .filter(|index| regexes[*index].find(&text).is_some())
+            .collect::<Vec<_>>();
+        assert_eq!(set.matches(&text), expected, "{patterns:?} on {text:?}");
+        assert_eq!(set.is_match(&text), !expected.is_empty());
+    }
+}
+
+#[test]
+fn random_patterns() {
+    for seed in 0..CASES {
+        let mut rng = Rng::new(seed);
+        let patterns = (0..1 + rng.below(5))
+            .map(|_| common::pattern(&mut rng))
+            .collect::<Vec<_>>();
+        check(&patterns, &mut rng);
+    }
+}
+
+#[test]
+fn patterns_starting_with_strings() {
+    for seed in 0..CASES {
+        let mut rng = Rng::new(seed);
+        let patterns = (0..1 + rng.below(6))
+            .map(|_| match rng.below(3) {
+                0 => format!("{}{}", rng.pick(&LITERALS), common::pattern(&mut rng)),
+                1 => format!("{}.?{}", rng.pick(&LITERALS), rng.pick(&LITERALS)),
+                _ => rng.pick(&LITERALS).to_string(),
+            })
+            .collect::<Vec<_>>();
+        check(&patterns, &mut rng);
+    }
+}